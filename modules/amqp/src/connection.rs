@@ -0,0 +1,105 @@
+use crate::consumer::{consumer, MainSetup};
+use crate::setup::Config;
+use crate::topology;
+use lapin::{Channel, Connection, ConnectionProperties};
+use phlow_sdk::prelude::*;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// How long a publish waits for the connection to come back before failing.
+const PUBLISH_WAIT: Duration = Duration::from_secs(5);
+
+/// The channel messages are published on. It is replaced on every
+/// reconnection and empty while disconnected.
+#[derive(Clone)]
+pub struct PublishChannel(watch::Receiver<Option<Channel>>);
+
+impl PublishChannel {
+    pub async fn get(&mut self) -> Result<Channel, String> {
+        let channel =
+            match tokio::time::timeout(PUBLISH_WAIT, self.0.wait_for(Option::is_some)).await {
+                Ok(Ok(channel)) => channel.clone(),
+                _ => None,
+            };
+
+        channel.ok_or_else(|| "Not connected to RabbitMQ".to_string())
+    }
+}
+
+/// Starts the supervisor of the connection, which keeps it open in the
+/// background, declaring the topology and consuming again whenever it is lost.
+/// The module is reported down while it is disconnected.
+pub fn supervise(config: Config, main: Option<MainSetup>, health: ModuleHealth) -> PublishChannel {
+    let (tx, rx) = watch::channel(None);
+
+    tokio::task::spawn(async move {
+        let mut backoff = MIN_BACKOFF;
+
+        loop {
+            let (lost_tx, mut lost_rx) = mpsc::unbounded_channel();
+
+            match connect(&config, main.clone(), lost_tx).await {
+                Ok((connection, channel)) => {
+                    info!("Connected to RabbitMQ");
+                    backoff = MIN_BACKOFF;
+                    tx.send_replace(Some(channel));
+                    health.up();
+
+                    let reason = lost_rx.recv().await.unwrap_or_default();
+                    tx.send_replace(None);
+                    health.down(format!("Connection lost: {}", reason));
+                    warn!("RabbitMQ connection lost ({}), reconnecting", reason);
+
+                    let _ = connection.close(200, "Reconnecting").await;
+                }
+                Err(e) => {
+                    error!("Failed to connect to RabbitMQ: {}", e);
+                    health.down(format!("Failed to connect: {}", e));
+                }
+            }
+
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(MAX_BACKOFF);
+        }
+    });
+
+    PublishChannel(rx)
+}
+
+async fn connect(
+    config: &Config,
+    main: Option<MainSetup>,
+    lost: mpsc::UnboundedSender<String>,
+) -> Result<(Connection, Channel), lapin::Error> {
+    let connection = Connection::connect(
+        &config.to_connection_string(),
+        ConnectionProperties::default(),
+    )
+    .await?;
+
+    let on_error = |lost: mpsc::UnboundedSender<String>| {
+        move |e: lapin::Error| {
+            let _ = lost.send(e.to_string());
+        }
+    };
+    connection.on_error(on_error(lost.clone()));
+
+    let channel = connection.create_channel().await?;
+    channel.on_error(on_error(lost.clone()));
+
+    debug!("Created channel");
+
+    topology::declare(&channel, config).await?;
+
+    if let Some(main) = main {
+        let channel = connection.create_channel().await?;
+        channel.on_error(on_error(lost));
+
+        consumer(main, config.clone(), channel).await?;
+    }
+
+    Ok((connection, channel))
+}
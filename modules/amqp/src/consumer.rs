@@ -1,28 +1,46 @@
+use crate::delivery;
+use crate::rpc;
+use crate::settle::{reply_body, settle, Settlement};
 use crate::setup::Config;
+use crate::topology::declare_queue;
 use lapin::message::DeliveryResult;
 use lapin::{options::*, types::FieldTable};
+use phlow_sdk::otel::TracePropagation;
 use phlow_sdk::prelude::*;
 use phlow_sdk::tracing::debug;
 
+/// What the consumer needs from the module setup to start the flow.
+#[derive(Clone)]
+pub struct MainSetup {
+    pub id: ModuleId,
+    pub main_sender: MainRuntimeSender,
+    pub dispatch: Dispatch,
+    pub trace_propagation: TracePropagation,
+}
+
 pub async fn consumer(
-    id: ModuleId,
-    main_sender: MainRuntimeSender,
+    main: MainSetup,
     config: Config,
     channel: lapin::Channel,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<(), lapin::Error> {
     debug!("Starting consumer");
 
+    if let Some(prefetch) = config.prefetch {
+        channel
+            .basic_qos(prefetch, BasicQosOptions::default())
+            .await?;
+    }
+
+    // Requeued copies must be confirmed before the original is acked.
     channel
-        .queue_declare(
-            &config.routing_key,
-            QueueDeclareOptions::default(),
-            FieldTable::default(),
-        )
+        .confirm_select(ConfirmSelectOptions::default())
         .await?;
 
+    declare_queue(&channel, &config.queue).await?;
+
     let consumer = channel
         .basic_consume(
-            &config.routing_key,
+            &config.queue.name,
             &config.consumer_tag,
             BasicConsumeOptions::default(),
             FieldTable::default(),
@@ -32,34 +50,73 @@ pub async fn consumer(
     consumer.set_delegate(move |delivery: DeliveryResult| {
         debug!("Received message");
 
-        let sender = main_sender.clone();
+        let MainSetup {
+            id,
+            main_sender: sender,
+            dispatch,
+            trace_propagation,
+        } = main.clone();
+        let config = config.clone();
+        let channel = channel.clone();
 
         async move {
             let delivery = match delivery {
                 Ok(Some(delivery)) => delivery,
                 Ok(None) => return,
                 Err(error) => {
-                    dbg!("Failed to consume queue message {}", error);
+                    error!("Failed to consume queue message {}", error);
                     return;
                 }
             };
 
-            let data: Value = String::from_utf8_lossy(&delivery.data)
-                .to_string()
-                .to_value();
+            let data = delivery::to_value(&delivery);
 
             debug!("Received message: {:?}", data);
 
-            let response_value = sender_package!(id, sender, Some(data))
-                .await
-                .unwrap_or(Value::Null);
+            let span = tracing::dispatcher::with_default(&dispatch, || {
+                tracing::info_span!(
+                    "amqp_message",
+                    otel.name = format!("{} process", config.queue.name),
+                    otel.kind = "consumer",
+                    messaging.system = "rabbitmq",
+                    messaging.destination.name = config.queue.name.as_str(),
+                    messaging.rabbitmq.redelivered = delivery.redelivered,
+                    messaging.message.id = field::Empty,
+                    messaging.message.conversation_id = field::Empty,
+                )
+            });
 
-            debug!("Response: {:?}", response_value);
+            if let Some(message_id) = delivery.properties.message_id() {
+                span.record("messaging.message.id", message_id.as_str());
+            }
+            if let Some(correlation_id) = delivery.properties.correlation_id() {
+                span.record("messaging.message.conversation_id", correlation_id.as_str());
+            }
+            (trace_propagation.extract)(&span, &delivery::trace_context(&delivery.properties));
 
-            delivery
-                .ack(BasicAckOptions::default())
+            let result = sender_package!(span, dispatch, id, sender, Some(data))
                 .await
-                .expect("Failed to ack send_webhook_event message");
+                .ok();
+
+            debug!("Response: {:?}", result);
+
+            let settlement = Settlement::from_result(result.as_ref()).bounded(&config, &delivery);
+
+            // A requeued message is handled again, and only its final result
+            // is the answer to the caller.
+            match &result {
+                Some(result) if settlement.is_final() => {
+                    if let Err(e) = rpc::reply(&channel, &delivery, &reply_body(result)).await {
+                        error!("Failed to reply to message: {}", e);
+                    }
+                }
+                Some(_) => debug!("Message requeued, replying once it is settled"),
+                None => warn!("Flow failed for message ({:?})", settlement),
+            }
+
+            if let Err(e) = settle(&channel, &config, &delivery, settlement).await {
+                error!("Failed to settle message ({:?}): {}", settlement, e);
+            }
         }
     });
 
@@ -0,0 +1,213 @@
+use lapin::message::Delivery;
+use lapin::types::{AMQPValue, FieldTable};
+use lapin::BasicProperties;
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+
+pub fn from_amqp_value(value: &AMQPValue) -> Value {
+    match value {
+        AMQPValue::Boolean(boolean) => boolean.to_value(),
+        AMQPValue::ShortShortInt(v) => (*v as i64).to_value(),
+        AMQPValue::ShortShortUInt(v) => (*v as i64).to_value(),
+        AMQPValue::ShortInt(v) => (*v as i64).to_value(),
+        AMQPValue::ShortUInt(v) => (*v as i64).to_value(),
+        AMQPValue::LongInt(v) => (*v as i64).to_value(),
+        AMQPValue::LongUInt(v) => (*v as i64).to_value(),
+        AMQPValue::LongLongInt(v) => v.to_value(),
+        AMQPValue::Timestamp(v) => v.to_value(),
+        AMQPValue::Float(v) => (*v as f64).to_value(),
+        AMQPValue::Double(v) => v.to_value(),
+        AMQPValue::DecimalValue(decimal) => {
+            (decimal.value as f64 / 10f64.powi(decimal.scale as i32)).to_value()
+        }
+        AMQPValue::ShortString(string) => string.as_str().to_value(),
+        AMQPValue::LongString(string) => String::from_utf8_lossy(string.as_bytes())
+            .to_string()
+            .to_value(),
+        AMQPValue::ByteArray(bytes) => String::from_utf8_lossy(bytes.as_slice())
+            .to_string()
+            .to_value(),
+        AMQPValue::FieldArray(array) => array
+            .as_slice()
+            .iter()
+            .map(from_amqp_value)
+            .collect::<Vec<_>>()
+            .to_value(),
+        AMQPValue::FieldTable(table) => from_field_table(table),
+        AMQPValue::Void => Value::Null,
+    }
+}
+
+pub fn from_field_table(table: &FieldTable) -> Value {
+    table
+        .inner()
+        .iter()
+        .map(|(key, value)| (key.to_string(), from_amqp_value(value)))
+        .collect::<HashMap<_, _>>()
+        .to_value()
+}
+
+fn is_json(content_type: &str) -> bool {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    mime == "application/json" || mime.ends_with("+json")
+}
+
+/// Parses JSON bodies by their content type. Anything else, or JSON that
+/// fails to parse, is kept as text.
+pub fn body(raw: &str, content_type: Option<&str>) -> Value {
+    match content_type {
+        Some(content_type) if is_json(content_type) => {
+            Value::json_to_value(raw).unwrap_or_else(|_| raw.to_value())
+        }
+        _ => raw.to_value(),
+    }
+}
+
+fn properties_to_value(properties: &BasicProperties) -> Value {
+    let mut map: HashMap<String, Value> = HashMap::new();
+    let mut string = |key: &str, value: Option<&str>| {
+        if let Some(value) = value {
+            map.insert(key.to_string(), value.to_value());
+        }
+    };
+
+    string(
+        "content_type",
+        properties.content_type().as_ref().map(|v| v.as_str()),
+    );
+    string(
+        "content_encoding",
+        properties.content_encoding().as_ref().map(|v| v.as_str()),
+    );
+    string(
+        "correlation_id",
+        properties.correlation_id().as_ref().map(|v| v.as_str()),
+    );
+    string(
+        "reply_to",
+        properties.reply_to().as_ref().map(|v| v.as_str()),
+    );
+    string(
+        "expiration",
+        properties.expiration().as_ref().map(|v| v.as_str()),
+    );
+    string(
+        "message_id",
+        properties.message_id().as_ref().map(|v| v.as_str()),
+    );
+    string("type", properties.kind().as_ref().map(|v| v.as_str()));
+    string("user_id", properties.user_id().as_ref().map(|v| v.as_str()));
+    string("app_id", properties.app_id().as_ref().map(|v| v.as_str()));
+
+    if let Some(priority) = properties.priority() {
+        map.insert("priority".to_string(), (*priority as i64).to_value());
+    }
+    if let Some(delivery_mode) = properties.delivery_mode() {
+        map.insert(
+            "delivery_mode".to_string(),
+            (*delivery_mode as i64).to_value(),
+        );
+    }
+    if let Some(timestamp) = properties.timestamp() {
+        map.insert("timestamp".to_string(), timestamp.to_value());
+    }
+
+    map.to_value()
+}
+
+/// The main input for a consumed message.
+pub fn to_value(delivery: &Delivery) -> Value {
+    let raw = String::from_utf8_lossy(&delivery.data).to_string();
+    let content_type = delivery.properties.content_type().as_ref();
+    let headers = delivery
+        .properties
+        .headers()
+        .as_ref()
+        .map(from_field_table)
+        .unwrap_or_else(|| HashMap::<String, Value>::new().to_value());
+
+    HashMap::from([
+        ("body", body(&raw, content_type.map(|v| v.as_str()))),
+        ("raw", raw.to_value()),
+        ("headers", headers),
+        ("routing_key", delivery.routing_key.as_str().to_value()),
+        ("exchange", delivery.exchange.as_str().to_value()),
+        ("delivery_tag", delivery.delivery_tag.to_value()),
+        ("redelivered", delivery.redelivered.to_value()),
+        ("properties", properties_to_value(&delivery.properties)),
+    ])
+    .to_value()
+}
+
+/// W3C trace context headers of the message, for the consumer span.
+pub fn trace_context(properties: &BasicProperties) -> HashMap<String, String> {
+    let headers = match properties.headers() {
+        Some(headers) => headers.inner(),
+        None => return HashMap::new(),
+    };
+
+    ["traceparent", "tracestate"]
+        .iter()
+        .filter_map(|name| match headers.get(*name)? {
+            AMQPValue::LongString(value) => Some((
+                name.to_string(),
+                String::from_utf8_lossy(value.as_bytes()).to_string(),
+            )),
+            AMQPValue::ShortString(value) => Some((name.to_string(), value.to_string())),
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lapin::types::{LongString, ShortString};
+
+    #[test]
+    fn test_body_by_content_type() {
+        let json = r#"{"id": 42}"#;
+
+        assert_eq!(
+            body(json, Some("application/json; charset=utf-8")),
+            Value::json_to_value(json).unwrap()
+        );
+        assert_eq!(
+            body(json, Some("application/vnd.order+json")),
+            Value::json_to_value(json).unwrap()
+        );
+        assert_eq!(body(json, Some("text/plain")), json.to_value());
+        assert_eq!(body(json, None), json.to_value());
+        assert_eq!(body("{", Some("application/json")), "{".to_value());
+    }
+
+    #[test]
+    fn test_headers_and_trace_context() {
+        let mut headers = FieldTable::default();
+        headers.insert(
+            ShortString::from("tenant"),
+            AMQPValue::LongString(LongString::from("acme")),
+        );
+        headers.insert(ShortString::from("attempt"), AMQPValue::LongInt(2));
+        headers.insert(
+            ShortString::from("traceparent"),
+            AMQPValue::LongString(LongString::from(
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01",
+            )),
+        );
+
+        let value = from_field_table(&headers);
+        assert_eq!(value.get("tenant"), Some(&"acme".to_value()));
+        assert_eq!(value.get("attempt"), Some(&2i64.to_value()));
+
+        let trace_context = trace_context(&BasicProperties::default().with_headers(headers));
+        assert_eq!(trace_context.len(), 1);
+        assert!(trace_context["traceparent"].starts_with("00-4bf92f"));
+    }
+}
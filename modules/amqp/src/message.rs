@@ -0,0 +1,219 @@
+use crate::setup::Config;
+use lapin::types::{AMQPValue, FieldArray, FieldTable, LongString, ShortString};
+use lapin::BasicProperties;
+use phlow_sdk::prelude::*;
+use std::time::Duration;
+
+/// Persistent delivery mode: the broker writes the message to disk.
+const PERSISTENT: u8 = 2;
+
+const DEFAULT_RPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub fn to_amqp_value(value: &Value) -> AMQPValue {
+    match value {
+        Value::Boolean(boolean) => AMQPValue::Boolean(*boolean),
+        Value::Number(number) if number.is_float() => {
+            AMQPValue::Double(number.to_f64().unwrap_or_default())
+        }
+        Value::Number(number) => AMQPValue::LongLongInt(number.to_i64().unwrap_or_default()),
+        Value::Array(array) => AMQPValue::FieldArray(FieldArray::from(
+            array.into_iter().map(to_amqp_value).collect::<Vec<_>>(),
+        )),
+        Value::Object(_) => AMQPValue::FieldTable(to_field_table(value)),
+        Value::Null | Value::Undefined => AMQPValue::Void,
+        _ => AMQPValue::LongString(LongString::from(value.to_string())),
+    }
+}
+
+pub fn to_field_table(value: &Value) -> FieldTable {
+    let mut table = FieldTable::default();
+
+    if let Value::Object(object) = value {
+        for (key, value) in object.iter() {
+            table.insert(ShortString::from(key.to_string()), to_amqp_value(value));
+        }
+    }
+
+    table
+}
+
+/// Objects and arrays are sent as JSON, strings as they are.
+pub fn payload(message: &Value) -> Vec<u8> {
+    match message {
+        Value::Object(_) | Value::Array(_) => message.to_json(JsonMode::Inline).into_bytes(),
+        Value::Null | Value::Undefined => Vec::new(),
+        _ => message.to_string().into_bytes(),
+    }
+}
+
+/// A message to publish, with the routing from `with` unless the step
+/// input overrides it.
+#[derive(Debug)]
+pub struct Publish {
+    pub exchange: String,
+    pub routing_key: String,
+    pub payload: Vec<u8>,
+    pub properties: BasicProperties,
+    /// How long to wait for the reply when publishing as a request.
+    pub rpc: Option<Duration>,
+}
+
+impl TryFrom<(&Value, &Config)> for Publish {
+    type Error = String;
+
+    fn try_from((input, config): (&Value, &Config)) -> Result<Self, Self::Error> {
+        if !input.is_object() {
+            return Err("Input must be an object".to_string());
+        }
+
+        let string = |key: &str| {
+            input
+                .get(key)
+                .filter(|v| !v.is_null())
+                .map(Value::to_string)
+        };
+        let message = input.get("message").cloned().unwrap_or(Value::Null);
+
+        let mut properties = BasicProperties::default();
+
+        let content_type = string("content_type").or_else(|| {
+            matches!(message, Value::Object(_) | Value::Array(_))
+                .then(|| "application/json".to_string())
+        });
+        if let Some(content_type) = content_type {
+            properties = properties.with_content_type(ShortString::from(content_type));
+        }
+
+        if let Some(headers) = input.get("headers").filter(|v| v.is_object()) {
+            properties = properties.with_headers(to_field_table(headers));
+        }
+
+        if let Some(correlation_id) = string("correlation_id") {
+            properties = properties.with_correlation_id(ShortString::from(correlation_id));
+        }
+
+        if let Some(reply_to) = string("reply_to") {
+            properties = properties.with_reply_to(ShortString::from(reply_to));
+        }
+
+        if let Some(priority) = input.get("priority").filter(|v| !v.is_null()) {
+            let priority = priority
+                .to_u64()
+                .filter(|priority| *priority <= u8::MAX as u64)
+                .ok_or_else(|| "priority must be an integer from 0 to 255".to_string())?;
+            properties = properties.with_priority(priority as u8);
+        }
+
+        // Expiration is a TTL in milliseconds, sent as a string.
+        if let Some(expiration) = string("expiration") {
+            if expiration.parse::<u64>().is_err() {
+                return Err("expiration must be a number of milliseconds".to_string());
+            }
+            properties = properties.with_expiration(ShortString::from(expiration));
+        }
+
+        if *input
+            .get("persistent")
+            .and_then(Value::as_bool)
+            .unwrap_or(&false)
+        {
+            properties = properties.with_delivery_mode(PERSISTENT);
+        }
+
+        let rpc = if *input.get("rpc").and_then(Value::as_bool).unwrap_or(&false) {
+            let timeout = match input.get("timeout").filter(|v| !v.is_null()) {
+                Some(timeout) => timeout
+                    .to_u64()
+                    .map(Duration::from_millis)
+                    .ok_or_else(|| "timeout must be a number of milliseconds".to_string())?,
+                None => DEFAULT_RPC_TIMEOUT,
+            };
+            Some(timeout)
+        } else {
+            None
+        };
+
+        Ok(Publish {
+            exchange: string("exchange").unwrap_or_else(|| config.exchange.clone()),
+            routing_key: string("routing_key").unwrap_or_else(|| config.routing_key.clone()),
+            payload: payload(&message),
+            properties,
+            rpc,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Config {
+        Config::try_from(&Value::json_to_value(r#"{"routing_key": "orders"}"#).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_publish_from_input() {
+        let input = Value::json_to_value(
+            r#"{
+                "message": {"id": 42},
+                "exchange": "events",
+                "headers": {"tenant": "acme", "attempt": 2, "tags": ["a"]},
+                "correlation_id": "req-1",
+                "priority": 5,
+                "expiration": 60000,
+                "persistent": true
+            }"#,
+        )
+        .unwrap();
+        let publish = Publish::try_from((&input, &config())).unwrap();
+
+        assert_eq!(publish.exchange, "events");
+        assert_eq!(publish.routing_key, "orders");
+        assert_eq!(publish.payload, br#"{"id": 42}"#.to_vec());
+
+        let properties = publish.properties;
+        assert_eq!(
+            properties.content_type().as_ref().map(|v| v.as_str()),
+            Some("application/json")
+        );
+        assert_eq!(
+            properties.correlation_id().as_ref().map(|v| v.as_str()),
+            Some("req-1")
+        );
+        assert_eq!(*properties.priority(), Some(5));
+        assert_eq!(
+            properties.expiration().as_ref().map(|v| v.as_str()),
+            Some("60000")
+        );
+        assert_eq!(*properties.delivery_mode(), Some(PERSISTENT));
+        assert_eq!(publish.rpc, None);
+
+        let headers = properties.headers().clone().unwrap();
+        assert_eq!(
+            headers.inner().get("attempt"),
+            Some(&AMQPValue::LongLongInt(2))
+        );
+        assert_eq!(
+            headers.inner().get("tenant"),
+            Some(&AMQPValue::LongString(LongString::from("acme")))
+        );
+    }
+
+    #[test]
+    fn test_publish_string_message() {
+        let input = Value::json_to_value(r#"{"message": "hello", "priority": 300}"#).unwrap();
+        assert!(Publish::try_from((&input, &config())).is_err());
+
+        let input = Value::json_to_value(r#"{"message": "hello"}"#).unwrap();
+        let publish = Publish::try_from((&input, &config())).unwrap();
+
+        assert_eq!(publish.payload, b"hello".to_vec());
+        assert_eq!(*publish.properties.content_type(), None);
+
+        let input =
+            Value::json_to_value(r#"{"message": "hello", "rpc": true, "timeout": 500}"#).unwrap();
+        let publish = Publish::try_from((&input, &config())).unwrap();
+
+        assert_eq!(publish.rpc, Some(Duration::from_millis(500)));
+    }
+}
@@ -1,14 +1,18 @@
 use lapin::options::BasicPublishOptions;
 use lapin::publisher_confirm::Confirmation;
-use lapin::BasicProperties;
 use phlow_sdk::prelude::*;
 
+use crate::connection::PublishChannel;
+use crate::message::Publish;
+use crate::rpc::Rpc;
 use crate::setup::Config;
 
 #[derive(Debug, ToValue)]
 pub struct ProducerResponse {
     pub success: bool,
     pub error_message: Option<String>,
+    /// The reply to an `rpc` request.
+    pub response: Option<Value>,
 }
 
 impl ProducerResponse {
@@ -16,6 +20,7 @@ impl ProducerResponse {
         Self {
             success: false,
             error_message: Some(error_message.to_string()),
+            response: None,
         }
     }
 }
@@ -23,7 +28,7 @@ impl ProducerResponse {
 pub async fn producer(
     setup_sender: ModuleSetupSender,
     config: Config,
-    channel: lapin::Channel,
+    mut channel: PublishChannel,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let (tx, rx) = channel::unbounded::<ModulePackage>();
     match setup_sender.send(Some(tx)) {
@@ -33,12 +38,14 @@ pub async fn producer(
         }
     };
 
+    let rpc = Rpc::default();
+
     debug!("Producer started");
 
     for package in rx {
         debug!("Received package");
 
-        let payload = {
+        let publish = {
             let input = match package.context.input {
                 Some(input) => input,
                 None => {
@@ -53,49 +60,52 @@ pub async fn producer(
                 }
             };
 
-            input.get("message").unwrap_or(&Value::Null).to_string()
+            match Publish::try_from((&input, &config)) {
+                Ok(publish) => publish,
+                Err(e) => {
+                    let response = ProducerResponse::from_error(&e);
+                    match package.sender.send(response.to_value().into()) {
+                        Ok(_) => {}
+                        Err(e) => {
+                            return Err(format!("{:?}", e).into());
+                        }
+                    }
+                    continue;
+                }
+            }
         };
 
-        let confirm = channel
-            .basic_publish(
-                &config.exchange,
-                &config.routing_key,
-                BasicPublishOptions::default(),
-                payload.as_bytes(),
-                BasicProperties::default(),
-            )
-            .await?
-            .await?;
-
-        debug!("Published message to {}", config.routing_key);
-
-        let (success, error_message) = match confirm {
-            Confirmation::NotRequested => {
-                debug!("Published message without ack");
-                (true, None)
-            }
-            Confirmation::Ack(basic_return_message) => {
-                debug!("Published message with ack: {:?}", basic_return_message);
-                (true, None)
-            }
-            Confirmation::Nack(basic_return_message) => {
-                let error_message =
-                    format!("Published message with nack: {:?}", basic_return_message);
+        // Requests wait for their reply apart, so other messages are not held up.
+        if let Some(timeout) = publish.rpc {
+            let rpc = rpc.clone();
+            let mut channel = channel.clone();
 
-                debug!(error_message);
+            tokio::task::spawn(async move {
+                let response = match channel.get().await {
+                    Ok(channel) => rpc.call(&channel, publish, timeout).await,
+                    Err(e) => Err(e),
+                };
+                let response = match response {
+                    Ok(reply) => ProducerResponse {
+                        success: true,
+                        error_message: None,
+                        response: Some(reply),
+                    },
+                    Err(e) => ProducerResponse::from_error(&e),
+                };
 
-                (false, Some(error_message))
-            }
+                sender_safe!(package.sender, response.to_value().into());
+            });
+
+            continue;
+        }
+
+        let response = match publish_message(&mut channel, publish).await {
+            Ok(response) => response,
+            Err(e) => ProducerResponse::from_error(&e),
         };
 
-        match package.sender.send(
-            ProducerResponse {
-                success,
-                error_message,
-            }
-            .to_value()
-            .into(),
-        ) {
+        match package.sender.send(response.to_value().into()) {
             Ok(_) => {}
             Err(e) => {
                 return Err(format!("{:?}", e).into());
@@ -105,3 +115,49 @@ pub async fn producer(
 
     Ok(())
 }
+
+async fn publish_message(
+    channel: &mut PublishChannel,
+    publish: Publish,
+) -> Result<ProducerResponse, String> {
+    let confirm = channel
+        .get()
+        .await?
+        .basic_publish(
+            &publish.exchange,
+            &publish.routing_key,
+            BasicPublishOptions::default(),
+            &publish.payload,
+            publish.properties,
+        )
+        .await
+        .map_err(|e| e.to_string())?
+        .await
+        .map_err(|e| e.to_string())?;
+
+    debug!("Published message to {}", publish.routing_key);
+
+    let (success, error_message) = match confirm {
+        Confirmation::NotRequested => {
+            debug!("Published message without ack");
+            (true, None)
+        }
+        Confirmation::Ack(basic_return_message) => {
+            debug!("Published message with ack: {:?}", basic_return_message);
+            (true, None)
+        }
+        Confirmation::Nack(basic_return_message) => {
+            let error_message = format!("Published message with nack: {:?}", basic_return_message);
+
+            debug!(error_message);
+
+            (false, Some(error_message))
+        }
+    };
+
+    Ok(ProducerResponse {
+        success,
+        error_message,
+        response: None,
+    })
+}
@@ -0,0 +1,196 @@
+use crate::delivery;
+use crate::message::{payload, Publish};
+use lapin::message::{Delivery, DeliveryResult};
+use lapin::options::{BasicConsumeOptions, BasicPublishOptions, QueueDeclareOptions};
+use lapin::types::{FieldTable, ShortString};
+use lapin::{BasicProperties, Channel};
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>;
+
+/// Client side of request/reply: replies arrive on an exclusive callback queue
+/// and are matched to their request by correlation id.
+#[derive(Clone, Default)]
+pub struct Rpc {
+    pending: Pending,
+    /// The callback queue and the channel consuming it. The queue goes away
+    /// with its connection, so it is declared again after reconnecting.
+    callback: Arc<tokio::sync::Mutex<Option<(Channel, String)>>>,
+}
+
+impl Rpc {
+    async fn callback_queue(&self, channel: &Channel) -> Result<String, lapin::Error> {
+        let mut callback = self.callback.lock().await;
+
+        if let Some((callback_channel, queue)) = callback.as_ref() {
+            if callback_channel.status().connected() {
+                return Ok(queue.clone());
+            }
+        }
+
+        let queue = channel
+            .queue_declare(
+                "",
+                QueueDeclareOptions {
+                    exclusive: true,
+                    auto_delete: true,
+                    ..QueueDeclareOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?
+            .name()
+            .to_string();
+
+        let consumer = channel
+            .basic_consume(
+                &queue,
+                "",
+                BasicConsumeOptions {
+                    no_ack: true,
+                    ..BasicConsumeOptions::default()
+                },
+                FieldTable::default(),
+            )
+            .await?;
+
+        let pending = self.pending.clone();
+        consumer.set_delegate(move |delivery: DeliveryResult| {
+            let pending = pending.clone();
+
+            async move {
+                if let Ok(Some(delivery)) = delivery {
+                    resolve(&pending, &delivery);
+                }
+            }
+        });
+
+        debug!("Declared callback queue {}", queue);
+        *callback = Some((channel.clone(), queue.clone()));
+
+        Ok(queue)
+    }
+
+    /// Publishes `publish` with a callback queue to reply to, and waits up to
+    /// `timeout` for the reply.
+    pub async fn call(
+        &self,
+        channel: &Channel,
+        mut publish: Publish,
+        timeout: Duration,
+    ) -> Result<Value, String> {
+        let queue = self
+            .callback_queue(channel)
+            .await
+            .map_err(|e| format!("Failed to declare callback queue: {}", e))?;
+
+        let correlation_id = match publish.properties.correlation_id() {
+            Some(correlation_id) => correlation_id.to_string(),
+            None => uuid::Uuid::new_v4().to_string(),
+        };
+
+        publish.properties = publish
+            .properties
+            .with_reply_to(ShortString::from(queue))
+            .with_correlation_id(ShortString::from(correlation_id.clone()));
+
+        let (tx, rx) = oneshot::channel();
+        self.insert(&correlation_id, tx);
+
+        let published = channel
+            .basic_publish(
+                &publish.exchange,
+                &publish.routing_key,
+                BasicPublishOptions::default(),
+                &publish.payload,
+                publish.properties,
+            )
+            .await;
+
+        if let Err(e) = published {
+            self.remove(&correlation_id);
+            return Err(e.to_string());
+        }
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err("Reply channel closed".to_string()),
+            Err(_) => {
+                self.remove(&correlation_id);
+                Err(format!("No reply within {} ms", timeout.as_millis()))
+            }
+        }
+    }
+
+    fn insert(&self, correlation_id: &str, tx: oneshot::Sender<Value>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(correlation_id.to_string(), tx);
+        }
+    }
+
+    fn remove(&self, correlation_id: &str) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(correlation_id);
+        }
+    }
+}
+
+fn resolve(pending: &Pending, delivery: &Delivery) {
+    let correlation_id = match delivery.properties.correlation_id() {
+        Some(correlation_id) => correlation_id.to_string(),
+        None => {
+            warn!("Discarding reply without correlation_id");
+            return;
+        }
+    };
+
+    let tx = pending
+        .lock()
+        .ok()
+        .and_then(|mut pending| pending.remove(&correlation_id));
+
+    match tx {
+        Some(tx) => {
+            let _ = tx.send(delivery::to_value(delivery));
+        }
+        None => debug!("Discarding reply to unknown request {}", correlation_id),
+    }
+}
+
+/// Server side of request/reply: publishes the flow result to the `reply_to`
+/// queue of the request, with the same correlation id.
+pub async fn reply(channel: &Channel, request: &Delivery, result: &Value) -> Result<(), String> {
+    let reply_to = match request.properties.reply_to() {
+        Some(reply_to) => reply_to.as_str(),
+        None => return Ok(()),
+    };
+
+    let mut properties = BasicProperties::default();
+
+    if matches!(result, Value::Object(_) | Value::Array(_)) {
+        properties = properties.with_content_type(ShortString::from("application/json"));
+    }
+
+    if let Some(correlation_id) = request.properties.correlation_id() {
+        properties = properties.with_correlation_id(correlation_id.clone());
+    }
+
+    channel
+        .basic_publish(
+            "",
+            reply_to,
+            BasicPublishOptions::default(),
+            &payload(result),
+            properties,
+        )
+        .await
+        .map_err(|e| e.to_string())?;
+
+    debug!("Replied to {}", reply_to);
+
+    Ok(())
+}
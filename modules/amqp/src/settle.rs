@@ -0,0 +1,250 @@
+use crate::setup::Config;
+use lapin::message::Delivery;
+use lapin::options::{BasicAckOptions, BasicNackOptions, BasicPublishOptions, BasicRejectOptions};
+use lapin::publisher_confirm::Confirmation;
+use lapin::types::{AMQPValue, ShortString};
+use lapin::BasicProperties;
+use phlow_sdk::prelude::*;
+
+/// Header counting how many times the consumer requeued a message.
+pub const REDELIVERIES_HEADER: &str = "x-phlow-redeliveries";
+
+/// What to do with a delivery once the flow has handled it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Settlement {
+    Ack,
+    /// Requeue the message to be delivered again.
+    Nack,
+    /// Drop the message, or dead-letter it when the queue has a dead-letter exchange.
+    Reject,
+}
+
+/// Key of the flow result that settles the message, namespaced so that results
+/// with an `action` of their own are not mistaken for a settlement.
+pub const SETTLEMENT_KEY: &str = "amqp_ack";
+
+impl Settlement {
+    /// Reads `amqp_ack` from the flow result. Results without one are acked,
+    /// and a flow that failed (no result) is nacked. Unknown values are acked
+    /// with a warning, since requeueing them would redeliver the message for a
+    /// mistake in the flow.
+    pub fn from_result(result: Option<&Value>) -> Self {
+        let result = match result {
+            Some(result) => result,
+            None => return Settlement::Nack,
+        };
+
+        let action = match result {
+            Value::Object(_) => result.get(SETTLEMENT_KEY).map(Value::to_string),
+            _ => None,
+        };
+
+        match action.as_deref() {
+            None | Some("ack") => Settlement::Ack,
+            Some("nack") | Some("requeue") => Settlement::Nack,
+            Some("reject") => Settlement::Reject,
+            Some(action) => {
+                warn!(
+                    "Invalid {}: {}, acking the message. Use 'ack', 'nack' or 'reject'.",
+                    SETTLEMENT_KEY, action
+                );
+                Settlement::Ack
+            }
+        }
+    }
+
+    /// Turns a requeue into a rejection once the message has been redelivered
+    /// `max_redeliveries` times, so the settlement returned is the one applied.
+    pub fn bounded(self, config: &Config, delivery: &Delivery) -> Self {
+        match self {
+            Settlement::Nack if redeliveries(&delivery.properties) >= config.max_redeliveries => {
+                warn!(
+                    "Message redelivered {} times, rejecting it",
+                    config.max_redeliveries
+                );
+                Settlement::Reject
+            }
+            settlement => settlement,
+        }
+    }
+
+    /// Whether the message is done with, so the flow result is its answer.
+    pub fn is_final(self) -> bool {
+        matches!(self, Settlement::Ack | Settlement::Reject)
+    }
+}
+
+/// The flow result without the settlement key, as sent back to rpc callers.
+pub fn reply_body(result: &Value) -> Value {
+    let mut body = result.clone();
+
+    if let Value::Object(_) = body {
+        body.remove(&SETTLEMENT_KEY);
+    }
+
+    body
+}
+
+fn as_u64(value: &AMQPValue) -> Option<u64> {
+    match value {
+        AMQPValue::ShortShortInt(v) => u64::try_from(*v).ok(),
+        AMQPValue::ShortShortUInt(v) => Some(*v as u64),
+        AMQPValue::ShortInt(v) => u64::try_from(*v).ok(),
+        AMQPValue::ShortUInt(v) => Some(*v as u64),
+        AMQPValue::LongInt(v) => u64::try_from(*v).ok(),
+        AMQPValue::LongUInt(v) => Some(*v as u64),
+        AMQPValue::LongLongInt(v) => u64::try_from(*v).ok(),
+        _ => None,
+    }
+}
+
+/// Times the message has been delivered again, from our header or the
+/// `x-delivery-count` quorum queues set.
+pub fn redeliveries(properties: &BasicProperties) -> u64 {
+    let headers = match properties.headers() {
+        Some(headers) => headers.inner(),
+        None => return 0,
+    };
+
+    [REDELIVERIES_HEADER, "x-delivery-count"]
+        .iter()
+        .filter_map(|header| headers.get(*header).and_then(as_u64))
+        .max()
+        .unwrap_or(0)
+}
+
+/// Requeues a message with its redelivery count incremented. The copy goes
+/// straight to the queue through the default exchange, and the original is
+/// acked only once the broker has confirmed the copy. A copy that is refused,
+/// unroutable or unconfirmed falls back to a plain requeue of the original,
+/// so the message is never lost, at the cost of not counting that redelivery.
+async fn requeue(channel: &lapin::Channel, queue: &str, delivery: &Delivery) -> Result<(), String> {
+    let mut headers = delivery.properties.headers().clone().unwrap_or_default();
+    headers.insert(
+        ShortString::from(REDELIVERIES_HEADER),
+        AMQPValue::LongLongInt(redeliveries(&delivery.properties) as i64 + 1),
+    );
+
+    let published = match channel
+        .basic_publish(
+            "",
+            queue,
+            BasicPublishOptions {
+                mandatory: true,
+                ..BasicPublishOptions::default()
+            },
+            &delivery.data,
+            delivery.properties.clone().with_headers(headers),
+        )
+        .await
+    {
+        Ok(confirm) => confirm.await.map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+
+    // A returned message was unroutable, even though the broker acked it.
+    match published {
+        Ok(Confirmation::Ack(None)) => {
+            return delivery
+                .ack(BasicAckOptions::default())
+                .await
+                .map_err(|e| e.to_string())
+        }
+        Ok(confirmation) => warn!("Requeued copy not confirmed: {:?}", confirmation),
+        Err(e) => warn!("Failed to requeue copy: {}", e),
+    }
+
+    delivery
+        .nack(BasicNackOptions {
+            requeue: true,
+            ..BasicNackOptions::default()
+        })
+        .await
+        .map_err(|e| e.to_string())
+}
+
+pub async fn settle(
+    channel: &lapin::Channel,
+    config: &Config,
+    delivery: &Delivery,
+    settlement: Settlement,
+) -> Result<(), String> {
+    match settlement {
+        Settlement::Ack => delivery
+            .ack(BasicAckOptions::default())
+            .await
+            .map_err(|e| e.to_string()),
+        // Counting redeliveries needs a header, which a plain nack cannot set.
+        Settlement::Nack => requeue(channel, &config.queue.name, delivery).await,
+        Settlement::Reject => delivery
+            .reject(BasicRejectOptions { requeue: false })
+            .await
+            .map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lapin::types::FieldTable;
+
+    #[test]
+    fn test_settlement_from_result() {
+        let result = |json: &str| Value::json_to_value(json).unwrap();
+
+        assert_eq!(Settlement::from_result(None), Settlement::Nack);
+        assert_eq!(
+            Settlement::from_result(Some(&result(r#"{"status": "done"}"#))),
+            Settlement::Ack
+        );
+        assert_eq!(
+            Settlement::from_result(Some(&result(r#"{"amqp_ack": "reject"}"#))),
+            Settlement::Reject
+        );
+        assert_eq!(
+            Settlement::from_result(Some(&result(r#"{"amqp_ack": "requeue"}"#))),
+            Settlement::Nack
+        );
+        assert_eq!(
+            Settlement::from_result(Some(&"ok".to_value())),
+            Settlement::Ack
+        );
+
+        // The flow's own keys are left alone, and unknown values are acked.
+        assert_eq!(
+            Settlement::from_result(Some(&result(r#"{"action": "created"}"#))),
+            Settlement::Ack
+        );
+        assert_eq!(
+            Settlement::from_result(Some(&result(r#"{"amqp_ack": "retry"}"#))),
+            Settlement::Ack
+        );
+    }
+
+    #[test]
+    fn test_reply_body() {
+        let result = Value::json_to_value(r#"{"amqp_ack": "reject", "error": "invalid"}"#).unwrap();
+        let body = reply_body(&result);
+
+        assert_eq!(body.get(SETTLEMENT_KEY), None);
+        assert_eq!(body.get("error"), Some(&"invalid".to_value()));
+        assert_eq!(reply_body(&"ok".to_value()), "ok".to_value());
+    }
+
+    #[test]
+    fn test_redeliveries() {
+        let mut headers = FieldTable::default();
+        assert_eq!(redeliveries(&BasicProperties::default()), 0);
+
+        headers.insert(
+            ShortString::from(REDELIVERIES_HEADER),
+            AMQPValue::LongLongInt(2),
+        );
+        headers.insert(ShortString::from("x-delivery-count"), AMQPValue::LongInt(3));
+
+        assert_eq!(
+            redeliveries(&BasicProperties::default().with_headers(headers)),
+            3
+        );
+    }
+}
@@ -1,19 +1,148 @@
+use crate::message::to_field_table;
+use lapin::types::{AMQPValue, FieldTable, LongString, ShortString};
+use lapin::ExchangeKind;
 use phlow_sdk::prelude::*;
 use std::fmt::Display;
 
+/// Failed messages are rejected after this many requeues unless
+/// `max_redeliveries` says otherwise, so a poison message cannot loop forever.
+const DEFAULT_MAX_REDELIVERIES: u64 = 5;
+
 #[derive(Debug)]
 pub enum Error {
     RoutingKey,
+    ExchangeType(String),
+    QueueType(String),
+    QueueName,
+    DeadLetterRoutingKey,
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::RoutingKey => write!(f, "routing_key is required"),
+            Self::ExchangeType(kind) => write!(
+                f,
+                "Invalid exchange_type: {}. Use 'direct', 'topic', 'fanout' or 'headers'.",
+                kind
+            ),
+            Self::QueueType(kind) => write!(
+                f,
+                "Invalid queue_type: {}. Use 'classic' or 'quorum'.",
+                kind
+            ),
+            Self::QueueName => write!(f, "every entry of queues needs a name"),
+            Self::DeadLetterRoutingKey => write!(
+                f,
+                "dead_letter_routing_key is required with dead_letter_queue"
+            ),
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum QueueType {
+    Classic,
+    Quorum,
+}
+
+#[derive(Clone, Debug)]
+pub struct QueueConfig {
+    pub name: String,
+    pub durable: bool,
+    pub queue_type: QueueType,
+    /// Milliseconds a message may wait in the queue (`x-message-ttl`).
+    pub message_ttl: Option<u64>,
+    /// Messages the queue holds before dropping or dead-lettering the oldest (`x-max-length`).
+    pub max_length: Option<u64>,
+    pub dead_letter_exchange: Option<String>,
+    pub dead_letter_routing_key: Option<String>,
+    /// Any other `x-` arguments, as given.
+    pub arguments: FieldTable,
+}
+
+impl QueueConfig {
+    /// Reads the options of the queue `name` from `value`, which is durable
+    /// when `durable` unless it says otherwise.
+    fn from_value(value: &Value, name: String, durable: bool) -> Result<Self, Error> {
+        let optional = |key: &str| value.get(key).map(|v| v.to_string());
+
+        let queue_type = match optional("queue_type").as_deref() {
+            None | Some("classic") => QueueType::Classic,
+            Some("quorum") => QueueType::Quorum,
+            Some(kind) => return Err(Error::QueueType(kind.to_string())),
+        };
+
+        Ok(Self {
+            name,
+            // Quorum queues are always durable.
+            durable: queue_type == QueueType::Quorum
+                || value
+                    .get("durable")
+                    .and_then(|v| v.as_bool().copied())
+                    .unwrap_or(durable),
+            queue_type,
+            message_ttl: value.get("message_ttl").and_then(|v| v.to_u64()),
+            max_length: value.get("max_length").and_then(|v| v.to_u64()),
+            dead_letter_exchange: optional("dead_letter_exchange"),
+            dead_letter_routing_key: optional("dead_letter_routing_key"),
+            arguments: value
+                .get("arguments")
+                .map(to_field_table)
+                .unwrap_or_default(),
+        })
+    }
+
+    /// Arguments the queue is declared with. They must be the same wherever
+    /// the queue is declared, or the broker refuses the declaration.
+    pub fn arguments(&self) -> FieldTable {
+        let mut arguments = self.arguments.clone();
+        let mut insert = |key: &str, value: AMQPValue| {
+            arguments.insert(ShortString::from(key), value);
+        };
+
+        if self.queue_type == QueueType::Quorum {
+            insert(
+                "x-queue-type",
+                AMQPValue::LongString(LongString::from("quorum")),
+            );
+        }
+
+        if let Some(ttl) = self.message_ttl {
+            insert("x-message-ttl", AMQPValue::LongLongInt(ttl as i64));
+        }
+
+        if let Some(max_length) = self.max_length {
+            insert("x-max-length", AMQPValue::LongLongInt(max_length as i64));
+        }
+
+        if let Some(exchange) = &self.dead_letter_exchange {
+            insert(
+                "x-dead-letter-exchange",
+                AMQPValue::LongString(LongString::from(exchange.as_str())),
+            );
+
+            if let Some(routing_key) = &self.dead_letter_routing_key {
+                insert(
+                    "x-dead-letter-routing-key",
+                    AMQPValue::LongString(LongString::from(routing_key.as_str())),
+                );
+            }
+        }
+
+        arguments
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Binding {
+    pub queue: String,
+    pub exchange: String,
+    pub routing_key: String,
+    /// Matching arguments, such as `x-match` for headers exchanges.
+    pub arguments: FieldTable,
+}
+
 #[derive(Clone, Debug)]
 pub struct Config {
     pub host: String,
@@ -24,6 +153,19 @@ pub struct Config {
     pub exchange: String,
     pub consumer_tag: String,
     pub declare: bool,
+    pub exchange_type: ExchangeKind,
+    pub durable: bool,
+    /// The queue consumed from, named after the routing key unless `queue` is set.
+    pub queue: QueueConfig,
+    /// Other queues to declare.
+    pub queues: Vec<QueueConfig>,
+    pub bindings: Vec<Binding>,
+    /// Unacknowledged messages the consumer may hold at once (`basic_qos`).
+    pub prefetch: Option<u16>,
+    /// Times a message is requeued after failing before it is rejected.
+    pub max_redeliveries: u64,
+    /// Queue declared and bound to the dead-letter exchange of `queue`.
+    pub dead_letter_queue: Option<String>,
 }
 
 impl Config {
@@ -76,6 +218,83 @@ impl TryFrom<&Value> for Config {
             .map(|v| v.as_bool().unwrap_or(&false))
             .unwrap_or(&false);
 
+        let optional = |key: &str| value.get(key).map(|v| v.to_string());
+
+        let exchange_type = match optional("exchange_type").as_deref() {
+            None | Some("direct") => ExchangeKind::Direct,
+            Some("topic") => ExchangeKind::Topic,
+            Some("fanout") => ExchangeKind::Fanout,
+            Some("headers") => ExchangeKind::Headers,
+            Some(kind) => return Err(Error::ExchangeType(kind.to_string())),
+        };
+
+        let durable = *value
+            .get("durable")
+            .map(|v| v.as_bool().unwrap_or(&false))
+            .unwrap_or(&false);
+
+        let queue = QueueConfig::from_value(
+            value,
+            optional("queue").unwrap_or_else(|| routing_key.clone()),
+            durable,
+        )?;
+
+        let queues = match value.get("queues") {
+            Some(Value::Array(queues)) => queues
+                .into_iter()
+                .map(|queue| {
+                    let name = queue
+                        .get("name")
+                        .map(|v| v.to_string())
+                        .ok_or(Error::QueueName)?;
+                    QueueConfig::from_value(queue, name, durable)
+                })
+                .collect::<Result<Vec<_>, _>>()?,
+            _ => Vec::new(),
+        };
+
+        let bindings = match value.get("bindings") {
+            Some(Value::Array(bindings)) => bindings
+                .into_iter()
+                .map(|binding| Binding {
+                    queue: binding
+                        .get("queue")
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| queue.name.clone()),
+                    exchange: binding
+                        .get("exchange")
+                        .map(|v| v.to_string())
+                        .unwrap_or_else(|| exchange.clone()),
+                    routing_key: binding
+                        .get("routing_key")
+                        .map(|v| v.to_string())
+                        .unwrap_or_default(),
+                    arguments: binding
+                        .get("arguments")
+                        .map(to_field_table)
+                        .unwrap_or_default(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+
+        let dead_letter_queue = optional("dead_letter_queue");
+
+        // The dead-letter queue is bound with this key on a direct exchange, so
+        // without it messages dead-lettered under other keys, such as those
+        // matched by topic patterns, would be dropped.
+        if dead_letter_queue.is_some()
+            && queue.dead_letter_exchange.is_some()
+            && queue.dead_letter_routing_key.is_none()
+        {
+            return Err(Error::DeadLetterRoutingKey);
+        }
+
+        let prefetch = value
+            .get("prefetch")
+            .and_then(|v| v.to_u64())
+            .map(|prefetch| prefetch.min(u16::MAX as u64) as u16);
+
         Ok(Self {
             host,
             username,
@@ -85,6 +304,103 @@ impl TryFrom<&Value> for Config {
             exchange,
             consumer_tag,
             declare,
+            exchange_type,
+            durable,
+            queue,
+            queues,
+            bindings,
+            prefetch,
+            max_redeliveries: value
+                .get("max_redeliveries")
+                .and_then(|v| v.to_u64())
+                .unwrap_or(DEFAULT_MAX_REDELIVERIES),
+            dead_letter_queue,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_topology_config() {
+        let value = Value::json_to_value(
+            r##"{
+                "routing_key": "orders.*",
+                "exchange": "events",
+                "exchange_type": "topic",
+                "queue": "orders",
+                "queue_type": "quorum",
+                "message_ttl": 60000,
+                "dead_letter_exchange": "orders.dlx",
+                "queues": [{"name": "audit", "max_length": 1000}],
+                "bindings": [
+                    {"routing_key": "orders.created"},
+                    {"queue": "audit", "routing_key": "#"}
+                ]
+            }"##,
+        )
+        .unwrap();
+        let config = Config::try_from(&value).unwrap();
+
+        assert_eq!(config.exchange_type, ExchangeKind::Topic);
+        assert_eq!(config.queue.name, "orders");
+        assert!(config.queue.durable);
+
+        let arguments = config.queue.arguments();
+        let arguments = arguments.inner();
+        assert_eq!(
+            arguments.get("x-queue-type"),
+            Some(&AMQPValue::LongString(LongString::from("quorum")))
+        );
+        assert_eq!(
+            arguments.get("x-message-ttl"),
+            Some(&AMQPValue::LongLongInt(60000))
+        );
+        assert!(arguments.contains_key("x-dead-letter-exchange"));
+
+        assert_eq!(config.queues[0].name, "audit");
+        assert!(!config.queues[0].durable);
+        assert_eq!(
+            config.queues[0].arguments().inner().get("x-max-length"),
+            Some(&AMQPValue::LongLongInt(1000))
+        );
+
+        assert_eq!(config.bindings[0].queue, "orders");
+        assert_eq!(config.bindings[0].exchange, "events");
+        assert_eq!(config.bindings[1].queue, "audit");
+        assert_eq!(config.max_redeliveries, DEFAULT_MAX_REDELIVERIES);
+    }
+
+    #[test]
+    fn test_invalid_exchange_type() {
+        let value =
+            Value::json_to_value(r#"{"routing_key": "orders", "exchange_type": "fast"}"#).unwrap();
+        assert!(matches!(
+            Config::try_from(&value),
+            Err(Error::ExchangeType(_))
+        ));
+    }
+
+    #[test]
+    fn test_dead_letter_queue_needs_routing_key() {
+        let value = Value::json_to_value(
+            r#"{
+                "routing_key": "orders.*",
+                "dead_letter_exchange": "orders.dlx",
+                "dead_letter_queue": "orders.dead"
+            }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            Config::try_from(&value),
+            Err(Error::DeadLetterRoutingKey)
+        ));
+
+        let mut value = value;
+        value.insert("dead_letter_routing_key", "orders.dead");
+        let config = Config::try_from(&value).unwrap();
+        assert_eq!(config.dead_letter_queue.as_deref(), Some("orders.dead"));
+    }
+}
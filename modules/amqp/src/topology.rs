@@ -0,0 +1,130 @@
+use crate::setup::{Config, QueueConfig};
+use lapin::options::{ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions};
+use lapin::types::FieldTable;
+use lapin::{Channel, ExchangeKind};
+use phlow_sdk::prelude::*;
+
+pub async fn declare_queue(channel: &Channel, queue: &QueueConfig) -> Result<(), lapin::Error> {
+    channel
+        .queue_declare(
+            &queue.name,
+            QueueDeclareOptions {
+                durable: queue.durable,
+                ..QueueDeclareOptions::default()
+            },
+            queue.arguments(),
+        )
+        .await?;
+
+    Ok(())
+}
+
+async fn declare_exchange(
+    channel: &Channel,
+    name: &str,
+    kind: ExchangeKind,
+    durable: bool,
+) -> Result<(), lapin::Error> {
+    channel
+        .exchange_declare(
+            name,
+            kind,
+            ExchangeDeclareOptions {
+                durable,
+                ..ExchangeDeclareOptions::default()
+            },
+            FieldTable::default(),
+        )
+        .await
+}
+
+/// Declares the exchanges, queues and bindings of `config`. It runs again on
+/// every reconnection, so everything declared here must be idempotent.
+pub async fn declare(channel: &Channel, config: &Config) -> Result<(), lapin::Error> {
+    // Without its exchange, dead-lettered messages would be silently dropped.
+    if let Some(exchange) = &config.queue.dead_letter_exchange {
+        declare_exchange(channel, exchange, ExchangeKind::Direct, config.durable).await?;
+
+        // Config requires a dead_letter_routing_key along with the queue, so
+        // every dead-lettered message carries the key it is bound with.
+        if let (Some(queue), Some(routing_key)) = (
+            &config.dead_letter_queue,
+            &config.queue.dead_letter_routing_key,
+        ) {
+            channel
+                .queue_declare(
+                    queue,
+                    QueueDeclareOptions {
+                        durable: config.durable,
+                        ..QueueDeclareOptions::default()
+                    },
+                    FieldTable::default(),
+                )
+                .await?;
+            channel
+                .queue_bind(
+                    queue,
+                    exchange,
+                    routing_key,
+                    QueueBindOptions::default(),
+                    FieldTable::default(),
+                )
+                .await?;
+        }
+
+        debug!("Declared dead-letter exchange");
+    }
+
+    if !config.declare {
+        return Ok(());
+    }
+
+    if !config.exchange.is_empty() {
+        declare_exchange(
+            channel,
+            &config.exchange,
+            config.exchange_type.clone(),
+            config.durable,
+        )
+        .await?;
+
+        debug!("Declared exchange");
+    }
+
+    declare_queue(channel, &config.queue).await?;
+
+    for queue in &config.queues {
+        declare_queue(channel, queue).await?;
+    }
+
+    debug!("Declared queues");
+
+    // The default exchange routes to every queue by name and takes no bindings.
+    if config.bindings.is_empty() && !config.exchange.is_empty() {
+        channel
+            .queue_bind(
+                &config.queue.name,
+                &config.exchange,
+                &config.routing_key,
+                QueueBindOptions::default(),
+                FieldTable::default(),
+            )
+            .await?;
+    }
+
+    for binding in &config.bindings {
+        channel
+            .queue_bind(
+                &binding.queue,
+                &binding.exchange,
+                &binding.routing_key,
+                QueueBindOptions::default(),
+                binding.arguments.clone(),
+            )
+            .await?;
+    }
+
+    debug!("Declared bindings");
+
+    Ok(())
+}
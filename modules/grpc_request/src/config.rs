@@ -0,0 +1,115 @@
+use phlow_grpc::strings;
+use phlow_sdk::prelude::*;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// Default endpoint, such as `http://localhost:50051`. `https` enables TLS.
+    pub url: Option<String>,
+    /// `.proto` files describing the services called.
+    pub protos: Vec<String>,
+    /// Directories imports are resolved from, besides the one of each proto.
+    pub includes: Vec<String>,
+    /// A compiled `FileDescriptorSet`, as written by `protoc --descriptor_set_out`.
+    pub descriptor_set: Option<String>,
+    /// Deadline of each call in seconds.
+    pub timeout: u64,
+    pub tls: TlsConfig,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TlsConfig {
+    pub ca_cert: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    /// Name checked against the server certificate instead of the url host.
+    pub domain: Option<String>,
+}
+
+impl TlsConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == TlsConfig::default()
+    }
+}
+
+impl From<&Value> for TlsConfig {
+    fn from(value: &Value) -> Self {
+        let string = |key: &str| value.get(key).map(|v| v.to_string());
+
+        Self {
+            ca_cert: string("ca_cert"),
+            client_cert: string("client_cert"),
+            client_key: string("client_key"),
+            domain: string("domain"),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Config {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if !value.is_object() {
+            return Err("protos or descriptor_set is required".to_string());
+        }
+
+        let protos = strings(value, "protos");
+        let descriptor_set = value.get("descriptor_set").map(|v| v.to_string());
+
+        if protos.is_empty() && descriptor_set.is_none() {
+            return Err("protos or descriptor_set is required".to_string());
+        }
+
+        let tls = value
+            .get("tls")
+            .filter(|v| v.is_object())
+            .map(TlsConfig::from)
+            .unwrap_or_default();
+
+        if tls.client_cert.is_some() != tls.client_key.is_some() {
+            return Err("client_cert and client_key must be set together".to_string());
+        }
+
+        Ok(Self {
+            url: value.get("url").map(|v| v.to_string()),
+            protos,
+            includes: strings(value, "includes"),
+            descriptor_set,
+            timeout: value.get("timeout").and_then(|v| v.to_u64()).unwrap_or(29),
+            tls,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let config = Config::try_from(
+            &Value::json_to_value(
+                r#"{
+                    "url": "https://users.internal:443",
+                    "protos": "protos/users.proto",
+                    "timeout": 5,
+                    "tls": {"ca_cert": "ca.pem", "domain": "users"}
+                }"#,
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(config.url.as_deref(), Some("https://users.internal:443"));
+        assert_eq!(config.protos, vec!["protos/users.proto".to_string()]);
+        assert_eq!(config.timeout, 5);
+        assert_eq!(config.tls.ca_cert.as_deref(), Some("ca.pem"));
+        assert_eq!(config.tls.domain.as_deref(), Some("users"));
+
+        assert!(Config::try_from(&Value::json_to_value(r#"{"url": "x"}"#).unwrap()).is_err());
+        assert!(Config::try_from(
+            &Value::json_to_value(r#"{"protos": ["a.proto"], "tls": {"client_cert": "c.pem"}}"#)
+                .unwrap()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,86 @@
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Input {
+    /// Overrides the url of the module.
+    pub url: Option<String>,
+    /// Full name of the service, such as `users.Users`.
+    pub service: String,
+    pub method: String,
+    pub message: Value,
+    pub metadata: HashMap<String, String>,
+    /// Overrides the timeout of the module, in seconds.
+    pub timeout: Option<u64>,
+}
+
+impl Input {
+    pub fn new(value: Value) -> Self {
+        let string = |key: &str| {
+            value
+                .get(key)
+                .filter(|v| !v.is_null())
+                .map(|v| v.to_string())
+        };
+
+        // A full `/users.Users/Get` path in `method` names the service too.
+        let mut service = string("service").unwrap_or_default();
+        let mut method = string("method").unwrap_or_default();
+
+        if let Some((path_service, path_method)) = method.trim_start_matches('/').split_once('/') {
+            service = path_service.to_string();
+            method = path_method.to_string();
+        }
+
+        let metadata = match value.get("metadata") {
+            Some(Value::Object(metadata)) => metadata
+                .iter()
+                .map(|(key, value)| (key.to_string().to_lowercase(), value.to_string()))
+                .collect(),
+            _ => HashMap::new(),
+        };
+
+        Self {
+            url: string("url"),
+            service,
+            method,
+            message: value.get("message").cloned().unwrap_or(Value::Null),
+            metadata,
+            timeout: value.get("timeout").and_then(|v| v.to_u64()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input() {
+        let input = Input::new(
+            Value::json_to_value(
+                r#"{
+                    "service": "users.Users",
+                    "method": "Get",
+                    "message": {"id": 1},
+                    "metadata": {"Authorization": "Bearer x"}
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        assert_eq!(input.service, "users.Users");
+        assert_eq!(input.method, "Get");
+        assert_eq!(input.message, Value::json_to_value(r#"{"id": 1}"#).unwrap());
+        assert_eq!(
+            input.metadata.get("authorization").map(String::as_str),
+            Some("Bearer x")
+        );
+        assert_eq!(input.url, None);
+
+        let input = Input::new(Value::json_to_value(r#"{"method": "/users.Users/List"}"#).unwrap());
+        assert_eq!(input.service, "users.Users");
+        assert_eq!(input.method, "List");
+        assert_eq!(input.message, Value::Null);
+    }
+}
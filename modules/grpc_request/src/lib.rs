@@ -0,0 +1,113 @@
+mod config;
+mod input;
+mod request;
+use config::Config;
+use input::Input;
+use phlow_grpc::descriptor;
+use phlow_sdk::otel::TracePropagation;
+use phlow_sdk::prelude::*;
+use phlow_sdk::tracing::Instrument;
+use request::Client;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+create_step!(grpc_request(setup));
+
+pub async fn grpc_request(
+    setup: ModuleSetup,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let config = Config::try_from(&setup.with).map_err(|e| format!("{:?}", e))?;
+    let pool = descriptor::load(
+        config.descriptor_set.as_deref(),
+        &config.protos,
+        &config.includes,
+    )?;
+
+    let rx = module_channel!(setup);
+
+    for service in pool.services() {
+        debug!("Calling {}", service.full_name());
+    }
+
+    let client = Arc::new(Client::new(config, pool));
+    let trace_propagation = setup.trace_propagation;
+
+    listen!(rx, resolve, client, trace_propagation);
+
+    Ok(())
+}
+
+pub async fn resolve(
+    package: ModulePackage,
+    client: Arc<Client>,
+    trace_propagation: TracePropagation,
+) {
+    let response = match package.input() {
+        Some(value) => execute(&package, Input::new(value), &client, trace_propagation).await,
+        _ => failure("No input provided".to_string()),
+    }
+    .to_value();
+
+    sender_safe!(package.sender, response.into());
+}
+
+fn failure(message: String) -> HashMap<&'static str, Value> {
+    HashMap::from([
+        ("response", Value::Undefined),
+        ("is_success", false.to_value()),
+        ("is_error", true.to_value()),
+        ("message", message.to_value()),
+    ])
+}
+
+async fn execute(
+    package: &ModulePackage,
+    input: Input,
+    client: &Client,
+    trace_propagation: TracePropagation,
+) -> HashMap<&'static str, Value> {
+    let url = match input.url.clone().or_else(|| client.config.url.clone()) {
+        Some(url) => url,
+        None => return failure("No url provided".to_string()),
+    };
+
+    let method = match client.method(&input.service, &input.method) {
+        Ok(method) => method,
+        Err(e) => return failure(e),
+    };
+
+    let channel = match client.channel(&url) {
+        Ok(channel) => channel,
+        Err(e) => return failure(e),
+    };
+
+    let timeout = Duration::from_secs(input.timeout.unwrap_or(client.config.timeout));
+    let span = request::client_span(package, &url, &method);
+
+    match request::request(channel, &method, input, timeout, &span, trace_propagation)
+        .instrument(span.clone())
+        .await
+    {
+        Ok(result) => {
+            let status_code = result
+                .get("status_code")
+                .and_then(|code| code.to_i64())
+                .unwrap_or_default();
+
+            span.record("rpc.grpc.status_code", status_code);
+            if status_code != 0 {
+                span.record("error.type", status_code.to_string());
+                span.record("otel.status_code", "ERROR");
+            }
+
+            result
+        }
+        Err(e) => {
+            tracing::error!("Error: {}", e);
+            span.record("otel.status_code", "ERROR");
+
+            failure(e)
+        }
+    }
+}
@@ -0,0 +1,427 @@
+use crate::config::{Config, TlsConfig};
+use crate::input::Input;
+use phlow_grpc::codec::{self, DynamicCodec};
+use phlow_sdk::otel::TracePropagation;
+use phlow_sdk::prelude::*;
+use phlow_sdk::tracing::{dispatcher, Span};
+use prost_reflect::{DescriptorPool, MethodDescriptor};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::metadata::{AsciiMetadataKey, MetadataMap};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use tonic::{Code, Request, Status};
+
+fn read_file(path: &str) -> Result<Vec<u8>, String> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))
+}
+
+fn tls_config(tls: &TlsConfig) -> Result<ClientTlsConfig, String> {
+    let mut config = ClientTlsConfig::new().with_native_roots();
+
+    if let Some(ca_cert) = &tls.ca_cert {
+        config = config.ca_certificate(Certificate::from_pem(read_file(ca_cert)?));
+    }
+
+    if let (Some(cert), Some(key)) = (&tls.client_cert, &tls.client_key) {
+        config = config.identity(Identity::from_pem(read_file(cert)?, read_file(key)?));
+    }
+
+    if let Some(domain) = &tls.domain {
+        config = config.domain_name(domain);
+    }
+
+    Ok(config)
+}
+
+/// The descriptors of the callable services and a channel per url, each
+/// connected on its first call and reused afterwards.
+pub struct Client {
+    pub config: Config,
+    pub pool: DescriptorPool,
+    channels: Mutex<HashMap<String, Channel>>,
+}
+
+impl Client {
+    pub fn new(config: Config, pool: DescriptorPool) -> Self {
+        Self {
+            config,
+            pool,
+            channels: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn method(&self, service: &str, method: &str) -> Result<MethodDescriptor, String> {
+        self.pool
+            .get_service_by_name(service)
+            .ok_or_else(|| format!("Unknown service: {}", service))?
+            .methods()
+            .find(|candidate| candidate.name() == method)
+            .ok_or_else(|| format!("Unknown method: {}/{}", service, method))
+    }
+
+    pub fn channel(&self, url: &str) -> Result<Channel, String> {
+        let mut channels = self.channels.lock().unwrap_or_else(|e| e.into_inner());
+
+        if let Some(channel) = channels.get(url) {
+            return Ok(channel.clone());
+        }
+
+        let mut endpoint = Endpoint::from_shared(url.to_string())
+            .map_err(|e| format!("Invalid url {}: {}", url, e))?;
+
+        if url.starts_with("https://") {
+            endpoint = endpoint
+                .tls_config(tls_config(&self.config.tls)?)
+                .map_err(|e| format!("Invalid TLS config: {}", e))?;
+        }
+
+        let channel = endpoint.connect_lazy();
+        channels.insert(url.to_string(), channel.clone());
+
+        Ok(channel)
+    }
+}
+
+pub fn client_span(package: &ModulePackage, url: &str, method: &MethodDescriptor) -> Span {
+    let dispatch = package
+        .dispatch
+        .clone()
+        .unwrap_or_else(|| dispatcher::get_default(|dispatch| dispatch.clone()));
+    let parent = package.span.clone().unwrap_or_else(Span::none);
+    let service = method.parent_service().full_name();
+
+    dispatcher::with_default(&dispatch, || {
+        tracing::info_span!(
+            parent: &parent,
+            "grpc_client",
+            otel.name = format!("{}/{}", service, method.name()),
+            otel.kind = "client",
+            otel.status_code = field::Empty,
+            rpc.system = "grpc",
+            rpc.service = service,
+            rpc.method = method.name(),
+            rpc.grpc.status_code = field::Empty,
+            url.full = url,
+            error.type = field::Empty,
+        )
+    })
+}
+
+/// Metadata as an object, without the status carried in the trailers.
+fn metadata_value(metadata: &MetadataMap) -> Value {
+    let map: HashMap<String, String> = metadata
+        .clone()
+        .into_headers()
+        .iter()
+        .filter(|(key, _)| !matches!(key.as_str(), "grpc-status" | "grpc-message"))
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect();
+
+    map.to_value()
+}
+
+/// The messages sent: each item of an array for client streaming methods,
+/// the message itself otherwise.
+fn messages(method: &MethodDescriptor, message: &Value) -> Vec<Value> {
+    match (method.is_client_streaming(), message) {
+        (true, Value::Array(items)) => items.values.clone(),
+        (true, Value::Null) => Vec::new(),
+        _ => vec![message.clone()],
+    }
+}
+
+pub async fn request(
+    channel: Channel,
+    method: &MethodDescriptor,
+    input: Input,
+    timeout: Duration,
+    span: &Span,
+    trace_propagation: TracePropagation,
+) -> Result<HashMap<&'static str, Value>, String> {
+    let mut sent = Vec::new();
+    for message in messages(method, &input.message) {
+        sent.push(codec::from_value(method.input(), &message)?);
+    }
+
+    let mut request = Request::new(tokio_stream::iter(sent));
+    request.set_timeout(timeout);
+
+    let injected = (trace_propagation.inject)(span);
+    for (key, value) in input.metadata.iter().chain(injected.iter()) {
+        let name = AsciiMetadataKey::from_bytes(key.as_bytes())
+            .map_err(|_| format!("Invalid metadata key: {}", key))?;
+        let value = value
+            .parse()
+            .map_err(|_| format!("Invalid metadata value for {}", key))?;
+        request.metadata_mut().insert(name, value);
+    }
+
+    let path = PathAndQuery::try_from(format!(
+        "/{}/{}",
+        method.parent_service().full_name(),
+        method.name()
+    ))
+    .map_err(|e| format!("Invalid method path: {}", e))?;
+
+    let mut received = Vec::new();
+    let mut headers = MetadataMap::new();
+    let mut trailers = MetadataMap::new();
+
+    let mut grpc = tonic::client::Grpc::new(channel);
+    let started = Instant::now();
+
+    let status = match grpc.ready().await {
+        Err(e) => Status::unavailable(format!("Service was not ready: {}", e)),
+        Ok(_) => match grpc
+            .streaming(request, path, DynamicCodec::new(method.output()))
+            .await
+        {
+            Err(status) => status,
+            Ok(response) => {
+                headers = response.metadata().clone();
+                let mut stream = response.into_inner();
+
+                let mut status = Status::new(Code::Ok, "");
+                loop {
+                    match stream.message().await {
+                        Ok(Some(message)) => received.push(codec::to_value(&message)),
+                        Ok(None) => break,
+                        Err(error) => {
+                            status = error;
+                            break;
+                        }
+                    }
+                }
+
+                if status.code() == Code::Ok {
+                    match stream.trailers().await {
+                        Ok(Some(metadata)) => trailers = metadata,
+                        Ok(None) => {}
+                        Err(error) => status = error,
+                    }
+                }
+
+                status
+            }
+        },
+    };
+
+    // tonic cancels a call whose deadline passed on our side, which gRPC
+    // reports as DEADLINE_EXCEEDED like a deadline enforced by the server.
+    let status = match status.code() == Code::Cancelled && started.elapsed() >= timeout {
+        true => Status::deadline_exceeded(format!("Deadline of {:?} exceeded", timeout)),
+        false => status,
+    };
+
+    // A failed call carries its trailers in the status.
+    if status.code() != Code::Ok && trailers.is_empty() {
+        trailers = status.metadata().clone();
+    }
+
+    let response = match method.is_server_streaming() {
+        true => received.to_value(),
+        false => received.into_iter().next().unwrap_or(Value::Null),
+    };
+
+    let code = status.code() as i32;
+    let is_success = status.code() == Code::Ok;
+
+    Ok(HashMap::from([
+        ("response", response),
+        ("status_code", code.to_value()),
+        ("status", phlow_grpc::code_name(status.code()).to_value()),
+        ("message", status.message().to_value()),
+        ("headers", metadata_value(&headers)),
+        ("trailers", metadata_value(&trailers)),
+        ("is_success", is_success.to_value()),
+        ("is_error", (!is_success).to_value()),
+    ]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phlow_grpc::testing;
+    use prost_reflect::DynamicMessage;
+    use tonic::codegen::BoxFuture;
+    use tonic::server::{Grpc, ServerStreamingService, UnaryService};
+    use tonic::Response;
+
+    /// `Get` answers with a user, NOT_FOUND with a trailer for id 0, and only
+    /// after a second for id -1; `List` streams `count` users.
+    #[derive(Clone)]
+    struct Users(MethodDescriptor);
+
+    fn user(method: &MethodDescriptor, id: i64) -> DynamicMessage {
+        let user = Value::json_to_value(&format!(r#"{{"id": {}, "name": "user {}"}}"#, id, id));
+        codec::from_value(method.output(), &user.unwrap()).unwrap()
+    }
+
+    fn field(request: &Request<DynamicMessage>, name: &str) -> i64 {
+        codec::to_value(request.get_ref())
+            .get(name)
+            .and_then(Value::to_i64)
+            .unwrap_or(0)
+    }
+
+    impl UnaryService<DynamicMessage> for Users {
+        type Response = DynamicMessage;
+        type Future = BoxFuture<Response<Self::Response>, Status>;
+
+        fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+            let method = self.0.clone();
+
+            Box::pin(async move {
+                match field(&request, "id") {
+                    0 => {
+                        let mut trailers = MetadataMap::new();
+                        trailers.insert("x-reason", "deleted".parse().unwrap());
+                        Err(Status::with_metadata(Code::NotFound, "No user", trailers))
+                    }
+                    -1 => {
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        Ok(Response::new(user(&method, -1)))
+                    }
+                    id => {
+                        let mut response = Response::new(user(&method, id));
+                        response
+                            .metadata_mut()
+                            .insert("x-server", "users".parse().unwrap());
+                        Ok(response)
+                    }
+                }
+            })
+        }
+    }
+
+    impl ServerStreamingService<DynamicMessage> for Users {
+        type Response = DynamicMessage;
+        type ResponseStream =
+            tokio_stream::Iter<std::vec::IntoIter<Result<DynamicMessage, Status>>>;
+        type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+        fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+            let method = self.0.clone();
+            let count = field(&request, "count");
+
+            Box::pin(async move {
+                let users: Vec<_> = (1..=count).map(|id| Ok(user(&method, id))).collect();
+                Ok(Response::new(tokio_stream::iter(users)))
+            })
+        }
+    }
+
+    async fn call(
+        client: &Client,
+        url: &str,
+        method: &str,
+        message: &str,
+        timeout: Duration,
+    ) -> HashMap<&'static str, Value> {
+        let method = client.method("users.Users", method).unwrap();
+        let input = Input::new(
+            Value::json_to_value(&format!(
+                r#"{{"service": "users.Users", "method": "{}", "message": {}}}"#,
+                method.name(),
+                message
+            ))
+            .unwrap(),
+        );
+
+        request(
+            client.channel(url).unwrap(),
+            &method,
+            input,
+            timeout,
+            &Span::none(),
+            TracePropagation::default(),
+        )
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request() {
+        let pool = testing::users();
+        let url = testing::spawn(move |http_request: phlow_grpc::server::HttpRequest| {
+            let pool = pool.clone();
+
+            async move {
+                let path = http_request.uri().path().to_string();
+                let service = pool.get_service_by_name("users.Users").unwrap();
+                let method = service
+                    .methods()
+                    .find(|method| path == format!("/users.Users/{}", method.name()))
+                    .unwrap();
+
+                let mut grpc = Grpc::new(DynamicCodec::new(method.input()));
+                let users = Users(method.clone());
+
+                Ok(match method.is_server_streaming() {
+                    true => grpc.server_streaming(users, http_request).await,
+                    false => grpc.unary(users, http_request).await,
+                })
+            }
+        })
+        .await;
+
+        let config = Value::json_to_value(r#"{"protos": ["users.proto"]}"#).unwrap();
+        let client = Client::new(Config::try_from(&config).unwrap(), testing::users());
+        let timeout = Duration::from_secs(5);
+
+        let result = call(&client, &url, "Get", r#"{"id": 7}"#, timeout).await;
+        assert_eq!(
+            result["response"],
+            Value::json_to_value(r#"{"id": 7, "name": "user 7"}"#).unwrap()
+        );
+        assert_eq!(result["status"], "OK".to_value());
+        assert_eq!(result["is_success"], true.to_value());
+        assert_eq!(result["headers"].get("x-server"), Some(&"users".to_value()));
+
+        // The status of a failed call is returned, with its trailers.
+        let result = call(&client, &url, "Get", r#"{"id": 0}"#, timeout).await;
+        assert_eq!(result["response"], Value::Null);
+        assert_eq!(result["status"], "NOT_FOUND".to_value());
+        assert_eq!(result["status_code"].to_u64(), Some(5));
+        assert_eq!(result["message"], "No user".to_value());
+        assert_eq!(result["is_error"], true.to_value());
+        assert_eq!(
+            result["trailers"].get("x-reason"),
+            Some(&"deleted".to_value())
+        );
+
+        let result = call(&client, &url, "List", r#"{"count": 3}"#, timeout).await;
+        assert_eq!(result["status"], "OK".to_value());
+        assert_eq!(
+            result["response"].get(2).and_then(|user| user.get("name")),
+            Some(&"user 3".to_value())
+        );
+
+        // The deadline cuts a slow call short.
+        let result = call(
+            &client,
+            &url,
+            "Get",
+            r#"{"id": -1}"#,
+            Duration::from_millis(100),
+        )
+        .await;
+        assert_eq!(result["status"], "DEADLINE_EXCEEDED".to_value());
+        assert_eq!(result["is_error"], true.to_value());
+    }
+
+    #[test]
+    fn test_metadata_value() {
+        let mut metadata = MetadataMap::new();
+        metadata.insert("x-request-id", "42".parse().unwrap());
+        metadata.insert("grpc-status", "5".parse().unwrap());
+        metadata.insert("grpc-message", "No user".parse().unwrap());
+
+        assert_eq!(
+            metadata_value(&metadata),
+            Value::json_to_value(r#"{"x-request-id": "42"}"#).unwrap()
+        );
+    }
+}
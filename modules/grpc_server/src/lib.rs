@@ -0,0 +1,86 @@
+mod service;
+mod setup;
+use phlow_grpc::descriptor;
+use phlow_sdk::{prelude::*, tokio::net::TcpListener};
+use service::{route, MainSetup, Server};
+use setup::Config;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+create_main!(start_server(setup));
+
+pub async fn start_server(
+    setup: ModuleSetup,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !setup.is_main() {
+        debug!("This module is not the main module, exiting");
+        match setup.setup_sender.send(None) {
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!("{:?}", e).into());
+            }
+        };
+        return Ok(());
+    }
+
+    let config = Config::try_from(&setup.with).map_err(|e| format!("{:?}", e))?;
+    let pool = descriptor::load(
+        config.descriptor_set.as_deref(),
+        &config.protos,
+        &config.includes,
+    )?;
+
+    let mut routes = HashMap::new();
+
+    if config.reflection {
+        let descriptors = pool.encode_to_vec();
+
+        let v1 = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(&descriptors)
+            .build_v1()?;
+        let v1alpha = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(&descriptors)
+            .build_v1alpha()?;
+
+        routes.insert("grpc.reflection.v1.ServerReflection".to_string(), route(v1));
+        routes.insert(
+            "grpc.reflection.v1alpha.ServerReflection".to_string(),
+            route(v1alpha),
+        );
+    }
+
+    for service in pool.services() {
+        debug!("Serving {}", service.full_name());
+    }
+
+    let main_sender = match setup.main_sender.clone() {
+        Some(sender) => sender,
+        None => {
+            return Err("Main sender is None".into());
+        }
+    };
+
+    let server = Arc::new(Server {
+        main: MainSetup {
+            id: setup.id,
+            main_sender,
+            dispatch: setup.dispatch.clone(),
+            trace_propagation: setup.trace_propagation,
+        },
+        pool,
+        routes,
+    });
+
+    let addr: SocketAddr = format!("{}:{}", config.host, config.port).parse()?;
+    let listener = TcpListener::bind(addr).await?;
+
+    info!("gRPC server listening on {}", listener.local_addr()?);
+
+    // Nothing to offer as a step, the runtime only waits for the setup.
+    sender_safe!(setup.setup_sender, None);
+
+    phlow_grpc::server::serve(listener, move |request| server.clone().handle(request)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,388 @@
+use phlow_grpc::codec::{self, DynamicCodec};
+use phlow_grpc::server::HttpRequest;
+use phlow_sdk::otel::TracePropagation;
+use phlow_sdk::prelude::*;
+use prost_reflect::{DescriptorPool, DynamicMessage, MethodDescriptor};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::sync::Arc;
+use tonic::body::Body;
+use tonic::codegen::{BoxFuture, Service};
+use tonic::server::{Grpc, ServerStreamingService, UnaryService};
+use tonic::{Code, Request, Response, Status};
+
+/// What the server needs from the module setup to start the flow.
+#[derive(Clone)]
+pub struct MainSetup {
+    pub id: ModuleId,
+    pub main_sender: MainRuntimeSender,
+    pub dispatch: Dispatch,
+    pub trace_propagation: TracePropagation,
+}
+
+/// A service served as is, such as reflection.
+pub type Route =
+    Arc<dyn Fn(HttpRequest) -> BoxFuture<http::Response<Body>, Infallible> + Send + Sync>;
+
+pub fn route<S>(service: S) -> Route
+where
+    S: Service<HttpRequest, Response = http::Response<Body>, Error = Infallible>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    S::Future: Send + 'static,
+{
+    Arc::new(move |request| {
+        let mut service = service.clone();
+        Box::pin(async move { service.call(request).await })
+    })
+}
+
+pub struct Server {
+    pub main: MainSetup,
+    pub pool: DescriptorPool,
+    /// Services by full name that bypass the flow.
+    pub routes: HashMap<String, Route>,
+}
+
+impl Server {
+    /// Finds the method of a `/package.Service/Method` path.
+    fn method(&self, path: &str) -> Option<MethodDescriptor> {
+        let (service, method) = path.trim_start_matches('/').split_once('/')?;
+
+        self.pool
+            .get_service_by_name(service)?
+            .methods()
+            .find(|candidate| candidate.name() == method)
+    }
+
+    pub async fn handle(
+        self: Arc<Self>,
+        request: HttpRequest,
+    ) -> Result<http::Response<Body>, Infallible> {
+        let path = request.uri().path().to_string();
+
+        if let Some(route) = path
+            .trim_start_matches('/')
+            .split_once('/')
+            .and_then(|(service, _)| self.routes.get(service))
+        {
+            return route(request).await;
+        }
+
+        let method = match self.method(&path) {
+            Some(method) => method,
+            None => {
+                debug!("Unknown method {}", path);
+                return Ok(Status::unimplemented(format!("Unknown method {}", path)).into_http());
+            }
+        };
+
+        if method.is_client_streaming() {
+            return Ok(Status::unimplemented(format!(
+                "Client streaming is not supported: {}",
+                method.full_name()
+            ))
+            .into_http());
+        }
+
+        let mut grpc = Grpc::new(DynamicCodec::new(method.input()));
+        let flow = Flow {
+            main: self.main.clone(),
+            method: method.clone(),
+        };
+
+        let response = match method.is_server_streaming() {
+            true => grpc.server_streaming(flow, request).await,
+            false => grpc.unary(flow, request).await,
+        };
+
+        Ok(response)
+    }
+}
+
+/// Runs the flow for one call of `method`.
+#[derive(Clone)]
+struct Flow {
+    main: MainSetup,
+    method: MethodDescriptor,
+}
+
+fn metadata(request: &Request<DynamicMessage>) -> HashMap<String, String> {
+    request
+        .metadata()
+        .clone()
+        .into_headers()
+        .iter()
+        .filter_map(|(key, value)| Some((key.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// A status set by the flow with `grpc_status`, by number or name, and an
+/// optional `grpc_message`.
+fn status(result: &Value) -> Option<Status> {
+    if !result.is_object() {
+        return None;
+    }
+
+    let code = match result.get("grpc_status")? {
+        Value::Number(number) => Code::from_i32(number.to_i64().unwrap_or(2) as i32),
+        name => phlow_grpc::code_from_name(&name.to_string()),
+    };
+
+    let message = result
+        .get("grpc_message")
+        .map(|message| message.to_string())
+        .unwrap_or_default();
+
+    Some(Status::new(code, message))
+}
+
+impl Flow {
+    async fn run(self, request: Request<DynamicMessage>) -> Result<Value, Status> {
+        let MainSetup {
+            id,
+            main_sender: sender,
+            dispatch,
+            trace_propagation,
+        } = self.main;
+
+        let service = self.method.parent_service().full_name().to_string();
+        let method = self.method.name().to_string();
+        let metadata = metadata(&request);
+
+        let data: HashMap<String, Value> = HashMap::from([
+            ("service".to_string(), service.to_value()),
+            ("method".to_string(), method.to_value()),
+            ("metadata".to_string(), metadata.to_value()),
+            ("message".to_string(), codec::to_value(request.get_ref())),
+        ]);
+
+        let span = tracing::dispatcher::with_default(&dispatch, || {
+            tracing::info_span!(
+                "grpc_request",
+                otel.name = format!("{}/{}", service, method),
+                otel.kind = "server",
+                rpc.system = "grpc",
+                rpc.service = service.as_str(),
+                rpc.method = method.as_str(),
+                rpc.grpc.status_code = field::Empty,
+            )
+        });
+
+        (trace_propagation.extract)(&span, &metadata);
+
+        let result = sender_package!(span.clone(), dispatch, id, sender, Some(data.to_value()))
+            .await
+            .map_err(|_| Status::internal("The flow failed"));
+
+        let code = match &result {
+            Ok(result) => status(result)
+                .map(|status| status.code())
+                .unwrap_or(Code::Ok),
+            Err(status) => status.code(),
+        };
+        span.record("rpc.grpc.status_code", code as i32);
+
+        let result = result?;
+
+        match status(&result) {
+            Some(status) if status.code() != Code::Ok => Err(status),
+            _ => Ok(result),
+        }
+    }
+}
+
+impl UnaryService<DynamicMessage> for Flow {
+    type Response = DynamicMessage;
+    type Future = BoxFuture<Response<Self::Response>, Status>;
+
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let flow = self.clone();
+
+        Box::pin(async move {
+            let output = flow.method.output();
+            let result = flow.run(request).await?;
+
+            codec::from_value(output, &result)
+                .map(Response::new)
+                .map_err(Status::internal)
+        })
+    }
+}
+
+type MessageStream = tokio_stream::Iter<std::vec::IntoIter<Result<DynamicMessage, Status>>>;
+
+impl ServerStreamingService<DynamicMessage> for Flow {
+    type Response = DynamicMessage;
+    type ResponseStream = MessageStream;
+    type Future = BoxFuture<Response<Self::ResponseStream>, Status>;
+
+    /// Each item of an array returned by the flow is a message of the stream.
+    fn call(&mut self, request: Request<DynamicMessage>) -> Self::Future {
+        let flow = self.clone();
+
+        Box::pin(async move {
+            let output = flow.method.output();
+            let result = flow.run(request).await?;
+
+            let items = match result {
+                Value::Array(items) => items.values,
+                Value::Null => Vec::new(),
+                item => vec![item],
+            };
+
+            // Every item is converted before the stream starts, so a bad one
+            // fails the call instead of cutting the stream short.
+            let mut messages = Vec::new();
+            for item in items.iter() {
+                let message = codec::from_value(output.clone(), item).map_err(Status::internal)?;
+                messages.push(Ok(message));
+            }
+
+            Ok(Response::new(tokio_stream::iter(messages)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use phlow_grpc::testing;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::transport::Channel;
+
+    /// A flow answering `Get` with a user, or NOT_FOUND for id 0, and `List`
+    /// with `count` users.
+    fn flow(receiver: channel::Receiver<Package>) {
+        for package in receiver.iter() {
+            let data = package.request_data.clone().unwrap();
+            let message = data.get("message").cloned().unwrap();
+            let id = message.get("id").and_then(Value::to_i64).unwrap_or(0);
+            let user = |id: i64| {
+                HashMap::from([
+                    ("id", id.to_value()),
+                    ("name", format!("user {}", id).to_value()),
+                ])
+                .to_value()
+            };
+
+            let result = match data.get("method").map(Value::to_string).as_deref() {
+                Some("Get") if id == 0 => Value::json_to_value(
+                    r#"{"grpc_status": "NOT_FOUND", "grpc_message": "No user"}"#,
+                )
+                .unwrap(),
+                Some("Get") => user(id),
+                _ => {
+                    let count = message.get("count").and_then(Value::to_i64).unwrap_or(0);
+                    (1..=count).map(user).collect::<Vec<_>>().to_value()
+                }
+            };
+
+            package.response.unwrap().send(result).unwrap();
+        }
+    }
+
+    async fn call(
+        channel: Channel,
+        path: &'static str,
+        method: &MethodDescriptor,
+        message: &str,
+    ) -> Result<Vec<Value>, Status> {
+        let message =
+            codec::from_value(method.input(), &Value::json_to_value(message).unwrap()).unwrap();
+        let mut grpc = tonic::client::Grpc::new(channel);
+        grpc.ready().await.unwrap();
+
+        let mut stream = grpc
+            .server_streaming(
+                Request::new(message),
+                PathAndQuery::from_static(path),
+                DynamicCodec::new(method.output()),
+            )
+            .await?
+            .into_inner();
+
+        let mut messages = Vec::new();
+        while let Some(message) = stream.message().await? {
+            messages.push(codec::to_value(&message));
+        }
+
+        Ok(messages)
+    }
+
+    #[tokio::test]
+    async fn test_handle() {
+        let (sender, receiver) = channel::unbounded::<Package>();
+        std::thread::spawn(move || flow(receiver));
+
+        let pool = testing::users();
+        let server = Arc::new(Server {
+            main: MainSetup {
+                id: 0,
+                main_sender: sender,
+                dispatch: Dispatch::none(),
+                trace_propagation: TracePropagation::default(),
+            },
+            pool: pool.clone(),
+            routes: HashMap::new(),
+        });
+
+        let url = testing::spawn(move |request| server.clone().handle(request)).await;
+        let channel = Channel::from_shared(url).unwrap().connect().await.unwrap();
+        let service = pool.get_service_by_name("users.Users").unwrap();
+        let get = service.methods().find(|m| m.name() == "Get").unwrap();
+        let list = service.methods().find(|m| m.name() == "List").unwrap();
+
+        // A unary reply is the flow result.
+        let users = call(channel.clone(), "/users.Users/Get", &get, r#"{"id": 7}"#)
+            .await
+            .unwrap();
+        assert_eq!(
+            users,
+            vec![Value::json_to_value(r#"{"id": 7, "name": "user 7"}"#).unwrap()]
+        );
+
+        let status = call(channel.clone(), "/users.Users/Get", &get, r#"{"id": 0}"#)
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(status.message(), "No user");
+
+        // Each item of an array result is a message of the stream.
+        let users = call(
+            channel.clone(),
+            "/users.Users/List",
+            &list,
+            r#"{"count": 3}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(users.len(), 3);
+        assert_eq!(users[2].get("name"), Some(&"user 3".to_value()));
+
+        for path in ["/users.Users/Delete", "/users.Accounts/Get"] {
+            let status = call(channel.clone(), path, &get, r#"{"id": 1}"#)
+                .await
+                .unwrap_err();
+            assert_eq!(status.code(), Code::Unimplemented, "{}", path);
+        }
+    }
+
+    #[test]
+    fn test_status() {
+        let result =
+            Value::json_to_value(r#"{"grpc_status": "not_found", "grpc_message": "No user"}"#)
+                .unwrap();
+        let not_found = status(&result).unwrap();
+        assert_eq!(not_found.code(), Code::NotFound);
+        assert_eq!(not_found.message(), "No user");
+
+        let result = Value::json_to_value(r#"{"grpc_status": 7}"#).unwrap();
+        assert_eq!(status(&result).unwrap().code(), Code::PermissionDenied);
+
+        assert!(status(&Value::json_to_value(r#"{"name": "x"}"#).unwrap()).is_none());
+        assert!(status(&Value::json_to_value(r#"[1, 2]"#).unwrap()).is_none());
+    }
+}
@@ -0,0 +1,53 @@
+use phlow_grpc::strings;
+use phlow_sdk::prelude::*;
+
+#[derive(Clone, Debug)]
+pub struct Config {
+    pub host: String,
+    pub port: u16,
+    /// `.proto` files to serve.
+    pub protos: Vec<String>,
+    /// Directories imports are resolved from, besides the one of each proto.
+    pub includes: Vec<String>,
+    /// A compiled `FileDescriptorSet`, as written by `protoc --descriptor_set_out`.
+    pub descriptor_set: Option<String>,
+    pub reflection: bool,
+}
+
+impl TryFrom<&Value> for Config {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let protos = strings(value, "protos");
+        let descriptor_set = value.get("descriptor_set").map(|v| v.to_string());
+
+        if protos.is_empty() && descriptor_set.is_none() {
+            return Err("protos or descriptor_set is required".to_string());
+        }
+
+        let host = value
+            .get("host")
+            .map(|v| v.to_string())
+            .unwrap_or("0.0.0.0".to_string());
+
+        let port = value
+            .get("port")
+            .and_then(|v| v.to_u64())
+            .map(|port| port as u16)
+            .unwrap_or(50051);
+
+        let reflection = *value
+            .get("reflection")
+            .map(|v| v.as_bool().unwrap_or(&true))
+            .unwrap_or(&true);
+
+        Ok(Self {
+            host,
+            port,
+            protos,
+            includes: strings(value, "includes"),
+            descriptor_set,
+            reflection,
+        })
+    }
+}
@@ -0,0 +1,256 @@
+use phlow_sdk::metrics::PrometheusMetrics;
+use phlow_sdk::opentelemetry::metrics::{Counter, UpDownCounter};
+use phlow_sdk::opentelemetry::KeyValue;
+use phlow_sdk::prelude::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CircuitBreakerConfig {
+    pub failure_threshold: u32,
+    pub half_open_after: Duration,
+}
+
+impl From<&Value> for CircuitBreakerConfig {
+    fn from(value: &Value) -> Self {
+        let number =
+            |key: &str, default: u64| value.get(key).and_then(|v| v.to_u64()).unwrap_or(default);
+
+        Self {
+            failure_threshold: number("failure_threshold", 5).max(1) as u32,
+            half_open_after: Duration::from_secs(number("half_open_after", 30)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// The breaker instruments in the shared Prometheus registry, served on the
+/// http_server `/metrics` endpoint.
+#[derive(Debug, Clone)]
+pub struct BreakerMetrics {
+    open: UpDownCounter<i64>,
+    rejected: Counter<u64>,
+}
+
+impl BreakerMetrics {
+    pub fn new(exporter: &PrometheusMetrics) -> Self {
+        let meter = exporter.meter("http_request");
+
+        Self {
+            open: meter
+                .i64_up_down_counter("http_request.circuit_breaker.open")
+                .with_description("Whether the circuit breaker of each upstream is open")
+                .build(),
+            rejected: meter
+                .u64_counter("http_request.circuit_breaker.rejected")
+                .with_description("Calls rejected by an open circuit breaker")
+                .build(),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    failures: u32,
+    opened_at: Option<Instant>,
+    probing: bool,
+}
+
+/// Stops calling an upstream after `failure_threshold` consecutive failures
+/// (connection errors or 5xx). Once `half_open_after` has passed, a single probe
+/// request is let through: success closes the circuit, failure opens it again.
+///
+/// Transitions are reported as the `http_request.circuit_breaker.open` up-down
+/// counter and rejected calls as `http_request.circuit_breaker.rejected`, both
+/// through tracing (OTLP) and the shared Prometheus registry.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    upstream: String,
+    config: CircuitBreakerConfig,
+    state: Mutex<BreakerState>,
+    metrics: Option<BreakerMetrics>,
+}
+
+impl CircuitBreaker {
+    pub fn new(
+        upstream: &str,
+        config: CircuitBreakerConfig,
+        metrics: Option<BreakerMetrics>,
+    ) -> Self {
+        Self {
+            upstream: upstream.to_string(),
+            config,
+            state: Mutex::new(BreakerState::default()),
+            metrics,
+        }
+    }
+
+    fn attributes(&self) -> [KeyValue; 1] {
+        [KeyValue::new("upstream", self.upstream.clone())]
+    }
+
+    /// Whether a request may be sent now.
+    pub fn acquire(&self) -> bool {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        let allowed = match state.opened_at {
+            None => true,
+            Some(opened_at)
+                if opened_at.elapsed() >= self.config.half_open_after && !state.probing =>
+            {
+                state.probing = true;
+                true
+            }
+            Some(_) => false,
+        };
+
+        if !allowed {
+            tracing::info!(
+                monotonic_counter.http_request.circuit_breaker.rejected = 1_u64,
+                upstream = self.upstream.as_str(),
+            );
+
+            if let Some(metrics) = &self.metrics {
+                metrics.rejected.add(1, &self.attributes());
+            }
+        }
+
+        allowed
+    }
+
+    pub fn record(&self, success: bool) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        if success {
+            if state.opened_at.is_some() {
+                self.report_transition(CircuitState::Closed, -1);
+            }
+            *state = BreakerState::default();
+            return;
+        }
+
+        state.probing = false;
+
+        match state.opened_at {
+            Some(_) => state.opened_at = Some(Instant::now()),
+            None => {
+                state.failures += 1;
+                if state.failures >= self.config.failure_threshold {
+                    state.opened_at = Some(Instant::now());
+                    self.report_transition(CircuitState::Open, 1);
+                }
+            }
+        }
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+
+        match state.opened_at {
+            None => CircuitState::Closed,
+            Some(opened_at) if opened_at.elapsed() >= self.config.half_open_after => {
+                CircuitState::HalfOpen
+            }
+            Some(_) => CircuitState::Open,
+        }
+    }
+
+    fn report_transition(&self, state: CircuitState, open: i64) {
+        tracing::warn!(
+            counter.http_request.circuit_breaker.open = open,
+            upstream = self.upstream.as_str(),
+            "Circuit breaker for upstream {} is {}",
+            self.upstream,
+            state.name()
+        );
+
+        if let Some(metrics) = &self.metrics {
+            metrics.open.add(open, &self.attributes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_circuit_breaker_transitions() {
+        let breaker = CircuitBreaker::new(
+            "billing",
+            CircuitBreakerConfig {
+                failure_threshold: 2,
+                half_open_after: Duration::ZERO,
+            },
+            None,
+        );
+
+        assert!(breaker.acquire());
+        breaker.record(false);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.record(false);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+        // Only one probe is let through while half-open.
+        assert!(breaker.acquire());
+        assert!(!breaker.acquire());
+
+        breaker.record(false);
+        assert!(breaker.acquire());
+        breaker.record(true);
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert!(breaker.acquire());
+    }
+
+    #[test]
+    fn test_circuit_breaker_stays_open() {
+        let breaker = CircuitBreaker::new(
+            "billing",
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                half_open_after: Duration::from_secs(60),
+            },
+            None,
+        );
+
+        breaker.record(false);
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(!breaker.acquire());
+    }
+
+    #[test]
+    fn test_circuit_breaker_prometheus_metrics() {
+        let exporter = PrometheusMetrics::new().unwrap();
+        let breaker = CircuitBreaker::new(
+            "billing",
+            CircuitBreakerConfig {
+                failure_threshold: 1,
+                half_open_after: Duration::from_secs(60),
+            },
+            Some(BreakerMetrics::new(&exporter)),
+        );
+
+        breaker.record(false);
+        assert!(!breaker.acquire());
+
+        let text = exporter.encode();
+        assert!(text.contains("http_request_circuit_breaker_open{upstream=\"billing\""));
+        assert!(text.contains("http_request_circuit_breaker_rejected_total{upstream=\"billing\""));
+    }
+}
@@ -1,8 +1,52 @@
+use crate::circuit_breaker::CircuitBreakerConfig;
 use phlow_sdk::prelude::{NumberBehavior, Value};
+use std::collections::HashMap;
 
 pub struct Config {
     pub timeout: u64,
     pub verify_ssl: bool,
+    pub upstreams: HashMap<String, UpstreamConfig>,
+}
+
+/// Named upstream profile, selected per request with the `upstream` input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpstreamConfig {
+    pub base_url: Option<String>,
+    pub timeout: u64,
+    pub verify_ssl: bool,
+    pub pool_max_idle: Option<usize>,
+    pub keep_alive: Option<u64>,
+    pub proxy: Option<String>,
+    pub ca_cert: Option<String>,
+    pub client_cert: Option<String>,
+    pub client_key: Option<String>,
+    pub circuit_breaker: Option<CircuitBreakerConfig>,
+}
+
+impl UpstreamConfig {
+    fn from_value(value: &Value, defaults: &Config) -> Self {
+        let string = |key: &str| value.get(key).map(|v| v.to_string());
+        let number = |key: &str| value.get(key).and_then(|v| v.to_u64());
+
+        UpstreamConfig {
+            base_url: string("base_url"),
+            timeout: number("timeout").unwrap_or(defaults.timeout),
+            verify_ssl: *value
+                .get("verify_ssl")
+                .and_then(Value::as_bool)
+                .unwrap_or(&defaults.verify_ssl),
+            pool_max_idle: number("pool_max_idle").map(|size| size as usize),
+            keep_alive: number("keep_alive"),
+            proxy: string("proxy"),
+            ca_cert: string("ca_cert"),
+            client_cert: string("client_cert"),
+            client_key: string("client_key"),
+            circuit_breaker: value
+                .get("circuit_breaker")
+                .filter(|v| v.is_object())
+                .map(CircuitBreakerConfig::from),
+        }
+    }
 }
 
 impl From<Value> for Config {
@@ -11,6 +55,7 @@ impl From<Value> for Config {
             return Config {
                 timeout: 29,
                 verify_ssl: true,
+                upstreams: HashMap::new(),
             };
         }
 
@@ -20,9 +65,65 @@ impl From<Value> for Config {
             .and_then(Value::as_bool)
             .unwrap_or(&true);
 
-        Config {
+        let mut config = Config {
             timeout,
             verify_ssl,
+            upstreams: HashMap::new(),
+        };
+
+        if let Some(Value::Object(upstreams)) = value.get("upstreams") {
+            for (name, upstream) in upstreams.iter() {
+                if upstream.is_object() {
+                    let upstream = UpstreamConfig::from_value(upstream, &config);
+                    config.upstreams.insert(name.to_string(), upstream);
+                }
+            }
         }
+
+        config
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_config_upstreams() {
+        let config = Config::from(
+            Value::json_to_value(
+                r#"{
+                    "timeout": 10,
+                    "upstreams": {
+                        "billing": {
+                            "base_url": "https://billing.internal",
+                            "pool_max_idle": 16,
+                            "proxy": "http://proxy:3128",
+                            "circuit_breaker": {"failure_threshold": 3}
+                        }
+                    }
+                }"#,
+            )
+            .unwrap(),
+        );
+
+        let billing = config.upstreams.get("billing").unwrap();
+
+        assert_eq!(
+            billing.base_url.as_deref(),
+            Some("https://billing.internal")
+        );
+        assert_eq!(billing.timeout, 10);
+        assert!(billing.verify_ssl);
+        assert_eq!(billing.pool_max_idle, Some(16));
+        assert_eq!(billing.proxy.as_deref(), Some("http://proxy:3128"));
+        assert_eq!(
+            billing.circuit_breaker,
+            Some(CircuitBreakerConfig {
+                failure_threshold: 3,
+                half_open_after: Duration::from_secs(30),
+            })
+        );
     }
 }
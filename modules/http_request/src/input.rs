@@ -2,31 +2,175 @@ use phlow_sdk::prelude::*;
 use reqwest::header::{self, HeaderMap};
 use reqwest::Method;
 
+/// How the response body is decoded. `Auto` follows the response content-type.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ResponseType {
+    Auto,
+    Json,
+    Text,
+    Base64,
+}
+
+impl From<Option<&Value>> for ResponseType {
+    fn from(value: Option<&Value>) -> Self {
+        match value.map(|v| v.to_string().to_lowercase()).as_deref() {
+            Some("json") => ResponseType::Json,
+            Some("text") => ResponseType::Text,
+            Some("base64") | Some("raw") | Some("binary") => ResponseType::Base64,
+            _ => ResponseType::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MultipartPart {
+    pub name: String,
+    pub value: Option<String>,
+    pub file: Option<String>,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+}
+
+impl MultipartPart {
+    fn from_value(name: String, value: &Value) -> Self {
+        let field = |key: &str| match value {
+            Value::Object(_) => value.get(key).map(|v| v.to_string()),
+            _ => None,
+        };
+
+        Self {
+            value: match value {
+                Value::Object(_) => field("value"),
+                value => Some(value.to_string()),
+            },
+            file: field("file"),
+            filename: field("filename"),
+            content_type: field("content_type"),
+            name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestBody {
+    Raw(String),
+    Json(String),
+    Form(Vec<(String, String)>),
+    Multipart(Vec<MultipartPart>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Auth {
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    Bearer(String),
+    SigV4 {
+        service: String,
+        region: String,
+    },
+}
+
+impl TryFrom<&Value> for Auth {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if !value.is_object() {
+            return Err(format!("Invalid auth: {}", value));
+        }
+
+        let field = |key: &str| value.get(key).map(|v| v.to_string());
+        let kind = field("type").ok_or_else(|| "auth.type is required".to_string())?;
+
+        match kind.to_lowercase().as_str() {
+            "basic" => Ok(Auth::Basic {
+                username: field("username").unwrap_or_default(),
+                password: field("password"),
+            }),
+            "bearer" => match field("token").filter(|token| !token.is_empty()) {
+                Some(token) => Ok(Auth::Bearer(token)),
+                None => Err("auth.token is required for bearer auth".to_string()),
+            },
+            "sigv4" | "aws_sigv4" => Ok(Auth::SigV4 {
+                service: field("service").unwrap_or_else(|| "execute-api".to_string()),
+                region: field("region")
+                    .or_else(|| std::env::var("AWS_REGION").ok())
+                    .or_else(|| std::env::var("AWS_DEFAULT_REGION").ok())
+                    .unwrap_or_else(|| "us-east-1".to_string()),
+            }),
+            _ => Err(format!(
+                "Invalid auth type: {}. Use 'basic', 'bearer' or 'sigv4'.",
+                kind
+            )),
+        }
+    }
+}
+
+/// Flattens an object into key/value pairs; arrays repeat the key.
+fn to_pairs(value: &Value) -> Vec<(String, String)> {
+    let mut pairs = Vec::new();
+
+    if let Value::Object(object) = value {
+        for (key, value) in object.iter() {
+            match value {
+                Value::Array(items) => {
+                    for item in items.into_iter() {
+                        pairs.push((key.to_string(), item.to_string()));
+                    }
+                }
+                Value::Null | Value::Undefined => {}
+                value => pairs.push((key.to_string(), value.to_string())),
+            }
+        }
+    }
+
+    pairs
+}
+
+fn to_parts(value: &Value) -> Vec<MultipartPart> {
+    match value {
+        Value::Object(object) => object
+            .iter()
+            .map(|(name, value)| MultipartPart::from_value(name.to_string(), value))
+            .collect(),
+        Value::Array(parts) => parts
+            .into_iter()
+            .filter_map(|part| {
+                let name = part.get("name")?.to_string();
+                Some(MultipartPart::from_value(name, part))
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
 pub struct Input {
     pub method: Method,
+    pub upstream: Option<String>,
     pub url: String,
     pub headers: HeaderMap,
-    pub body: Option<String>,
+    pub query: Vec<(String, String)>,
+    pub body: Option<RequestBody>,
+    pub auth: Option<Auth>,
+    pub response_type: ResponseType,
 }
 impl Input {
-    pub fn new(value: Value, default_user_agent: &Option<String>) -> Self {
+    pub fn new(value: Value, default_user_agent: &Option<String>) -> Result<Self, String> {
         let method = match value.get("method") {
-            Some(Value::String(method)) => match method.as_str() {
-                "GET" => Method::GET,
-                "POST" => Method::POST,
-                "PUT" => Method::PUT,
-                "PATCH" => Method::PATCH,
-                "OPTIONS" => Method::OPTIONS,
-                "HEAD" => Method::HEAD,
-                "TRACE" => Method::TRACE,
-                "CONNECT" => Method::CONNECT,
-                "DELETE" => Method::DELETE,
-                _ => Method::GET,
-            },
-            _ => Method::GET,
+            Some(Value::String(method)) => {
+                Method::from_bytes(method.as_str().to_uppercase().as_bytes())
+                    .map_err(|_| format!("Invalid method: {}", method.as_str()))?
+            }
+            Some(Value::Null) | None => Method::GET,
+            Some(method) => return Err(format!("Invalid method: {}", method)),
         };
 
-        let url = value.get("url").unwrap_or(&Value::Null).to_string();
+        let upstream = value.get("upstream").map(|upstream| upstream.to_string());
+        let url = value
+            .get("url")
+            .map(|url| url.to_string())
+            .unwrap_or_default();
         let mut headers: HeaderMap = HeaderMap::default();
 
         if let Some(Value::Object(header_map)) = value.get("headers") {
@@ -56,26 +200,135 @@ impl Input {
             );
         }
 
-        let body = value.get("body").map(|v| v.to_string());
+        let body = if let Some(json) = value.get("json") {
+            Some(RequestBody::Json(json.to_json(JsonMode::Inline)))
+        } else if let Some(form) = value.get("form") {
+            Some(RequestBody::Form(to_pairs(form)))
+        } else if let Some(multipart) = value.get("multipart") {
+            Some(RequestBody::Multipart(to_parts(multipart)))
+        } else {
+            value.get("body").map(|body| match body {
+                Value::Object(_) | Value::Array(_) => {
+                    RequestBody::Json(body.to_json(JsonMode::Inline))
+                }
+                body => {
+                    let body = body.to_string();
+                    if body.starts_with('{') && body.ends_with('}') {
+                        RequestBody::Json(body)
+                    } else {
+                        RequestBody::Raw(body)
+                    }
+                }
+            })
+        };
 
-        if let Some(body) = &body {
-            if body.starts_with('{')
-                && body.ends_with('}')
-                && headers.get("Content-Type").is_none()
-                && headers.get("content-type").is_none()
-            {
+        if let Some(RequestBody::Json(_)) = &body {
+            if !headers.contains_key(header::CONTENT_TYPE) {
                 headers.insert(
-                    "content-type",
+                    header::CONTENT_TYPE,
                     header::HeaderValue::from_static("application/json"),
                 );
             }
         }
 
-        Input {
+        let query = value.get("query").map(to_pairs).unwrap_or_default();
+        let auth = match value.get("auth") {
+            Some(Value::Null) | None => None,
+            Some(auth) => Some(Auth::try_from(auth)?),
+        };
+        let response_type = ResponseType::from(value.get("response_type"));
+
+        Ok(Input {
             method,
+            upstream,
             url,
             headers,
+            query,
             body,
+            auth,
+            response_type,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(json: &str) -> Input {
+        Input::new(Value::json_to_value(json).unwrap(), &None).unwrap()
+    }
+
+    #[test]
+    fn test_input_invalid_method() {
+        let invalid = |json: &str| Input::new(Value::json_to_value(json).unwrap(), &None).is_err();
+
+        assert!(invalid(
+            r#"{"method": "GET /", "url": "https://api.example.com"}"#
+        ));
+        assert!(invalid(
+            r#"{"method": 1, "url": "https://api.example.com"}"#
+        ));
+        assert_eq!(input(r#"{"method": "purge"}"#).method.as_str(), "PURGE");
+    }
+
+    #[test]
+    fn test_input_invalid_auth() {
+        let invalid = |json: &str| Input::new(Value::json_to_value(json).unwrap(), &None).is_err();
+
+        assert!(invalid(r#"{"auth": {"type": "digest"}}"#));
+        assert!(invalid(r#"{"auth": {"token": "secret"}}"#));
+        assert!(invalid(r#"{"auth": {"type": "bearer"}}"#));
+        assert!(invalid(r#"{"auth": "secret"}"#));
+        assert_eq!(input(r#"{"auth": null}"#).auth, None);
+    }
+
+    #[test]
+    fn test_input_structured_fields() {
+        let input = input(
+            r#"{
+                "method": "post",
+                "url": "https://api.example.com/students",
+                "query": {"tag": ["a", "b"], "page": 2},
+                "json": {"name": "Ana"},
+                "auth": {"type": "bearer", "token": "secret"}
+            }"#,
+        );
+
+        assert_eq!(input.method, Method::POST);
+        assert_eq!(input.query.len(), 3);
+        assert!(input.query.contains(&("tag".to_string(), "b".to_string())));
+        assert!(input.query.contains(&("page".to_string(), "2".to_string())));
+        assert_eq!(
+            input.body,
+            Some(RequestBody::Json(r#"{"name": "Ana"}"#.to_string()))
+        );
+        assert_eq!(
+            input.headers.get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        assert_eq!(input.auth, Some(Auth::Bearer("secret".to_string())));
+    }
+
+    #[test]
+    fn test_input_multipart_parts() {
+        let input = input(
+            r#"{
+                "url": "https://api.example.com/upload",
+                "multipart": [
+                    {"name": "description", "value": "report"},
+                    {"name": "file", "file": "./report.pdf", "content_type": "application/pdf"}
+                ]
+            }"#,
+        );
+
+        match input.body {
+            Some(RequestBody::Multipart(parts)) => {
+                assert_eq!(parts.len(), 2);
+                assert_eq!(parts[0].value.as_deref(), Some("report"));
+                assert_eq!(parts[1].file.as_deref(), Some("./report.pdf"));
+            }
+            body => panic!("unexpected body: {:?}", body),
         }
     }
 }
@@ -1,11 +1,19 @@
+mod circuit_breaker;
 mod config;
 mod input;
 mod request;
+mod sigv4;
+mod upstream;
+use circuit_breaker::BreakerMetrics;
 use config::Config;
 use input::Input;
+use phlow_sdk::otel::TracePropagation;
 use phlow_sdk::prelude::*;
+use phlow_sdk::tracing::Instrument;
 use reqwest::Client;
 use std::collections::HashMap;
+use std::sync::Arc;
+use upstream::Upstream;
 
 create_step!(http_request(setup));
 
@@ -43,41 +51,158 @@ pub async fn http_request(
         }
     };
 
-    listen!(rx, resolve, default_user_agent, client);
+    let mut upstreams = HashMap::new();
+    let breaker_metrics = match config
+        .upstreams
+        .values()
+        .any(|upstream| upstream.circuit_breaker.is_some())
+    {
+        true => setup
+            .health
+            .registry()
+            .metrics()
+            .map(|exporter| BreakerMetrics::new(&exporter)),
+        false => None,
+    };
+
+    for (name, upstream) in config.upstreams.iter() {
+        upstreams.insert(
+            name.clone(),
+            Upstream::new(name, upstream, breaker_metrics.clone())?,
+        );
+    }
+
+    let upstreams = Arc::new(upstreams);
+    let trace_propagation = setup.trace_propagation;
+
+    listen!(
+        rx,
+        resolve,
+        default_user_agent,
+        client,
+        upstreams,
+        trace_propagation
+    );
 
     Ok(())
 }
 
-pub async fn resolve(package: ModulePackage, default_user_agent: Option<String>, client: Client) {
+pub async fn resolve(
+    package: ModulePackage,
+    default_user_agent: Option<String>,
+    client: Client,
+    upstreams: Arc<HashMap<String, Upstream>>,
+    trace_propagation: TracePropagation,
+) {
     let response = match package.input() {
-        Some(value) => {
-            let input = Input::new(value, &default_user_agent);
-            match request::request(input, client).await {
-                Ok(value) => HashMap::from([
-                    ("response", value),
-                    ("is_success", true.to_value()),
-                    ("is_error", false.to_value()),
-                    ("message", "Request successful".to_value()),
-                ]),
-                Err(e) => {
-                    tracing::error!("Error: {:?}", e);
-                    HashMap::from([
-                        ("response", Value::Undefined),
-                        ("is_success", false.to_value()),
-                        ("is_error", true.to_value()),
-                        ("message", format!("{:?}", e).to_value()),
-                    ])
-                }
-            }
-        }
-        _ => HashMap::from([
-            ("response", Value::Undefined),
-            ("is_success", false.to_value()),
-            ("is_error", true.to_value()),
-            ("message", "No input provided".to_value()),
-        ]),
+        Some(value) => match Input::new(value, &default_user_agent) {
+            Ok(input) => execute(&package, input, client, &upstreams, trace_propagation).await,
+            Err(e) => failure(e),
+        },
+        _ => failure("No input provided".to_string()),
     }
     .to_value();
 
     sender_safe!(package.sender, response.into());
 }
+
+fn failure(message: String) -> HashMap<&'static str, Value> {
+    HashMap::from([
+        ("response", Value::Undefined),
+        ("is_success", false.to_value()),
+        ("is_error", true.to_value()),
+        ("message", message.to_value()),
+    ])
+}
+
+async fn execute(
+    package: &ModulePackage,
+    mut input: Input,
+    client: Client,
+    upstreams: &HashMap<String, Upstream>,
+    trace_propagation: TracePropagation,
+) -> HashMap<&'static str, Value> {
+    let upstream = match &input.upstream {
+        Some(name) => match upstreams.get(name) {
+            Some(upstream) => Some(upstream),
+            None => return failure(format!("Unknown upstream: {}", name)),
+        },
+        None => None,
+    };
+
+    let client = match upstream {
+        Some(upstream) => {
+            input.url = upstream.url(&input.url);
+            upstream.client.clone()
+        }
+        None => client,
+    };
+
+    let breaker = upstream.and_then(|upstream| upstream.breaker.as_ref());
+
+    if let Some(breaker) = breaker {
+        if !breaker.acquire() {
+            let mut result = failure(format!(
+                "Circuit breaker open for upstream {}",
+                input.upstream.as_deref().unwrap_or_default()
+            ));
+            result.insert("circuit_breaker", breaker.state().name().to_value());
+            return result;
+        }
+    }
+
+    let span = request::client_span(package, &input);
+
+    let mut result = match request::request(input, client, &span, trace_propagation)
+        .instrument(span.clone())
+        .await
+    {
+        Ok(value) => {
+            let status_code = value
+                .get("status_code")
+                .and_then(|status| status.to_u64())
+                .unwrap_or_default();
+
+            span.record("http.response.status_code", status_code);
+            if status_code >= 400 {
+                span.record("error.type", status_code.to_string());
+                span.record("otel.status_code", "ERROR");
+            }
+
+            if let Some(breaker) = breaker {
+                breaker.record(status_code < 500);
+            }
+
+            let is_success = (200..300).contains(&status_code);
+            let message = if is_success {
+                "Request successful".to_string()
+            } else {
+                format!("Request failed with status code {}", status_code)
+            };
+
+            HashMap::from([
+                ("response", value),
+                ("is_success", is_success.to_value()),
+                ("is_error", (status_code >= 400).to_value()),
+                ("message", message.to_value()),
+            ])
+        }
+        Err(e) => {
+            tracing::error!("Error: {:?}", e);
+            span.record("error.type", e.kind());
+            span.record("otel.status_code", "ERROR");
+
+            if let Some(breaker) = breaker {
+                breaker.record(false);
+            }
+
+            failure(format!("{:?}", e))
+        }
+    };
+
+    if let Some(breaker) = breaker {
+        result.insert("circuit_breaker", breaker.state().name().to_value());
+    }
+
+    result
+}
@@ -0,0 +1,111 @@
+use crate::circuit_breaker::{BreakerMetrics, CircuitBreaker};
+use crate::config::UpstreamConfig;
+use reqwest::{Certificate, Client, Identity, Proxy};
+use std::time::Duration;
+
+/// Upstream profile with its own connection pool and circuit breaker.
+#[derive(Debug)]
+pub struct Upstream {
+    pub base_url: Option<String>,
+    pub client: Client,
+    pub breaker: Option<CircuitBreaker>,
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e).into())
+}
+
+impl Upstream {
+    pub fn new(
+        name: &str,
+        config: &UpstreamConfig,
+        metrics: Option<BreakerMetrics>,
+    ) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let mut builder = Client::builder()
+            .danger_accept_invalid_certs(!config.verify_ssl)
+            .timeout(Duration::from_secs(config.timeout));
+
+        if let Some(pool_max_idle) = config.pool_max_idle {
+            builder = builder.pool_max_idle_per_host(pool_max_idle);
+        }
+
+        if let Some(keep_alive) = config.keep_alive {
+            builder = builder
+                .pool_idle_timeout(Duration::from_secs(keep_alive))
+                .tcp_keepalive(Duration::from_secs(keep_alive));
+        }
+
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(Proxy::all(proxy)?);
+        }
+
+        if let Some(ca_cert) = &config.ca_cert {
+            for certificate in Certificate::from_pem_bundle(&read_file(ca_cert)?)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        match (&config.client_cert, &config.client_key) {
+            (Some(cert), Some(key)) => {
+                let identity = Identity::from_pkcs8_pem(&read_file(cert)?, &read_file(key)?)?;
+                builder = builder.identity(identity);
+            }
+            (None, None) => {}
+            _ => {
+                return Err(format!(
+                    "Upstream {}: client_cert and client_key must be set together",
+                    name
+                )
+                .into())
+            }
+        }
+
+        Ok(Self {
+            base_url: config.base_url.clone(),
+            client: builder.build()?,
+            breaker: config
+                .circuit_breaker
+                .map(|breaker| CircuitBreaker::new(name, breaker, metrics)),
+        })
+    }
+
+    /// Resolves `url` against the base URL; absolute URLs are kept as they are.
+    pub fn url(&self, url: &str) -> String {
+        match &self.base_url {
+            Some(base_url) if !url.starts_with("http://") && !url.starts_with("https://") => {
+                let path = url.trim_start_matches('/');
+
+                if path.is_empty() {
+                    base_url.clone()
+                } else {
+                    format!("{}/{}", base_url.trim_end_matches('/'), path)
+                }
+            }
+            _ => url.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_upstream_url() {
+        let upstream = Upstream {
+            base_url: Some("https://billing.internal/v1/".to_string()),
+            client: Client::new(),
+            breaker: None,
+        };
+
+        assert_eq!(
+            upstream.url("/invoices"),
+            "https://billing.internal/v1/invoices"
+        );
+        assert_eq!(upstream.url(""), "https://billing.internal/v1/");
+        assert_eq!(
+            upstream.url("https://other.internal/ping"),
+            "https://other.internal/ping"
+        );
+    }
+}
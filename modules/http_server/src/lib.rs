@@ -1,16 +1,26 @@
 mod middleware;
+mod probes;
+mod query;
 mod resolver;
 mod response;
+mod routes;
 mod settings;
 mod setup;
+mod sse;
+mod stream;
+mod validation;
+mod websocket;
 use hyper::{server::conn::http1, service::service_fn};
 use hyper_util::rt::TokioIo;
 use middleware::TracingMiddleware;
 use phlow_sdk::{prelude::*, tokio::net::TcpListener};
+use probes::Probes;
 use resolver::proxy;
+use routes::Router;
 use settings::Settings;
 use setup::Config;
-use std::{net::SocketAddr, sync::Arc};
+use std::{net::SocketAddr, path::Path, sync::Arc};
+use stream::{resolve_stream_package, Streams};
 
 create_main!(start_server(setup));
 
@@ -42,11 +52,45 @@ pub async fn start_server(
 
     debug!("Listening on {}", listener.local_addr()?);
 
-    sender_safe!(setup.setup_sender, None);
+    let openapi_document = phlow_sdk::openapi::build_document(
+        &setup.app_data,
+        &config.routes,
+        &phlow_sdk::openapi::load_module_specs(Path::new(phlow_sdk::openapi::MODULES_DIR)),
+    )
+    .to_json(JsonMode::Inline);
+    let router = Arc::new(Router::new(
+        &config.routes,
+        &config.schema,
+        config.openapi_path.clone(),
+        openapi_document,
+    )?);
+
+    let probes = Arc::new(Probes::new(
+        setup.health.registry(),
+        config.health_path.clone(),
+        config.readiness_path.clone(),
+        config.metrics_path.clone(),
+    ));
+
+    let streams = Streams::new(config.websocket_paths.clone(), config.sse_paths.clone());
+    let rx = module_channel!(setup);
+
+    {
+        let streams = streams.clone();
+        tokio::task::spawn_blocking(move || {
+            for package in rx {
+                resolve_stream_package(package, &streams);
+            }
+        });
+    }
 
     loop {
         let dispatch = setup.dispatch.clone();
         let authorization_span_mode = settings.authorization_span_mode.clone();
+        let coerce_query_params = config.coerce_query_params;
+        let streams = streams.clone();
+        let router = router.clone();
+        let probes = probes.clone();
         let sender = match setup.main_sender.clone() {
             Some(sender) => sender,
             None => {
@@ -67,11 +111,17 @@ pub async fn start_server(
                 id: setup.id,
                 peer_addr,
                 authorization_span_mode,
+                coerce_query_params,
+                streams,
+                router,
+                probes,
+                trace_propagation: setup.trace_propagation,
             };
 
             if let Err(e) = http1::Builder::new()
                 .keep_alive(true)
                 .serve_connection(io, middleware)
+                .with_upgrades()
                 .await
             {
                 debug!("Error serving connection: {}", e);
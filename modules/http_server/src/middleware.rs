@@ -1,8 +1,12 @@
+use crate::probes::Probes;
+use crate::routes::Router;
 use crate::settings::AuthorizationSpanMode;
+use crate::stream::Streams;
 use hyper::{body::Incoming, service::Service, Request};
+use phlow_sdk::otel::TracePropagation;
 use phlow_sdk::prelude::*;
 
-use std::{future::Future, pin::Pin};
+use std::{collections::HashMap, future::Future, pin::Pin, sync::Arc};
 
 #[derive(Debug, Clone)]
 pub struct RequestContext {
@@ -12,6 +16,10 @@ pub struct RequestContext {
     pub span: phlow_sdk::tracing::Span,
     pub client_ip: String,
     pub authorization_span_mode: AuthorizationSpanMode,
+    pub coerce_query_params: bool,
+    pub streams: Streams,
+    pub router: Arc<Router>,
+    pub probes: Arc<Probes>,
 }
 
 #[derive(Debug, Clone)]
@@ -22,6 +30,11 @@ pub struct TracingMiddleware<S> {
     pub sender: MainRuntimeSender,
     pub peer_addr: std::net::SocketAddr,
     pub authorization_span_mode: AuthorizationSpanMode,
+    pub coerce_query_params: bool,
+    pub streams: Streams,
+    pub router: Arc<Router>,
+    pub probes: Arc<Probes>,
+    pub trace_propagation: TracePropagation,
 }
 
 impl<S> Service<Request<Incoming>> for TracingMiddleware<S>
@@ -36,7 +49,8 @@ where
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
 
     fn call(&self, mut req: Request<Incoming>) -> Self::Future {
-        if req.method() == hyper::Method::GET && req.uri().path() == "/health" {
+        if self.probes.is_probe(req.method(), req.uri().path()) {
+            req.extensions_mut().insert(self.probes.clone());
             let fut: <S as Service<Request<Incoming>>>::Future = self.inner.call(req);
             return Box::pin(async move { fut.await });
         }
@@ -46,6 +60,7 @@ where
                 Level::INFO,
                 "http_request",
                 otel.name = field::Empty,
+                otel.kind = "server",
                 http.route = field::Empty,
                 http.request.body.size = field::Empty,
                 http.request.method = field::Empty,
@@ -115,6 +130,15 @@ where
                 "http.response.header.via" = field::Empty,
             );
 
+            let trace_context: HashMap<String, String> = ["traceparent", "tracestate"]
+                .iter()
+                .filter_map(|name| {
+                    let value = req.headers().get(*name)?.to_str().ok()?;
+                    Some((name.to_string(), value.to_string()))
+                })
+                .collect();
+            (self.trace_propagation.extract)(&span, &trace_context);
+
             span_enter!(span);
 
             let context = RequestContext {
@@ -124,6 +148,10 @@ where
                 client_ip: self.peer_addr.to_string(),
                 span,
                 authorization_span_mode: self.authorization_span_mode.clone(),
+                coerce_query_params: self.coerce_query_params,
+                streams: self.streams.clone(),
+                router: self.router.clone(),
+                probes: self.probes.clone(),
             };
 
             req.extensions_mut().insert(context);
@@ -0,0 +1,173 @@
+use crate::resolver::{full_body, ResponseBody};
+use hyper::{Method, Response};
+use phlow_sdk::{
+    metrics::PrometheusMetrics,
+    opentelemetry::{
+        metrics::{Histogram, ObservableGauge},
+        KeyValue,
+    },
+    prelude::*,
+};
+use std::time::Instant;
+
+#[derive(Debug)]
+struct HttpMetrics {
+    exporter: PrometheusMetrics,
+    duration: Histogram<f64>,
+    _modules_up: ObservableGauge<u64>,
+}
+
+/// Liveness, readiness and metrics endpoints. They answer before the request
+/// reaches the flow and are not traced.
+#[derive(Debug)]
+pub struct Probes {
+    health: Health,
+    health_path: Option<String>,
+    readiness_path: Option<String>,
+    metrics_path: Option<String>,
+    metrics: Option<HttpMetrics>,
+}
+
+impl Probes {
+    pub fn new(
+        health: Health,
+        health_path: Option<String>,
+        readiness_path: Option<String>,
+        metrics_path: Option<String>,
+    ) -> Self {
+        let metrics = match &metrics_path {
+            Some(_) => health
+                .metrics()
+                .map(|exporter| HttpMetrics::new(exporter, health.clone())),
+            None => None,
+        };
+
+        Self {
+            health,
+            health_path,
+            readiness_path,
+            metrics_path,
+            metrics,
+        }
+    }
+
+    pub fn is_probe(&self, method: &Method, path: &str) -> bool {
+        method == Method::GET
+            && [&self.health_path, &self.readiness_path, &self.metrics_path]
+                .iter()
+                .any(|probe| probe.as_deref() == Some(path))
+    }
+
+    pub fn respond(&self, path: &str) -> Response<ResponseBody> {
+        let path = Some(path);
+
+        if self.health_path.as_deref() == path {
+            return json_response(200, &self.health.report());
+        }
+
+        if self.readiness_path.as_deref() == path {
+            let status = if self.health.is_ready() { 200 } else { 503 };
+            return json_response(status, &self.health.report());
+        }
+
+        let body = self
+            .metrics
+            .as_ref()
+            .map(|metrics| metrics.exporter.encode())
+            .unwrap_or_default();
+
+        Response::builder()
+            .status(200)
+            .header("content-type", "text/plain; version=0.0.4")
+            .body(full_body(body))
+            .expect("Failed to build metrics response")
+    }
+
+    /// Records a served request in the `http.server.request.duration` histogram.
+    pub fn record(&self, method: &str, route: Option<&str>, status_code: u16, start: Instant) {
+        if let Some(metrics) = &self.metrics {
+            metrics.duration.record(
+                start.elapsed().as_secs_f64(),
+                &[
+                    KeyValue::new("http.request.method", method.to_string()),
+                    KeyValue::new("http.route", route.unwrap_or_default().to_string()),
+                    KeyValue::new("http.response.status_code", status_code as i64),
+                ],
+            );
+        }
+    }
+}
+
+impl HttpMetrics {
+    fn new(exporter: PrometheusMetrics, health: Health) -> Self {
+        let meter = exporter.meter("http_server");
+        let duration = meter
+            .f64_histogram("http.server.request.duration")
+            .with_unit("s")
+            .with_description("Duration of HTTP server requests")
+            .with_boundaries(vec![
+                0.005, 0.01, 0.025, 0.05, 0.075, 0.1, 0.25, 0.5, 0.75, 1.0, 2.5, 5.0, 7.5, 10.0,
+            ])
+            .build();
+        let modules_up = meter
+            .u64_observable_gauge("phlow.module.up")
+            .with_description("Whether each module reports itself as up")
+            .with_callback(move |observer| {
+                for (name, status) in health.modules() {
+                    observer.observe(u64::from(status.is_up()), &[KeyValue::new("module", name)]);
+                }
+            })
+            .build();
+
+        Self {
+            exporter,
+            duration,
+            _modules_up: modules_up,
+        }
+    }
+}
+
+fn json_response(status: u16, body: &Value) -> Response<ResponseBody> {
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(full_body(body.to_json(JsonMode::Inline)))
+        .expect("Failed to build health response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probes_readiness_and_metrics() {
+        let health = Health::default();
+        let module = health.module("postgres");
+        let probes = Probes::new(
+            health.clone(),
+            Some("/health".to_string()),
+            Some("/ready".to_string()),
+            Some("/metrics".to_string()),
+        );
+
+        assert!(probes.is_probe(&Method::GET, "/ready"));
+        assert!(!probes.is_probe(&Method::POST, "/ready"));
+        assert!(!probes.is_probe(&Method::GET, "/students"));
+        assert_eq!(probes.respond("/health").status(), 200);
+        assert_eq!(probes.respond("/ready").status(), 503);
+
+        module.up();
+        health.set_ready(true);
+        probes.record("GET", Some("/students"), 200, Instant::now());
+
+        assert_eq!(probes.respond("/ready").status(), 200);
+        assert_eq!(probes.respond("/metrics").status(), 200);
+        assert!(probes
+            .metrics
+            .as_ref()
+            .unwrap()
+            .exporter
+            .encode()
+            .contains("phlow_module_up"));
+    }
+}
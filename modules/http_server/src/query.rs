@@ -0,0 +1,234 @@
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+
+/// Parses a raw query string (without the leading `?`) into a `Value` object.
+///
+/// - Keys and values are percent-decoded (`+` is treated as a space).
+/// - Repeated keys (`a=1&a=2`) become arrays.
+/// - Bracket notation (`filter[name]=x`) becomes nested objects and
+///   `tags[]=a&tags[]=b` becomes an array. Mixing both under one key keeps
+///   every value: `a=1&a[b]=2` becomes `["1", {"b": "2"}]`.
+/// - When `coerce` is enabled, numeric and boolean values are converted.
+pub fn parse_query(query: &str, coerce: bool) -> Value {
+    let mut root = Value::from(HashMap::<String, Value>::new());
+
+    for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+        if key.is_empty() {
+            continue;
+        }
+
+        let path = split_key(&key);
+        let value = if coerce {
+            coerce_value(&value)
+        } else {
+            value.to_value()
+        };
+
+        insert_path(&mut root, &path, value);
+    }
+
+    root
+}
+
+fn split_key(key: &str) -> Vec<String> {
+    let (base, mut rest) = match key.find('[') {
+        Some(pos) if pos > 0 => (&key[..pos], &key[pos..]),
+        _ => return vec![key.to_string()],
+    };
+
+    let mut path = vec![base.to_string()];
+
+    while let Some(stripped) = rest.strip_prefix('[') {
+        match stripped.find(']') {
+            Some(end) => {
+                path.push(stripped[..end].to_string());
+                rest = &stripped[end + 1..];
+            }
+            None => return vec![key.to_string()],
+        }
+    }
+
+    if !rest.is_empty() {
+        return vec![key.to_string()];
+    }
+
+    path
+}
+
+fn insert_path(target: &mut Value, path: &[String], value: Value) {
+    let key = path[0].as_str();
+
+    if path.len() == 1 {
+        match target.get_mut(key) {
+            Some(Value::Array(array)) => array.push(value),
+            Some(existing) => {
+                let previous = std::mem::take(existing);
+                *existing = Value::from(vec![previous, value]);
+            }
+            None => {
+                target.insert(key, value);
+            }
+        }
+        return;
+    }
+
+    if path.len() == 2 && path[1].is_empty() {
+        match target.get_mut(key) {
+            Some(Value::Array(array)) => array.push(value),
+            Some(existing) => {
+                let previous = std::mem::take(existing);
+                *existing = Value::from(vec![previous, value]);
+            }
+            None => {
+                target.insert(key, Value::from(vec![value]));
+            }
+        }
+        return;
+    }
+
+    if let Some(child) = nested(target, key) {
+        insert_path(child, &path[1..], value);
+    }
+}
+
+/// The object nested keys of `key` go into. A value already under the key
+/// that is not an object is kept: the object is added after it in an array,
+/// and later nested keys go into that last object.
+fn nested<'a>(target: &'a mut Value, key: &str) -> Option<&'a mut Value> {
+    let object = || Value::from(HashMap::<String, Value>::new());
+
+    match target.get_mut(key) {
+        Some(Value::Object(_)) => {}
+        Some(Value::Array(array)) => {
+            if !matches!(array.values.last(), Some(Value::Object(_))) {
+                array.push(object());
+            }
+        }
+        Some(existing) => {
+            let previous = std::mem::take(existing);
+            *existing = Value::from(vec![previous, object()]);
+        }
+        None => {
+            target.insert(key, object());
+        }
+    }
+
+    match target.get_mut(key)? {
+        Value::Array(array) => array.values.last_mut(),
+        child => Some(child),
+    }
+}
+
+fn coerce_value(value: &str) -> Value {
+    match value {
+        "true" => return true.to_value(),
+        "false" => return false.to_value(),
+        _ => {}
+    }
+
+    let digits = value.strip_prefix('-').unwrap_or(value);
+    let has_leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+
+    if !has_leading_zero
+        && !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit() || c == '.')
+    {
+        if let Ok(number) = value.parse::<i64>() {
+            return number.to_value();
+        }
+
+        // Integers out of the i64 range, such as long IDs, would lose
+        // precision as f64, so they are kept as strings.
+        if digits.contains('.') {
+            if let Ok(number) = value.parse::<f64>() {
+                return number.to_value();
+            }
+        }
+    }
+
+    value.to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query_empty() {
+        let result = parse_query("", false);
+
+        assert!(result.is_object());
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_parse_query_decodes_values() {
+        let result = parse_query("name=John%20Doe&city=S%C3%A3o+Paulo&flag", false);
+
+        assert_eq!(result.get("name"), Some(&"John Doe".to_value()));
+        assert_eq!(result.get("city"), Some(&"São Paulo".to_value()));
+        assert_eq!(result.get("flag"), Some(&"".to_value()));
+    }
+
+    #[test]
+    fn test_parse_query_repeated_keys() {
+        let result = parse_query("a=1&a=2&a=3&b[]=x", false);
+
+        assert_eq!(
+            result.get("a"),
+            Some(&Value::from(vec![
+                "1".to_value(),
+                "2".to_value(),
+                "3".to_value()
+            ]))
+        );
+        assert_eq!(result.get("b"), Some(&Value::from(vec!["x".to_value()])));
+    }
+
+    #[test]
+    fn test_parse_query_brackets() {
+        let result = parse_query("filter[name]=x&filter[age][gt]=10&filter%5Btag%5D=y", false);
+        let filter = result.get("filter").unwrap();
+
+        assert_eq!(filter.get("name"), Some(&"x".to_value()));
+        assert_eq!(filter.get("tag"), Some(&"y".to_value()));
+        assert_eq!(
+            filter.get("age").and_then(|age| age.get("gt")),
+            Some(&"10".to_value())
+        );
+    }
+
+    #[test]
+    fn test_parse_query_mixed_scalar_and_brackets() {
+        let result = parse_query("a=1&a[b]=2&a[c]=3&ids[]=1&ids[x]=2", false);
+
+        assert_eq!(
+            result.get("a"),
+            Some(&Value::json_to_value(r#"["1", {"b": "2", "c": "3"}]"#).unwrap())
+        );
+        assert_eq!(
+            result.get("ids"),
+            Some(&Value::json_to_value(r#"["1", {"x": "2"}]"#).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_query_coerce() {
+        let result = parse_query("page=2&price=9.5&active=true&zip=01234&name=ok", true);
+
+        assert_eq!(result.get("page"), Some(&2i64.to_value()));
+        assert_eq!(result.get("price"), Some(&9.5f64.to_value()));
+        assert_eq!(result.get("active"), Some(&true.to_value()));
+        assert_eq!(result.get("zip"), Some(&"01234".to_value()));
+        assert_eq!(result.get("name"), Some(&"ok".to_value()));
+    }
+
+    #[test]
+    fn test_parse_query_coerce_large_integers() {
+        let result = parse_query("id=9007199254740993&big=123456789012345678901", true);
+
+        assert_eq!(result.get("id"), Some(&9007199254740993i64.to_value()));
+        assert_eq!(result.get("id").unwrap().to_string(), "9007199254740993");
+        assert_eq!(result.get("big"), Some(&"123456789012345678901".to_value()));
+    }
+}
@@ -1,13 +1,16 @@
+use crate::probes::Probes;
+use crate::query::parse_query;
 use crate::settings::AuthorizationSpanMode;
-use crate::{middleware::RequestContext, response::ResponseHandler};
+use crate::validation::{invalid_json_response, violations_response};
+use crate::{middleware::RequestContext, response::ResponseHandler, sse, websocket};
 use bytes::Bytes;
-use http_body_util::{BodyExt, Full};
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt, Empty, Full};
 use hyper::body::Body;
 use hyper::{HeaderMap, Request, Response};
 use phlow_sdk::span_enter;
 use phlow_sdk::tracing::debug;
 use phlow_sdk::{prelude::*, tracing::Span};
-use std::{collections::HashMap, convert::Infallible};
+use std::{collections::HashMap, convert::Infallible, sync::Arc, time::Instant};
 
 macro_rules! to_span_format {
     ($target:expr, $key:expr) => {{
@@ -16,18 +19,25 @@ macro_rules! to_span_format {
     }};
 }
 
-pub async fn proxy(
-    req: Request<hyper::body::Incoming>,
-) -> Result<Response<Full<Bytes>>, Infallible> {
-    if req.method() == hyper::Method::GET && req.uri().path() == "/health" {
-        let response = Response::builder()
-            .status(200)
-            .body(Full::new(Bytes::from(r#"ok"#)))
-            .unwrap();
+pub type ResponseBody = UnsyncBoxBody<Bytes, Infallible>;
 
-        return Ok(response);
+pub fn full_body(body: impl Into<Bytes>) -> ResponseBody {
+    Full::new(body.into()).boxed_unsync()
+}
+
+pub fn empty_body() -> ResponseBody {
+    Empty::new().boxed_unsync()
+}
+
+pub async fn proxy(
+    mut req: Request<hyper::body::Incoming>,
+) -> Result<Response<ResponseBody>, Infallible> {
+    if let Some(probes) = req.extensions().get::<Arc<Probes>>() {
+        return Ok(probes.respond(req.uri().path()));
     }
 
+    let start = Instant::now();
+
     let context = req
         .extensions()
         .get::<RequestContext>()
@@ -37,6 +47,22 @@ pub async fn proxy(
     span_enter!(context.span);
 
     let path = req.uri().path().to_string();
+
+    if req.method() == hyper::Method::GET && context.router.is_openapi_path(&path) {
+        let response = Response::builder()
+            .status(200)
+            .header("content-type", "application/json")
+            .body(full_body(context.router.openapi_document.clone()))
+            .unwrap();
+
+        return Ok(response);
+    }
+
+    let upgrade = if context.streams.is_websocket_path(&path) {
+        websocket::accept(&mut req)
+    } else {
+        None
+    };
     let method = req.method().to_string();
     let body_size = req.body().size_hint().lower();
     let request_size = req.size_hint().lower();
@@ -49,7 +75,7 @@ pub async fn proxy(
         &context.authorization_span_mode,
     );
     let body = resolve_body(req);
-    let query_params = resolve_query_params(&query);
+    let query_params = parse_query(&query, context.coerce_query_params);
 
     context
         .span
@@ -59,11 +85,42 @@ pub async fn proxy(
     context.span.record("http.request.method", &method);
     context.span.record("http.request.path", &path);
 
-    let query_params = query_params.await;
     let body = body.await;
     let headers = headers.await;
 
-    let data = HashMap::from([
+    let route = context.router.find(&method, &path);
+    let body_schema = context
+        .router
+        .body_schema(&method, route.as_ref().map(|(route, _)| *route));
+
+    let checked = upgrade.is_none() && !context.streams.is_sse_path(&path);
+    let rejection = match &body {
+        Err(e) if checked => Some(invalid_json_response(e)),
+        Err(_) => None,
+        Ok(body) if checked => body_schema
+            .and_then(|schema| schema.validate(body).err())
+            .map(|violations| violations_response(&violations, body)),
+        Ok(_) => None,
+    };
+    let body = body.unwrap_or(Value::Undefined);
+
+    if let Some(response) = rejection {
+        let status_code = response.status().as_u16();
+
+        context
+            .span
+            .record("http.response.status_code", status_code);
+        context.probes.record(
+            &method,
+            route.as_ref().map(|(route, _)| route.path.as_str()),
+            status_code,
+            start,
+        );
+
+        return Ok(response);
+    }
+
+    let mut data = HashMap::from([
         ("client_ip", context.client_ip.to_value()),
         ("headers", headers),
         ("method", method.to_value()),
@@ -73,8 +130,26 @@ pub async fn proxy(
         ("uri", uri.to_value()),
         ("body", body),
         ("body_size", body_size.to_value()),
-    ])
-    .to_value();
+    ]);
+
+    let route_path = route.as_ref().map(|(route, _)| route.path.clone());
+
+    if let Some((route, path_params)) = route {
+        context.span.record("http.route", &route.path);
+        data.insert("route", route.path.to_value());
+        data.insert("path_params", path_params.to_value());
+    }
+
+    let data = data.to_value();
+
+    if let Some((on_upgrade, response)) = upgrade {
+        websocket::serve(on_upgrade, data, path, context);
+        return Ok(response);
+    }
+
+    if context.streams.is_sse_path(&path) {
+        return Ok(sse::stream(data, path, context));
+    }
 
     let response_value = sender_package!(
         context.span.clone(),
@@ -101,24 +176,31 @@ pub async fn proxy(
             .record(to_span_format!("http.response.header.{}", key), value);
     });
 
+    context
+        .probes
+        .record(&method, route_path.as_deref(), response.status_code, start);
+
     Ok(response.build())
 }
 
-async fn resolve_query_params(query: &str) -> Value {
-    let mut map = HashMap::new();
-
-    for pair in query.split('&') {
-        let mut parts = pair.split('=');
-        let key = parts.next().unwrap_or_default();
-        let value = parts.next().unwrap_or_default();
-
-        map.insert(key.to_string(), value.to_string());
-    }
+/// Whether the content type is JSON, `application/json` or any `+json` type.
+fn is_json(headers: &HeaderMap) -> bool {
+    let media_type = match headers
+        .get(hyper::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+    {
+        Some(content_type) => content_type.split(';').next().unwrap_or_default(),
+        None => return false,
+    };
+    let media_type = media_type.trim().to_lowercase();
 
-    map.to_value()
+    media_type == "application/json" || media_type.ends_with("+json")
 }
 
-async fn resolve_body(req: Request<hyper::body::Incoming>) -> Value {
+/// The request body. A JSON body is parsed whole, so a scalar stays a
+/// scalar, and fails when it is not valid JSON.
+async fn resolve_body(req: Request<hyper::body::Incoming>) -> Result<Value, String> {
+    let json = is_json(req.headers());
     let body_bytes: Bytes = match req.into_body().collect().await {
         Ok(full_body) => full_body.to_bytes(),
         Err(e) => {
@@ -127,22 +209,34 @@ async fn resolve_body(req: Request<hyper::body::Incoming>) -> Value {
         }
     };
 
-    let body = match std::str::from_utf8(&body_bytes) {
-        Ok(s) => {
-            let s = s.trim().to_string();
-            if s.starts_with('{') || s.starts_with('[') {
-                Value::json_to_value(&s).unwrap_or_else(|_| s.to_value())
-            } else {
-                s.to_value()
-            }
-        }
+    match std::str::from_utf8(&body_bytes) {
+        Ok(s) if json => json_body_to_value(s),
+        Ok(s) => Ok(body_to_value(s)),
         Err(e) => {
             debug!("Error parsing request body: {:?}", e);
-            Value::Undefined
+            Ok(Value::Undefined)
         }
-    };
+    }
+}
+
+/// Parses a body sent as JSON. An empty body is a missing one.
+pub fn json_body_to_value(body: &str) -> Result<Value, String> {
+    let body = body.trim();
+
+    if body.is_empty() {
+        return Ok(Value::Undefined);
+    }
+
+    Value::json_to_value(body).map_err(|e| format!("{:?}", e))
+}
 
-    body
+pub fn body_to_value(body: &str) -> Value {
+    let body = body.trim();
+    if body.starts_with('{') || body.starts_with('[') {
+        Value::json_to_value(body).unwrap_or_else(|_| body.to_value())
+    } else {
+        body.to_value()
+    }
 }
 
 fn resolve_authorization(authorization: &str, mode: &AuthorizationSpanMode) -> String {
@@ -187,3 +281,34 @@ async fn resolve_headers(
         .collect::<HashMap<String, String>>()
         .to_value()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_json_body_to_value() {
+        assert_eq!(json_body_to_value("42").unwrap(), 42.to_value());
+        assert_eq!(json_body_to_value(" true ").unwrap(), true.to_value());
+        assert_eq!(
+            json_body_to_value(r#"{"name": "Ana"}"#).unwrap(),
+            Value::json_to_value(r#"{"name": "Ana"}"#).unwrap()
+        );
+        assert_eq!(json_body_to_value("").unwrap(), Value::Undefined);
+        assert!(json_body_to_value(r#"{"name":"#).is_err());
+    }
+
+    #[test]
+    fn test_is_json() {
+        let headers = |content_type: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(hyper::header::CONTENT_TYPE, content_type.parse().unwrap());
+            headers
+        };
+
+        assert!(is_json(&headers("application/json; charset=utf-8")));
+        assert!(is_json(&headers("application/problem+json")));
+        assert!(!is_json(&headers("text/plain")));
+        assert!(!is_json(&HeaderMap::new()));
+    }
+}
@@ -1,5 +1,4 @@
-use http_body_util::Full;
-use hyper::body::Bytes;
+use crate::resolver::{full_body, ResponseBody};
 use hyper::Response;
 use phlow_sdk::prelude::*;
 use phlow_sdk::tracing::error;
@@ -13,7 +12,7 @@ pub struct ResponseHandler {
 }
 
 impl ResponseHandler {
-    pub fn build(&self) -> Response<Full<Bytes>> {
+    pub fn build(&self) -> Response<ResponseBody> {
         let response_builder = Response::builder().status(self.status_code);
         let response_builder = self
             .headers
@@ -22,15 +21,13 @@ impl ResponseHandler {
                 builder.header(key, value)
             });
 
-        match response_builder.body(Full::new(Bytes::from(self.body.clone()))) {
+        match response_builder.body(full_body(self.body.clone())) {
             Ok(response) => response,
             Err(e) => {
                 error!("Error creating response: {:?}", e);
                 Response::builder()
                     .status(500)
-                    .body(Full::new(Bytes::from(
-                        r#"{"error": "Internal Server Error"}"#,
-                    )))
+                    .body(full_body(r#"{"error": "Internal Server Error"}"#))
                     .expect("Failed to build response")
             }
         }
@@ -0,0 +1,216 @@
+use crate::validation::BodySchema;
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Static(String),
+    Param(String),
+}
+
+/// A route declared in `with.routes`. Routes describe the API (OpenAPI) and
+/// expose `route`/`path_params` to the flow; requests that match no route still
+/// reach the flow.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub method: Option<String>,
+    pub path: String,
+    pub schema: Option<BodySchema>,
+    segments: Vec<Segment>,
+}
+
+impl Route {
+    /// `None` for entries that are not routes, an error for an invalid schema.
+    fn try_from_value(value: &Value) -> Result<Option<Self>, String> {
+        if !value.is_object() {
+            return Ok(None);
+        }
+
+        let path = match value.get("path") {
+            Some(path) => path.to_string(),
+            None => return Ok(None),
+        };
+        let method = value.get("method").map(|m| m.to_string().to_uppercase());
+        let segments = split_path(&path)
+            .map(
+                |segment| match segment.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => Segment::Param(name.to_string()),
+                    None => Segment::Static(segment.to_string()),
+                },
+            )
+            .collect();
+        let schema = match value.get("schema") {
+            Some(schema) => {
+                BodySchema::compile(schema).map_err(|e| format!("Route {}: {}", path, e))?
+            }
+            None => None,
+        };
+
+        Ok(Some(Self {
+            method,
+            path,
+            schema,
+            segments,
+        }))
+    }
+
+    fn matches(&self, method: &str, path: &str) -> Option<HashMap<String, String>> {
+        if let Some(route_method) = &self.method {
+            if route_method != method {
+                return None;
+            }
+        }
+
+        let parts: Vec<&str> = split_path(path).collect();
+
+        if parts.len() != self.segments.len() {
+            return None;
+        }
+
+        let mut params = HashMap::new();
+
+        for (segment, part) in self.segments.iter().zip(parts) {
+            match segment {
+                Segment::Static(value) if value == part => {}
+                Segment::Static(_) => return None,
+                Segment::Param(name) => {
+                    let decoded = percent_encoding::percent_decode_str(part).decode_utf8_lossy();
+                    params.insert(name.clone(), decoded.to_string());
+                }
+            }
+        }
+
+        Some(params)
+    }
+
+    fn static_segments(&self) -> usize {
+        self.segments
+            .iter()
+            .filter(|s| matches!(s, Segment::Static(_)))
+            .count()
+    }
+}
+
+fn split_path(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+    pub schema: Option<BodySchema>,
+    pub openapi_path: Option<String>,
+    pub openapi_document: String,
+}
+
+impl Router {
+    pub fn new(
+        routes: &Value,
+        schema: &Value,
+        openapi_path: Option<String>,
+        openapi_document: String,
+    ) -> Result<Self, String> {
+        let mut parsed = Vec::new();
+
+        if let Value::Array(routes) = routes {
+            for route in routes.into_iter() {
+                if let Some(route) = Route::try_from_value(route)? {
+                    parsed.push(route);
+                }
+            }
+        }
+
+        Ok(Self {
+            routes: parsed,
+            schema: BodySchema::compile(schema)?,
+            openapi_path,
+            openapi_document,
+        })
+    }
+
+    pub fn is_openapi_path(&self, path: &str) -> bool {
+        self.openapi_path.as_deref() == Some(path)
+    }
+
+    /// Finds the declared route for the request, preferring static segments over parameters.
+    pub fn find(&self, method: &str, path: &str) -> Option<(&Route, HashMap<String, String>)> {
+        self.routes
+            .iter()
+            .filter_map(|route| route.matches(method, path).map(|params| (route, params)))
+            .max_by_key(|(route, _)| route.static_segments())
+    }
+
+    /// The schema the request body must match: the route's own, otherwise the
+    /// `with.schema` default for methods that carry a body.
+    pub fn body_schema<'a>(
+        &'a self,
+        method: &str,
+        route: Option<&'a Route>,
+    ) -> Option<&'a BodySchema> {
+        match route.and_then(|route| route.schema.as_ref()) {
+            Some(schema) => Some(schema),
+            None if matches!(method, "POST" | "PUT" | "PATCH") => self.schema.as_ref(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn router() -> Router {
+        let routes = Value::json_to_value(
+            r#"[
+                {"path": "/students/{id}", "method": "get"},
+                {"path": "/students/me", "method": "GET"},
+                {"path": "/students"}
+            ]"#,
+        )
+        .unwrap();
+
+        Router::new(
+            &routes,
+            &Value::Null,
+            Some("/openapi.json".to_string()),
+            String::new(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_router_find_with_params() {
+        let router = router();
+        let (route, params) = router.find("GET", "/students/J%C3%BA").unwrap();
+
+        assert_eq!(route.path, "/students/{id}");
+        assert_eq!(params.get("id"), Some(&"Jú".to_string()));
+    }
+
+    #[test]
+    fn test_router_prefers_static_segments() {
+        let router = router();
+        let (route, params) = router.find("GET", "/students/me").unwrap();
+
+        assert_eq!(route.path, "/students/me");
+        assert!(params.is_empty());
+    }
+
+    #[test]
+    fn test_router_method_and_miss() {
+        let router = router();
+
+        assert!(router.find("POST", "/students/10").is_none());
+        assert!(router.find("POST", "/students").is_some());
+        assert!(router.find("GET", "/teachers").is_none());
+        assert!(router.is_openapi_path("/openapi.json"));
+    }
+
+    #[test]
+    fn test_router_invalid_schema() {
+        let routes =
+            Value::json_to_value(r#"[{"path": "/students", "schema": {"type": 1}}]"#).unwrap();
+
+        assert!(Router::new(&routes, &Value::Null, None, String::new()).is_err());
+    }
+}
@@ -4,6 +4,33 @@ use phlow_sdk::prelude::*;
 pub struct Config {
     pub port: Option<u16>,
     pub host: Option<String>,
+    pub coerce_query_params: bool,
+    pub websocket_paths: Vec<String>,
+    pub sse_paths: Vec<String>,
+    pub routes: Value,
+    pub schema: Value,
+    pub openapi_path: Option<String>,
+    pub health_path: Option<String>,
+    pub readiness_path: Option<String>,
+    pub metrics_path: Option<String>,
+}
+
+fn paths(value: &Value, key: &str) -> Vec<String> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|paths| paths.into_iter().map(|path| path.to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// An endpoint path with a default; an empty string, `false` or `null` disables it.
+fn optional_path(value: &Value, key: &str, default: Option<&str>) -> Option<String> {
+    match value.get(key) {
+        Some(Value::String(path)) if path.as_str().is_empty() => None,
+        Some(Value::Boolean(false)) | Some(Value::Null) => None,
+        Some(path) => Some(path.to_string()),
+        None => default.map(|path| path.to_string()),
+    }
 }
 
 impl From<Value> for Config {
@@ -12,6 +39,15 @@ impl From<Value> for Config {
             return Config {
                 port: Some(3000),
                 host: Some("0.0.0.0".to_string()),
+                coerce_query_params: false,
+                websocket_paths: Vec::new(),
+                sse_paths: Vec::new(),
+                routes: Value::Null,
+                schema: Value::Null,
+                openapi_path: Some("/openapi.json".to_string()),
+                health_path: Some("/health".to_string()),
+                readiness_path: Some("/ready".to_string()),
+                metrics_path: None,
             };
         }
 
@@ -25,6 +61,26 @@ impl From<Value> for Config {
             None => Some("0.0.0.0".to_string()),
         };
 
-        Config { port, host }
+        let coerce_query_params = *value
+            .get("coerce_query_params")
+            .and_then(Value::as_bool)
+            .unwrap_or(&false);
+
+        let routes = value.get("routes").cloned().unwrap_or(Value::Null);
+        let schema = value.get("schema").cloned().unwrap_or(Value::Null);
+
+        Config {
+            port,
+            host,
+            coerce_query_params,
+            websocket_paths: paths(&value, "websocket_paths"),
+            sse_paths: paths(&value, "sse_paths"),
+            routes,
+            schema,
+            openapi_path: optional_path(&value, "openapi_path", Some("/openapi.json")),
+            health_path: optional_path(&value, "health_path", Some("/health")),
+            readiness_path: optional_path(&value, "readiness_path", Some("/ready")),
+            metrics_path: optional_path(&value, "metrics_path", None),
+        }
     }
 }
@@ -0,0 +1,125 @@
+use crate::{
+    middleware::RequestContext,
+    resolver::ResponseBody,
+    stream::{value_to_text, StreamKind, StreamMessage, Streams},
+};
+use bytes::Bytes;
+use http_body_util::{BodyExt, StreamBody};
+use hyper::{body::Frame, Response};
+use phlow_sdk::prelude::*;
+use std::convert::Infallible;
+
+/// Removes the connection from the registry once the client goes away and
+/// hyper drops the response body.
+struct ConnectionGuard {
+    streams: Streams,
+    id: String,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.streams.remove(&self.id);
+        debug!("SSE connection {} closed", self.id);
+    }
+}
+
+fn format_event(event: Option<&str>, id: Option<&str>, data: &str) -> String {
+    let mut out = String::new();
+
+    if let Some(event) = event {
+        out.push_str(&format!("event: {}\n", event));
+    }
+
+    if let Some(id) = id {
+        out.push_str(&format!("id: {}\n", id));
+    }
+
+    if data.is_empty() {
+        out.push_str("data: \n");
+    } else {
+        for line in data.lines() {
+            out.push_str(&format!("data: {}\n", line));
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+/// Opens an event stream for the request. The flow runs in the background with
+/// `connection_id` in `main`, writes events through the `http_server` step and its
+/// return value, when not null, becomes one more event.
+pub fn stream(request: Value, path: String, context: RequestContext) -> Response<ResponseBody> {
+    let (connection_id, receiver) = context.streams.register(StreamKind::Sse, &path);
+
+    debug!("SSE connection {} opened on {}", connection_id, path);
+
+    let mut data = request;
+    data.insert("connection_id", connection_id.to_value());
+
+    {
+        let streams = context.streams.clone();
+        let connection_id = connection_id.clone();
+
+        tokio::task::spawn(async move {
+            let response = sender_package!(
+                context.span.clone(),
+                context.dispatch.clone(),
+                context.id,
+                context.sender,
+                Some(data)
+            )
+            .await
+            .unwrap_or(Value::Null);
+
+            streams.send(&connection_id, StreamMessage::data(response));
+        });
+    }
+
+    let guard = ConnectionGuard {
+        streams: context.streams.clone(),
+        id: connection_id,
+    };
+
+    let events =
+        futures_util::stream::unfold((receiver, guard), |(mut receiver, guard)| async move {
+            loop {
+                match receiver.recv().await {
+                    Some(StreamMessage::Data { event, id, data }) => {
+                        let text = match value_to_text(&data) {
+                            Some(text) => text,
+                            None => continue,
+                        };
+                        let chunk = format_event(event.as_deref(), id.as_deref(), &text);
+
+                        return Some((
+                            Ok::<_, Infallible>(Frame::data(Bytes::from(chunk))),
+                            (receiver, guard),
+                        ));
+                    }
+                    Some(StreamMessage::Close) | None => return None,
+                }
+            }
+        });
+
+    Response::builder()
+        .status(200)
+        .header("content-type", "text/event-stream")
+        .header("cache-control", "no-cache")
+        .body(BodyExt::boxed_unsync(StreamBody::new(events)))
+        .expect("Failed to build event stream response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_event() {
+        assert_eq!(format_event(None, None, "hello"), "data: hello\n\n");
+        assert_eq!(
+            format_event(Some("update"), Some("7"), "line 1\nline 2"),
+            "event: update\nid: 7\ndata: line 1\ndata: line 2\n\n"
+        );
+    }
+}
@@ -0,0 +1,231 @@
+use phlow_sdk::prelude::*;
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StreamKind {
+    WebSocket,
+    Sse,
+}
+
+#[derive(Debug, Clone)]
+pub enum StreamMessage {
+    Data {
+        event: Option<String>,
+        id: Option<String>,
+        data: Box<Value>,
+    },
+    Close,
+}
+
+impl StreamMessage {
+    pub fn data(data: Value) -> Self {
+        StreamMessage::Data {
+            event: None,
+            id: None,
+            data: Box::new(data),
+        }
+    }
+}
+
+impl From<&Value> for StreamMessage {
+    fn from(value: &Value) -> Self {
+        if let Some(Value::Boolean(true)) = value.get("close") {
+            return StreamMessage::Close;
+        }
+
+        StreamMessage::Data {
+            event: value.get("event").map(|v| v.to_string()),
+            id: value.get("id").map(|v| v.to_string()),
+            data: Box::new(value.get("data").cloned().unwrap_or(Value::Null)),
+        }
+    }
+}
+
+/// Text sent over the wire for a value: strings as-is, everything else as JSON.
+/// `null`/`undefined` produce nothing.
+pub fn value_to_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null | Value::Undefined => None,
+        Value::String(s) => Some(s.as_string()),
+        value => Some(value.to_json(JsonMode::Inline)),
+    }
+}
+
+#[derive(Debug)]
+struct StreamConnection {
+    kind: StreamKind,
+    path: String,
+    sender: mpsc::UnboundedSender<StreamMessage>,
+}
+
+/// Open WebSocket and SSE connections, addressable by connection id so that
+/// flows can keep writing to them after the initial request.
+#[derive(Debug, Clone, Default)]
+pub struct Streams {
+    connections: Arc<Mutex<HashMap<String, StreamConnection>>>,
+    pub websocket_paths: Arc<Vec<String>>,
+    pub sse_paths: Arc<Vec<String>>,
+}
+
+impl Streams {
+    pub fn new(websocket_paths: Vec<String>, sse_paths: Vec<String>) -> Self {
+        Self {
+            connections: Arc::new(Mutex::new(HashMap::new())),
+            websocket_paths: Arc::new(websocket_paths),
+            sse_paths: Arc::new(sse_paths),
+        }
+    }
+
+    pub fn is_websocket_path(&self, path: &str) -> bool {
+        self.websocket_paths.iter().any(|p| p == path)
+    }
+
+    pub fn is_sse_path(&self, path: &str) -> bool {
+        self.sse_paths.iter().any(|p| p == path)
+    }
+
+    pub fn register(
+        &self,
+        kind: StreamKind,
+        path: &str,
+    ) -> (String, mpsc::UnboundedReceiver<StreamMessage>) {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let id = uuid::Uuid::new_v4().to_string();
+
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.insert(
+                id.clone(),
+                StreamConnection {
+                    kind,
+                    path: path.to_string(),
+                    sender,
+                },
+            );
+        }
+
+        (id, receiver)
+    }
+
+    pub fn remove(&self, id: &str) {
+        if let Ok(mut connections) = self.connections.lock() {
+            connections.remove(id);
+        }
+    }
+
+    pub fn send(&self, id: &str, message: StreamMessage) -> bool {
+        let sender = match self.connections.lock() {
+            Ok(connections) => connections.get(id).map(|c| c.sender.clone()),
+            Err(_) => None,
+        };
+
+        match sender {
+            Some(sender) => {
+                if sender.send(message).is_err() {
+                    self.remove(id);
+                    return false;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Sends the message to every open connection, optionally restricted to
+    /// connections opened on `path` and of the given `kind`.
+    pub fn broadcast(
+        &self,
+        path: Option<&str>,
+        kind: Option<&StreamKind>,
+        message: StreamMessage,
+    ) -> usize {
+        let targets: Vec<String> = match self.connections.lock() {
+            Ok(connections) => connections
+                .iter()
+                .filter(|(_, c)| path.is_none_or(|path| c.path == path))
+                .filter(|(_, c)| kind.is_none_or(|kind| &c.kind == kind))
+                .map(|(id, _)| id.clone())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+
+        targets
+            .iter()
+            .filter(|id| self.send(id, message.clone()))
+            .count()
+    }
+}
+
+/// Handles the step side of the module: `use: http_server` pushes `data` to an
+/// open WebSocket/SSE connection, or to all of them when no `connection_id` is set.
+pub fn resolve_stream_package(package: ModulePackage, streams: &Streams) {
+    let input = match package.input() {
+        Some(input) if input.is_object() => input,
+        _ => {
+            sender_safe!(
+                package.sender,
+                ModuleResponse::from_error("No input provided".to_string())
+            );
+            return;
+        }
+    };
+
+    let message = StreamMessage::from(&input);
+
+    let delivered = match input.get("connection_id") {
+        Some(id) => usize::from(streams.send(&id.to_string(), message)),
+        None => {
+            let path = input.get("path").map(|v| v.to_string());
+            let kind = match input.get("kind").map(|v| v.to_string()).as_deref() {
+                Some("websocket") => Some(StreamKind::WebSocket),
+                Some("sse") => Some(StreamKind::Sse),
+                _ => None,
+            };
+
+            streams.broadcast(path.as_deref(), kind.as_ref(), message)
+        }
+    };
+
+    sender_safe!(
+        package.sender,
+        HashMap::from([("delivered", delivered.to_value())])
+            .to_value()
+            .into()
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streams_send_and_broadcast() {
+        let streams = Streams::new(vec!["/ws".to_string()], vec!["/events".to_string()]);
+        let (ws_id, mut ws_rx) = streams.register(StreamKind::WebSocket, "/ws");
+        let (_sse_id, mut sse_rx) = streams.register(StreamKind::Sse, "/events");
+
+        assert!(streams.send(&ws_id, StreamMessage::data("hello".to_value())));
+        assert!(!streams.send("unknown", StreamMessage::data(Value::Null)));
+        assert!(matches!(ws_rx.try_recv(), Ok(StreamMessage::Data { .. })));
+
+        let delivered = streams.broadcast(Some("/events"), None, StreamMessage::Close);
+
+        assert_eq!(delivered, 1);
+        assert!(matches!(sse_rx.try_recv(), Ok(StreamMessage::Close)));
+        assert!(ws_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_streams_remove_closed_receiver() {
+        let streams = Streams::default();
+        let (id, rx) = streams.register(StreamKind::Sse, "/events");
+
+        drop(rx);
+
+        assert!(!streams.send(&id, StreamMessage::data(Value::Null)));
+        assert_eq!(streams.broadcast(None, None, StreamMessage::Close), 0);
+    }
+}
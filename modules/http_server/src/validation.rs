@@ -0,0 +1,180 @@
+use crate::resolver::{full_body, ResponseBody};
+use hyper::Response;
+use jsonschema::Validator;
+use phlow_sdk::prelude::*;
+use std::{collections::HashMap, sync::Arc};
+
+/// A compiled request body schema, declared as `schema` on a route or on `with`.
+#[derive(Debug, Clone)]
+pub struct BodySchema {
+    validator: Arc<Validator>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub pointer: String,
+    pub schema_path: String,
+    pub message: String,
+}
+
+impl ToValueBehavior for Violation {
+    fn to_value(&self) -> Value {
+        HashMap::from([
+            ("pointer", self.pointer.to_value()),
+            ("schema_path", self.schema_path.to_value()),
+            ("message", self.message.to_value()),
+        ])
+        .to_value()
+    }
+}
+
+fn to_json(value: &Value) -> serde_json::Value {
+    serde_json::from_str(&value.to_json(JsonMode::Inline)).unwrap_or(serde_json::Value::Null)
+}
+
+impl BodySchema {
+    /// Compiles the schema, `None` when no schema is declared.
+    pub fn compile(schema: &Value) -> Result<Option<Self>, String> {
+        if !schema.is_object() {
+            return Ok(None);
+        }
+
+        let validator = jsonschema::validator_for(&to_json(schema))
+            .map_err(|e| format!("Invalid request body schema: {}", e))?;
+
+        Ok(Some(Self {
+            validator: Arc::new(validator),
+        }))
+    }
+
+    /// Checks the request body and lists every violation by JSON pointer.
+    /// A missing body is validated as `null`, so the schema decides whether
+    /// it is optional.
+    pub fn validate(&self, body: &Value) -> Result<(), Vec<Violation>> {
+        let instance = match is_missing(body) {
+            true => serde_json::Value::Null,
+            false => to_json(body),
+        };
+        let violations: Vec<Violation> = self
+            .validator
+            .iter_errors(&instance)
+            .map(|error| Violation {
+                pointer: error.instance_path.to_string(),
+                schema_path: error.schema_path.to_string(),
+                message: error.to_string(),
+            })
+            .collect();
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+fn is_missing(body: &Value) -> bool {
+    matches!(body, Value::Undefined | Value::Null)
+        || matches!(body, Value::String(s) if s.as_str().is_empty())
+}
+
+/// `400` when a body sent as JSON does not parse.
+pub fn invalid_json_response(error: &str) -> Response<ResponseBody> {
+    let body = HashMap::from([
+        ("error", "invalid_json".to_value()),
+        ("message", format!("Invalid JSON: {}", error).to_value()),
+    ])
+    .to_value();
+
+    Response::builder()
+        .status(400)
+        .header("content-type", "application/json")
+        .body(full_body(body.to_json(JsonMode::Inline)))
+        .expect("Failed to build invalid JSON response")
+}
+
+/// `400` when the body is missing, `422` when it does not match the schema.
+pub fn violations_response(violations: &[Violation], body: &Value) -> Response<ResponseBody> {
+    let status = if is_missing(body) { 400 } else { 422 };
+
+    let body = HashMap::from([
+        ("error", "validation_failed".to_value()),
+        (
+            "message",
+            "Request body does not match the schema".to_value(),
+        ),
+        ("violations", violations.to_vec().to_value()),
+    ])
+    .to_value();
+
+    Response::builder()
+        .status(status)
+        .header("content-type", "application/json")
+        .body(full_body(body.to_json(JsonMode::Inline)))
+        .expect("Failed to build validation response")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> BodySchema {
+        let schema = Value::json_to_value(
+            r#"{
+                "type": "object",
+                "required": ["name", "age"],
+                "properties": {
+                    "name": {"type": "string"},
+                    "age": {"type": "integer", "minimum": 0},
+                    "tags": {"type": "array", "items": {"type": "string"}}
+                }
+            }"#,
+        )
+        .unwrap();
+
+        BodySchema::compile(&schema).unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_validate_lists_every_violation() {
+        let body = Value::json_to_value(r#"{"age": -1, "tags": ["a", 2]}"#).unwrap();
+        let violations = schema().validate(&body).unwrap_err();
+        let pointers: Vec<&str> = violations.iter().map(|v| v.pointer.as_str()).collect();
+
+        assert_eq!(violations.len(), 3);
+        assert!(pointers.contains(&""));
+        assert!(pointers.contains(&"/age"));
+        assert!(pointers.contains(&"/tags/1"));
+    }
+
+    #[test]
+    fn test_validate_valid_and_missing_body() {
+        let body = Value::json_to_value(r#"{"name": "Ana", "age": 20}"#).unwrap();
+
+        assert!(schema().validate(&body).is_ok());
+
+        let violations = schema().validate(&"".to_value()).unwrap_err();
+        assert_eq!(
+            violations_response(&violations, &"".to_value()).status(),
+            400
+        );
+    }
+
+    #[test]
+    fn test_validate_optional_body() {
+        let schema = Value::json_to_value(r#"{"type": ["object", "null"]}"#).unwrap();
+        let schema = BodySchema::compile(&schema).unwrap().unwrap();
+
+        assert!(schema.validate(&Value::Undefined).is_ok());
+        assert!(schema.validate(&"".to_value()).is_ok());
+        assert!(schema.validate(&"text".to_value()).is_err());
+    }
+
+    #[test]
+    fn test_compile_invalid_schema() {
+        let schema = Value::json_to_value(r#"{"type": "nope"}"#).unwrap();
+
+        assert!(BodySchema::compile(&schema).is_err());
+        assert!(BodySchema::compile(&Value::Null).unwrap().is_none());
+    }
+}
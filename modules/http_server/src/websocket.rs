@@ -0,0 +1,143 @@
+use crate::{
+    middleware::RequestContext,
+    resolver::{body_to_value, empty_body, ResponseBody},
+    stream::{value_to_text, StreamKind, StreamMessage},
+};
+use futures_util::{SinkExt, StreamExt};
+use hyper::{
+    body::Incoming,
+    header::{CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE},
+    upgrade::OnUpgrade,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use phlow_sdk::prelude::*;
+use tokio_tungstenite::{
+    tungstenite::{handshake::derive_accept_key, protocol::Role, Message},
+    WebSocketStream,
+};
+
+fn is_upgrade_request<B>(req: &Request<B>) -> bool {
+    let connection_upgrade = req
+        .headers()
+        .get(CONNECTION)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| {
+            v.split(',')
+                .any(|part| part.trim().eq_ignore_ascii_case("upgrade"))
+        })
+        .unwrap_or(false);
+
+    let upgrade_websocket = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.eq_ignore_ascii_case("websocket"))
+        .unwrap_or(false);
+
+    connection_upgrade && upgrade_websocket && req.headers().contains_key(SEC_WEBSOCKET_KEY)
+}
+
+/// Answers the WebSocket handshake. Returns `None` when the request is not an upgrade request.
+pub fn accept(req: &mut Request<Incoming>) -> Option<(OnUpgrade, Response<ResponseBody>)> {
+    if !is_upgrade_request(req) {
+        return None;
+    }
+
+    let accept_key = derive_accept_key(req.headers().get(SEC_WEBSOCKET_KEY)?.as_bytes());
+    let on_upgrade = hyper::upgrade::on(req);
+
+    let response = Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(CONNECTION, "upgrade")
+        .header(UPGRADE, "websocket")
+        .header(SEC_WEBSOCKET_ACCEPT, accept_key)
+        .body(empty_body())
+        .ok()?;
+
+    Some((on_upgrade, response))
+}
+
+/// Runs the upgraded connection: every inbound message is sent to the flow as a
+/// package and the flow's return value is written back as a frame.
+pub fn serve(on_upgrade: OnUpgrade, request: Value, path: String, context: RequestContext) {
+    tokio::task::spawn(async move {
+        let upgraded = match on_upgrade.await {
+            Ok(upgraded) => upgraded,
+            Err(e) => {
+                debug!("WebSocket upgrade failed: {:?}", e);
+                return;
+            }
+        };
+
+        let socket =
+            WebSocketStream::from_raw_socket(TokioIo::new(upgraded), Role::Server, None).await;
+        let (mut sink, mut stream) = socket.split();
+        let (connection_id, mut receiver) = context.streams.register(StreamKind::WebSocket, &path);
+
+        debug!("WebSocket connection {} opened on {}", connection_id, path);
+
+        tokio::task::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let frame = match message {
+                    StreamMessage::Data { data, .. } => match value_to_text(&data) {
+                        Some(text) => Message::text(text),
+                        None => continue,
+                    },
+                    StreamMessage::Close => {
+                        let _ = sink.send(Message::Close(None)).await;
+                        break;
+                    }
+                };
+
+                if sink.send(frame).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        while let Some(message) = stream.next().await {
+            let body = match message {
+                Ok(Message::Text(text)) => body_to_value(text.as_str()),
+                Ok(Message::Binary(bytes)) => body_to_value(&String::from_utf8_lossy(&bytes)),
+                Ok(Message::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    debug!("WebSocket connection {} error: {:?}", connection_id, e);
+                    break;
+                }
+            };
+
+            let mut data = request.clone();
+            data.insert("connection_id", connection_id.to_value());
+            data.insert("body", body);
+
+            let span = phlow_sdk::tracing::dispatcher::with_default(&context.dispatch, || {
+                tracing::info_span!(
+                    parent: &context.span,
+                    "websocket_message",
+                    otel.name = format!("WS {}", path),
+                    websocket.connection_id = connection_id.as_str(),
+                )
+            });
+
+            let response = sender_package!(
+                span,
+                context.dispatch.clone(),
+                context.id,
+                context.sender,
+                Some(data)
+            )
+            .await
+            .unwrap_or(Value::Null);
+
+            context
+                .streams
+                .send(&connection_id, StreamMessage::data(response));
+        }
+
+        context.streams.remove(&connection_id);
+
+        debug!("WebSocket connection {} closed", connection_id);
+    });
+}
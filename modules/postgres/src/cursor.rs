@@ -0,0 +1,272 @@
+use crate::input::Input;
+use crate::query;
+use crate::response::QueryResult;
+use deadpool_postgres::{Object, Pool};
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_postgres::Row;
+
+const CURSOR_NAME: &str = "phlow_cursor";
+
+/// Open cursors are closed after this long without a fetch.
+pub const CURSOR_IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A server-side cursor on its own pooled connection, inside a read-only
+/// transaction that lasts until the cursor is closed.
+pub struct Cursor {
+    client: Object,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Closes the connection instead of returning it to the pool, since it may
+/// still be inside the cursor's transaction.
+fn discard(client: Object) {
+    drop(Object::take(client));
+}
+
+impl Cursor {
+    async fn declare(
+        pool: &Pool,
+        input: &Input,
+        permit: OwnedSemaphorePermit,
+    ) -> Result<Self, String> {
+        let client = pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get client from pool: {}", e))?;
+
+        if let Err(e) = client.batch_execute("BEGIN READ ONLY").await {
+            discard(client);
+            return Err(format!("Failed to start transaction: {}", e));
+        }
+
+        let declare = format!(
+            "DECLARE {} NO SCROLL CURSOR FOR {}",
+            CURSOR_NAME, input.query
+        );
+        let declared = match query::prepare(&client, &declare, input).await {
+            Ok(stmt) => client
+                .execute(&stmt, &query::param_refs(input))
+                .await
+                .map_err(|e| format!("Failed to declare cursor: {}", e)),
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = declared {
+            discard(client);
+            return Err(e);
+        }
+
+        Ok(Self {
+            client,
+            _permit: permit,
+        })
+    }
+
+    /// Fetches the next `fetch_size` rows. The cursor is closed when the page
+    /// comes back short, in which case `None` is returned in its place.
+    pub async fn fetch(self, fetch_size: usize) -> Result<(Vec<Row>, Option<Self>), String> {
+        let rows = match self
+            .client
+            .query(&format!("FETCH {} FROM {}", fetch_size, CURSOR_NAME), &[])
+            .await
+        {
+            Ok(rows) => rows,
+            Err(e) => {
+                discard(self.client);
+                return Err(format!("Failed to fetch from cursor: {}", e));
+            }
+        };
+
+        if rows.len() == fetch_size {
+            return Ok((rows, Some(self)));
+        }
+
+        if let Err(e) = self
+            .client
+            .batch_execute(&format!("CLOSE {}; COMMIT", CURSOR_NAME))
+            .await
+        {
+            tracing::warn!("Failed to close cursor: {}", e);
+            discard(self.client);
+        }
+
+        Ok((rows, None))
+    }
+}
+
+struct OpenCursor {
+    cursor: Cursor,
+    fetch_size: usize,
+    last_fetch: Instant,
+}
+
+/// Server-side cursors kept open between steps. Each one holds a pooled
+/// connection with an open transaction until its last page has been fetched
+/// or it expires, so at most `max_open` of them are open at a time.
+#[derive(Clone)]
+pub struct Cursors {
+    open: Arc<Mutex<HashMap<String, OpenCursor>>>,
+    permits: Arc<Semaphore>,
+    max_open: usize,
+}
+
+impl Cursors {
+    pub fn new(max_open: usize) -> Self {
+        Self {
+            open: Arc::default(),
+            permits: Arc::new(Semaphore::new(max_open)),
+            max_open,
+        }
+    }
+
+    /// Declares a cursor for the query, failing when `max_open` are open.
+    pub async fn declare(&self, pool: &Pool, input: &Input) -> Result<Cursor, String> {
+        let permit = self.permits.clone().try_acquire_owned().map_err(|_| {
+            format!(
+                "Too many open cursors ({}), read them to the end or let them expire",
+                self.max_open
+            )
+        })?;
+
+        Cursor::declare(pool, input, permit).await
+    }
+
+    /// Declares a cursor for the query and returns its first page.
+    pub async fn open(
+        &self,
+        pool: &Pool,
+        input: &Input,
+        fetch_size: usize,
+    ) -> Result<Value, String> {
+        let cursor = self.declare(pool, input).await?;
+        self.fetch(cursor, fetch_size).await
+    }
+
+    /// Returns the next page of the cursor behind a continuation token.
+    pub async fn next(&self, token: &str, fetch_size: Option<usize>) -> Result<Value, String> {
+        let cursor = self
+            .open
+            .lock()
+            .map_err(|e| e.to_string())?
+            .remove(token)
+            .ok_or_else(|| format!("Cursor not found or expired: {}", token))?;
+
+        self.fetch(cursor.cursor, fetch_size.unwrap_or(cursor.fetch_size))
+            .await
+    }
+
+    async fn fetch(&self, cursor: Cursor, fetch_size: usize) -> Result<Value, String> {
+        let (rows, cursor) = cursor.fetch(fetch_size).await?;
+        let mut result = QueryResult::from(rows).to_value();
+
+        let cursor = match cursor {
+            Some(cursor) => cursor,
+            None => {
+                result.insert("cursor", Value::Null);
+                return Ok(result);
+            }
+        };
+
+        let token = uuid::Uuid::new_v4().to_string();
+
+        self.open.lock().map_err(|e| e.to_string())?.insert(
+            token.clone(),
+            OpenCursor {
+                cursor,
+                fetch_size,
+                last_fetch: Instant::now(),
+            },
+        );
+
+        result.insert("cursor", token);
+        Ok(result)
+    }
+
+    /// Drops the cursors that have not been fetched from within the idle timeout.
+    pub fn expire(&self) {
+        let expired: Vec<OpenCursor> = match self.open.lock() {
+            Ok(mut open) => {
+                let tokens: Vec<String> = open
+                    .iter()
+                    .filter(|(_, cursor)| cursor.last_fetch.elapsed() > CURSOR_IDLE_TIMEOUT)
+                    .map(|(token, _)| token.clone())
+                    .collect();
+
+                tokens
+                    .iter()
+                    .filter_map(|token| open.remove(token))
+                    .collect()
+            }
+            Err(_) => return,
+        };
+
+        for open in expired {
+            debug!("Closing idle cursor");
+            discard(open.cursor.client);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::{self, input};
+
+    fn values(page: &Value) -> Vec<Value> {
+        match page.get("rows") {
+            Some(Value::Array(rows)) => rows
+                .into_iter()
+                .map(|row| row.get("i").cloned().unwrap())
+                .collect(),
+            _ => panic!("No rows in {:?}", page),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pages() {
+        let Some(pool) = test_db::pool(3) else {
+            return;
+        };
+        let cursors = Cursors::new(2);
+        let query = input(r#"{"query": "SELECT i FROM generate_series(1, 5) AS i"}"#);
+
+        let page = cursors.open(&pool, &query, 2).await.unwrap();
+        assert_eq!(values(&page), vec![1.to_value(), 2.to_value()]);
+
+        let token = page.get("cursor").unwrap().to_string();
+        let page = cursors.next(&token, None).await.unwrap();
+        assert_eq!(values(&page), vec![3.to_value(), 4.to_value()]);
+
+        // The page size can change between fetches.
+        let token = page.get("cursor").unwrap().to_string();
+        let page = cursors.next(&token, Some(10)).await.unwrap();
+        assert_eq!(values(&page), vec![5.to_value()]);
+        assert_eq!(page.get("cursor"), Some(&Value::Null));
+
+        assert!(cursors.next(&token, None).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cursor_limit() {
+        let Some(pool) = test_db::pool(2) else {
+            return;
+        };
+        let cursors = Cursors::new(1);
+        let query = input(r#"{"query": "SELECT i FROM generate_series(1, 5) AS i"}"#);
+
+        let page = cursors.open(&pool, &query, 2).await.unwrap();
+        let error = cursors.open(&pool, &query, 2).await.unwrap_err();
+        assert!(error.starts_with("Too many open cursors (1)"), "{}", error);
+
+        // Reading the first cursor to the end frees its slot.
+        let token = page.get("cursor").unwrap().to_string();
+        let page = cursors.next(&token, Some(5)).await.unwrap();
+        assert_eq!(page.get("cursor"), Some(&Value::Null));
+
+        assert!(cursors.open(&pool, &query, 2).await.is_ok());
+    }
+}
@@ -1,14 +1,31 @@
 use phlow_sdk::prelude::*;
-use tokio_postgres::types::ToSql;
+use tokio_postgres::types::Type;
 
+use crate::params::{named_placeholders, Param};
 use crate::postgres::PostgresConfig;
 
 #[derive(Debug)]
 pub struct Input {
     pub query: String,
-    pub params: Vec<Box<dyn ToSql + Sync + Send>>,
+    pub params: Vec<Param>,
     pub batch: bool,
     pub cache_query: bool,
+    /// Rows read from the result; the server stops sending the rest and
+    /// `truncated` is set.
+    pub max_rows: Option<usize>,
+    /// Page size for cursor pagination.
+    pub fetch_size: Option<usize>,
+}
+
+/// Reads an optional row count, which must be a positive integer.
+pub fn row_limit(value: &Value, key: &str) -> Result<Option<usize>, String> {
+    match value.get(key) {
+        None | Some(Value::Null) => Ok(None),
+        Some(limit) => match limit.to_u64() {
+            Some(limit) if limit > 0 => Ok(Some(limit as usize)),
+            _ => Err(format!("{} must be a positive integer", key)),
+        },
+    }
 }
 
 impl TryFrom<(Option<Value>, &PostgresConfig)> for Input {
@@ -24,30 +41,30 @@ impl TryFrom<(Option<Value>, &PostgresConfig)> for Input {
             .ok_or_else(|| "Query not found or not a string".to_string())?
             .to_string();
 
-        let params = value
-            .get("params")
-            .clone()
-            .and_then(|v| v.as_array().cloned()) // <- clona os valores do array
-            .map(|arr| {
-                arr.into_iter()
-                    .map(|v| {
-                        if let Some(n) = v.to_i64() {
-                            if n >= i32::MIN as i64 && n <= i32::MAX as i64 {
-                                Ok::<_, String>(Box::new(n as i32) as Box<dyn ToSql + Sync + Send>)
-                            } else {
-                                Ok::<_, String>(Box::new(n) as Box<dyn ToSql + Sync + Send>)
-                            }
-                        } else if let Some(f) = v.to_f64() {
-                            Ok::<_, String>(Box::new(f) as Box<dyn ToSql + Sync + Send>)
-                        } else if let Some(b) = v.as_bool() {
-                            Ok::<_, String>(Box::new(b.clone()) as Box<dyn ToSql + Sync + Send>)
-                        } else {
-                            Ok::<_, String>(Box::new(v.to_string()) as Box<dyn ToSql + Sync + Send>)
-                        }
+        let (query, params) = match value.get("params") {
+            // Named parameters: `:name` placeholders resolved from an object.
+            Some(Value::Object(named)) => {
+                let (query, names) = named_placeholders(&query);
+                let params = names
+                    .iter()
+                    .map(|name| match named.get(name.as_str()) {
+                        Some(value) => Param::try_from(value),
+                        None => Err(format!("Missing parameter: {}", name)),
                     })
-                    .collect::<Result<Vec<_>, _>>()
-            })
-            .unwrap_or_else(|| Ok(vec![]))?;
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                (query, params)
+            }
+            Some(Value::Array(positional)) => {
+                let params = positional
+                    .into_iter()
+                    .map(Param::try_from)
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                (query, params)
+            }
+            _ => (query, Vec::new()),
+        };
 
         let prepare_statements = *value
             .get("batch")
@@ -64,6 +81,75 @@ impl TryFrom<(Option<Value>, &PostgresConfig)> for Input {
             params,
             batch: prepare_statements,
             cache_query,
+            max_rows: row_limit(&value, "max_rows")?,
+            fetch_size: row_limit(&value, "fetch_size")?,
         })
     }
 }
+
+impl Input {
+    /// Parameter types to prepare the statement with, when any parameter has a
+    /// type hint. Unhinted parameters are left for the server to infer.
+    pub fn param_types(&self) -> Option<Vec<Type>> {
+        if self.params.iter().all(|param| param.type_hint.is_none()) {
+            return None;
+        }
+
+        Some(
+            self.params
+                .iter()
+                .map(|param| param.type_hint.clone().unwrap_or(Type::UNKNOWN))
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::postgres_config;
+
+    #[test]
+    fn test_input_named_params() {
+        let value = Value::json_to_value(
+            r#"{
+                "query": "UPDATE students SET data = :data WHERE id = :id AND :id IS NOT NULL",
+                "params": {"id": {"value": "550e8400-e29b-41d4-a716-446655440000", "type": "uuid"}, "data": null}
+            }"#,
+        )
+        .unwrap();
+        let input = Input::try_from((Some(value), &postgres_config())).unwrap();
+
+        assert_eq!(
+            input.query,
+            "UPDATE students SET data = $1 WHERE id = $2 AND $2 IS NOT NULL"
+        );
+        assert_eq!(input.params[0].value, Value::Null);
+        assert_eq!(input.param_types(), Some(vec![Type::UNKNOWN, Type::UUID]));
+    }
+
+    #[test]
+    fn test_input_missing_named_param() {
+        let value =
+            Value::json_to_value(r#"{"query": "SELECT :id", "params": {"other": 1}}"#).unwrap();
+
+        assert!(Input::try_from((Some(value), &postgres_config())).is_err());
+    }
+
+    #[test]
+    fn test_input_row_limits() {
+        let value = Value::json_to_value(
+            r#"{"query": "SELECT * FROM events", "max_rows": 500, "fetch_size": 100}"#,
+        )
+        .unwrap();
+        let input = Input::try_from((Some(value), &postgres_config())).unwrap();
+
+        assert_eq!(input.max_rows, Some(500));
+        assert_eq!(input.fetch_size, Some(100));
+
+        let value =
+            Value::json_to_value(r#"{"query": "SELECT * FROM events", "fetch_size": 0}"#).unwrap();
+
+        assert!(Input::try_from((Some(value), &postgres_config())).is_err());
+    }
+}
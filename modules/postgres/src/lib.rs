@@ -1,114 +1,133 @@
+mod cursor;
 mod input;
+mod listener;
+mod migrations;
+mod money;
+mod params;
 mod postgres;
+mod query;
 mod response;
-use std::sync::Arc;
-
-use input::Input;
+mod stream;
+#[cfg(test)]
+mod test_db;
+mod tls;
+mod transaction;
+use std::{sync::Arc, time::Duration};
+
+use cursor::{Cursors, CURSOR_IDLE_TIMEOUT};
+use deadpool_postgres::Pool;
+use input::{row_limit, Input};
+use migrations::MigrationAction;
 use phlow_sdk::prelude::*;
 use postgres::PostgresConfig;
-use response::QueryResult;
-use tokio_postgres::types::ToSql;
+use transaction::TransactionInput;
 
 create_step!(postgres(setup));
 
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
 pub async fn postgres(setup: ModuleSetup) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let rx = module_channel!(setup);
-    let config = PostgresConfig::try_from(setup.with.clone())?;
+    let mut config = PostgresConfig::try_from(setup.with.clone())?;
+    config.migrations = config
+        .migrations
+        .map(|dir| migrations::resolve_dir(&dir, setup.app_data.base_path.as_deref()));
     let pool = Arc::new(config.create_pool()?);
+    let stream = config
+        .stream
+        .clone()
+        .map(|value| Input::try_from((Some(value), &config)))
+        .transpose()
+        .map_err(|e| format!("Invalid stream: {}", e))?;
+    let main_sender = setup.main_sender.clone();
+
+    if main_sender.is_some() && config.listen.is_empty() && stream.is_none() {
+        return Err(
+            "The postgres main module needs at least one channel in listen or a stream".into(),
+        );
+    }
+
+    // Pending migrations are applied before the module registers, so no flow
+    // runs against an outdated schema.
+    if let (Some(dir), true) = (&config.migrations, config.auto_migrate) {
+        let mut client = pool.get().await?;
+        migrations::run(&mut client, dir, &MigrationAction::Up).await?;
+    }
+
+    let rx = module_channel!(setup);
+
+    {
+        let pool = pool.clone();
+        setup.health.check_every(HEALTH_CHECK_INTERVAL, move || {
+            let pool = pool.clone();
+            async move {
+                // The check also keeps the MONEY scale in step with the server.
+                match pool.get().await {
+                    Ok(client) => match client.query_one(money::SCALE_QUERY, &[]).await {
+                        Ok(row) => {
+                            money::set_scale(row.get::<_, i32>(0) as u32);
+                            HealthStatus::Up
+                        }
+                        Err(e) => HealthStatus::Down(e.to_string()),
+                    },
+                    Err(e) => HealthStatus::Down(e.to_string()),
+                }
+            }
+        });
+    }
+
+    // Each cursor holds a pooled connection, so one is always left for the
+    // other queries.
+    let cursors = Cursors::new(config.max_size.saturating_sub(1));
+
+    {
+        let cursors = cursors.clone();
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(CURSOR_IDLE_TIMEOUT / 2).await;
+                cursors.expire();
+            }
+        });
+    }
+
+    if let Some(main_sender) = main_sender {
+        if !config.listen.is_empty() {
+            tokio::spawn(listener::listen(
+                setup.id,
+                main_sender.clone(),
+                setup.dispatch.clone(),
+                config.clone(),
+            ));
+        }
+
+        if let Some(input) = stream {
+            let pool = pool.clone();
+            let cursors = cursors.clone();
+            let dispatch = setup.dispatch.clone();
+
+            tokio::spawn(async move {
+                match stream::stream(setup.id, main_sender, dispatch, &pool, &cursors, input).await
+                {
+                    Ok(rows) => info!("Stream finished after {} rows", rows),
+                    Err(e) => error!("Stream failed: {}", e),
+                }
+            });
+        }
+    }
 
     let mut handles = Vec::new();
 
     for package in rx {
         let pool = pool.clone();
         let config = config.clone();
+        let cursors = cursors.clone();
 
         let handle = tokio::spawn(async move {
-            let input = match Input::try_from((package.context.input, &config)) {
-                Ok(input) => input,
-                Err(e) => {
-                    let response =
-                        ModuleResponse::from_error(format!("Failed to parse input: {}", e));
-
-                    sender_safe!(package.sender, response.into());
-                    return;
-                }
-            };
-
-            let client = match pool.get().await {
-                Ok(client) => client,
-                Err(e) => {
-                    let response = ModuleResponse::from_error(format!(
-                        "Failed to get client from pool: {}",
-                        e
-                    ));
-
-                    sender_safe!(package.sender, response.into());
-                    return;
-                }
+            let response = match resolve(package.context.input, &pool, &config, &cursors).await {
+                Ok(value) => value.into(),
+                Err(e) => ModuleResponse::from_error(e),
             };
 
-            if input.batch {
-                let stmt = if input.cache_query {
-                    match client.prepare_cached(input.query.as_str()).await {
-                        Ok(stmt) => stmt,
-                        Err(e) => {
-                            let response = ModuleResponse::from_error(format!(
-                                "Failed to prepare statement: {}",
-                                e
-                            ));
-
-                            sender_safe!(package.sender, response.into());
-                            return;
-                        }
-                    }
-                } else {
-                    match client.prepare(input.query.as_str()).await {
-                        Ok(stmt) => stmt,
-                        Err(e) => {
-                            let response = ModuleResponse::from_error(format!(
-                                "Failed to prepare statement: {}",
-                                e
-                            ));
-
-                            sender_safe!(package.sender, response.into());
-                            return;
-                        }
-                    }
-                };
-
-                let param_refs: Vec<&(dyn ToSql + Sync)> = input
-                    .params
-                    .iter()
-                    .map(|p| p.as_ref() as &(dyn ToSql + Sync))
-                    .collect();
-
-                match client.query(&stmt, &param_refs[..]).await {
-                    Ok(rows) => {
-                        let result = QueryResult::from(rows);
-
-                        sender_safe!(package.sender, result.to_value().into());
-                    }
-                    Err(e) => {
-                        let response =
-                            ModuleResponse::from_error(format!("Query execution failed: {}", e));
-
-                        sender_safe!(package.sender, response.into());
-                        return;
-                    }
-                };
-            } else {
-                match client.batch_execute(&input.query).await {
-                    Ok(_) => {
-                        let response = "OK".to_value().into();
-                        sender_safe!(package.sender, response);
-                    }
-                    Err(e) => {
-                        let response =
-                            ModuleResponse::from_error(format!("Batch execution failed: {}", e));
-                        sender_safe!(package.sender, response.into());
-                    }
-                }
-            }
+            sender_safe!(package.sender, response);
         });
 
         handles.push(handle);
@@ -122,3 +141,63 @@ pub async fn postgres(setup: ModuleSetup) -> Result<(), Box<dyn std::error::Erro
 
     Ok(())
 }
+
+async fn resolve(
+    input: Option<Value>,
+    pool: &Pool,
+    config: &PostgresConfig,
+    cursors: &Cursors,
+) -> Result<Value, String> {
+    let (transaction, cursor, migrations) = match &input {
+        Some(value @ Value::Object(_)) => (
+            value.get("transaction").cloned(),
+            value.get("cursor").filter(|cursor| !cursor.is_null()),
+            value.get("migrations").map(|_| value),
+        ),
+        _ => (None, None, None),
+    };
+
+    if let Some(value) = migrations {
+        let action = MigrationAction::try_from(value)?;
+        let dir = config
+            .migrations
+            .as_ref()
+            .ok_or_else(|| "No migrations directory configured in with.migrations".to_string())?;
+        let mut client = pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get client from pool: {}", e))?;
+
+        return migrations::run(&mut client, dir, &action).await;
+    }
+
+    if let (Some(cursor), Some(value)) = (cursor, &input) {
+        let fetch_size = row_limit(value, "fetch_size")?;
+        return cursors.next(&cursor.to_string(), fetch_size).await;
+    }
+
+    if let Some(transaction) = transaction {
+        let transaction = TransactionInput::try_from((&transaction, config))
+            .map_err(|e| format!("Failed to parse input: {}", e))?;
+        let mut client = pool
+            .get()
+            .await
+            .map_err(|e| format!("Failed to get client from pool: {}", e))?;
+
+        return transaction::run(&mut client, transaction).await;
+    }
+
+    let input =
+        Input::try_from((input, config)).map_err(|e| format!("Failed to parse input: {}", e))?;
+
+    if let Some(fetch_size) = input.fetch_size {
+        return cursors.open(pool, &input, fetch_size).await;
+    }
+
+    let mut client = pool
+        .get()
+        .await
+        .map_err(|e| format!("Failed to get client from pool: {}", e))?;
+
+    query::execute(&mut client, &input).await
+}
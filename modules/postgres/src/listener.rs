@@ -0,0 +1,164 @@
+use crate::postgres::PostgresConfig;
+use crate::tls::SslMode;
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+use std::future::poll_fn;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_postgres::tls::{MakeTlsConnect, TlsConnect};
+use tokio_postgres::{AsyncMessage, Client, NoTls, Notification, Socket};
+
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Turns the notifications of the configured channels into packages for the
+/// main sender. The connection is opened again whenever it drops.
+pub async fn listen(
+    id: ModuleId,
+    main_sender: MainRuntimeSender,
+    dispatch: Dispatch,
+    config: PostgresConfig,
+) {
+    let mut backoff = MIN_BACKOFF;
+
+    loop {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        match connect(&config, tx).await {
+            Ok(client) => {
+                info!("Listening on {}", config.listen.join(", "));
+                backoff = MIN_BACKOFF;
+
+                while let Some(notification) = rx.recv().await {
+                    dispatch_notification(&id, &main_sender, &dispatch, notification);
+                }
+
+                drop(client);
+                warn!("Listener connection closed, reconnecting");
+            }
+            Err(e) => {
+                error!("Failed to start listener: {}", e);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+async fn connect(
+    config: &PostgresConfig,
+    tx: mpsc::UnboundedSender<Notification>,
+) -> Result<Client, String> {
+    let pg_config = config.connection_config()?;
+
+    let client = match config.tls.ssl_mode {
+        SslMode::Disable => open(&pg_config, NoTls, tx).await?,
+        _ => open(&pg_config, config.tls.connector()?, tx).await?,
+    };
+
+    for channel in &config.listen {
+        client
+            .batch_execute(&format!("LISTEN \"{}\"", channel.replace('"', "\"\"")))
+            .await
+            .map_err(|e| format!("Failed to listen on {}: {}", channel, e))?;
+    }
+
+    Ok(client)
+}
+
+/// Connects and drives the connection in the background, forwarding its
+/// notifications until it ends.
+async fn open<T>(
+    pg_config: &tokio_postgres::Config,
+    tls: T,
+    tx: mpsc::UnboundedSender<Notification>,
+) -> Result<Client, String>
+where
+    T: MakeTlsConnect<Socket>,
+    T::Stream: Send + 'static,
+    T::TlsConnect: Send,
+    <T::TlsConnect as TlsConnect<Socket>>::Future: Send,
+{
+    let (client, mut connection) = pg_config.connect(tls).await.map_err(|e| e.to_string())?;
+
+    tokio::spawn(async move {
+        while let Some(message) = poll_fn(|cx| connection.poll_message(cx)).await {
+            match message {
+                Ok(AsyncMessage::Notification(notification)) => {
+                    if tx.send(notification).is_err() {
+                        break;
+                    }
+                }
+                Ok(AsyncMessage::Notice(notice)) => {
+                    debug!("Listener notice: {}", notice);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    error!("Listener connection error: {}", e);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(client)
+}
+
+fn dispatch_notification(
+    id: &ModuleId,
+    main_sender: &MainRuntimeSender,
+    dispatch: &Dispatch,
+    notification: Notification,
+) {
+    let channel = notification.channel().to_string();
+    let data = notification_to_value(&notification);
+
+    let span = tracing::dispatcher::with_default(dispatch, || {
+        tracing::info_span!(
+            "postgres_notification",
+            otel.name = format!("LISTEN {}", channel),
+            otel.kind = "consumer",
+            db.system = "postgresql",
+            messaging.destination.name = channel.as_str(),
+        )
+    });
+
+    let response = sender_package!(span, dispatch.clone(), *id, main_sender, Some(data));
+
+    tokio::spawn(async move {
+        let response = response.await.unwrap_or(Value::Null);
+        debug!("Notification on {} handled: {:?}", channel, response);
+    });
+}
+
+fn notification_to_value(notification: &Notification) -> Value {
+    HashMap::from([
+        ("channel", notification.channel().to_value()),
+        ("payload", payload_to_value(notification.payload())),
+        ("process_id", notification.process_id().to_value()),
+    ])
+    .to_value()
+}
+
+/// Payloads are parsed as JSON when possible and kept as strings otherwise.
+fn payload_to_value(payload: &str) -> Value {
+    Value::json_to_value(payload).unwrap_or_else(|_| payload.to_value())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_to_value() {
+        let payload = payload_to_value(r#"{"id": 42, "status": "paid"}"#);
+
+        assert_eq!(payload.get("id"), Some(&42.to_value()));
+        assert_eq!(payload.get("status"), Some(&"paid".to_value()));
+        assert_eq!(
+            payload_to_value("order 42 paid"),
+            "order 42 paid".to_value()
+        );
+        assert_eq!(payload_to_value(""), "".to_value());
+    }
+}
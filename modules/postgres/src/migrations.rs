@@ -0,0 +1,509 @@
+use deadpool_postgres::Client;
+use phlow_sdk::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+const HISTORY_TABLE: &str = "phlow_migrations";
+
+/// Key of the advisory lock held while migrating, so that instances starting
+/// together apply each migration once.
+const LOCK_KEY: i64 = 0x7068_6c6f_775f_6d67;
+
+/// A versioned migration: `<version>_<name>.sql` (or `.up.sql`) with an
+/// optional `<version>_<name>.down.sql` used to roll it back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: i64,
+    pub name: String,
+    pub up: PathBuf,
+    pub down: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationAction {
+    Up,
+    List,
+    Rollback(usize),
+}
+
+impl TryFrom<&Value> for MigrationAction {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let action = value
+            .get("migrations")
+            .map(Value::to_string)
+            .unwrap_or_default();
+
+        match action.as_str() {
+            "up" => Ok(MigrationAction::Up),
+            "list" => Ok(MigrationAction::List),
+            "rollback" => {
+                let steps = value.get("steps").and_then(Value::to_u64).unwrap_or(1);
+                Ok(MigrationAction::Rollback(steps as usize))
+            }
+            _ => Err(format!(
+                "Invalid migrations action: {}. Use 'up', 'list' or 'rollback'.",
+                action
+            )),
+        }
+    }
+}
+
+/// Splits `0001_create_students.up.sql` into its version, name and whether it
+/// is a down migration.
+fn parse_file_name(file_name: &str) -> Option<(i64, String, bool)> {
+    let stem = file_name.strip_suffix(".sql")?;
+    let (stem, down) = match stem.strip_suffix(".down") {
+        Some(stem) => (stem, true),
+        None => (stem.strip_suffix(".up").unwrap_or(stem), false),
+    };
+    let (version, name) = stem.split_once('_')?;
+
+    Some((version.parse().ok()?, name.to_string(), down))
+}
+
+/// Resolves a relative migrations directory against the directory of the main
+/// file, the way `!import` paths are, rather than the working directory.
+pub fn resolve_dir(dir: &str, base_path: Option<&str>) -> String {
+    match base_path {
+        Some(base_path) => Path::new(base_path).join(dir).to_string_lossy().to_string(),
+        None => dir.to_string(),
+    }
+}
+
+pub fn load(dir: &str) -> Result<Vec<Migration>, String> {
+    let entries =
+        std::fs::read_dir(dir).map_err(|e| format!("Failed to read migrations {}: {}", dir, e))?;
+
+    let mut ups: BTreeMap<i64, (String, PathBuf)> = BTreeMap::new();
+    let mut downs: HashMap<i64, PathBuf> = HashMap::new();
+
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let file_name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) if file_name.ends_with(".sql") => file_name.to_string(),
+            _ => continue,
+        };
+
+        let (version, name, down) = parse_file_name(&file_name)
+            .ok_or_else(|| format!("Invalid migration file name: {}", file_name))?;
+
+        let duplicate = if down {
+            downs.insert(version, path).is_some()
+        } else {
+            ups.insert(version, (name, path)).is_some()
+        };
+
+        if duplicate {
+            return Err(format!("Duplicate migration version: {}", version));
+        }
+    }
+
+    if let Some(version) = downs.keys().find(|version| !ups.contains_key(version)) {
+        return Err(format!("Down migration without up migration: {}", version));
+    }
+
+    Ok(ups
+        .into_iter()
+        .map(|(version, (name, up))| Migration {
+            version,
+            name,
+            up,
+            down: downs.remove(&version),
+        })
+        .collect())
+}
+
+fn read(path: &Path) -> Result<String, String> {
+    std::fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+fn migration_value(version: i64, name: &str) -> Value {
+    HashMap::from([("version", version.to_value()), ("name", name.to_value())]).to_value()
+}
+
+/// Applied migrations by version, with their name and when they were applied.
+async fn history(client: &Client) -> Result<BTreeMap<i64, (String, String)>, String> {
+    let rows = client
+        .query(
+            &format!(
+                "SELECT version, name, applied_at::text FROM {} ORDER BY version",
+                HISTORY_TABLE
+            ),
+            &[],
+        )
+        .await
+        .map_err(|e| format!("Failed to read migration history: {}", e))?;
+
+    Ok(rows
+        .iter()
+        .map(|row| (row.get(0), (row.get(1), row.get(2))))
+        .collect())
+}
+
+async fn up(client: &mut Client, migrations: &[Migration]) -> Result<Value, String> {
+    let applied = history(client).await?;
+    let mut results = Vec::new();
+
+    for migration in migrations {
+        if applied.contains_key(&migration.version) {
+            continue;
+        }
+
+        let sql = read(&migration.up)?;
+        let failed = |e: tokio_postgres::Error| {
+            format!(
+                "Migration {}_{} failed: {}",
+                migration.version, migration.name, e
+            )
+        };
+
+        let tx = client.transaction().await.map_err(failed)?;
+        tx.batch_execute(&sql).await.map_err(failed)?;
+        tx.execute(
+            &format!(
+                "INSERT INTO {} (version, name) VALUES ($1, $2)",
+                HISTORY_TABLE
+            ),
+            &[&migration.version, &migration.name],
+        )
+        .await
+        .map_err(failed)?;
+        tx.commit().await.map_err(failed)?;
+
+        info!("Applied migration {}_{}", migration.version, migration.name);
+        results.push(migration_value(migration.version, &migration.name));
+    }
+
+    Ok(HashMap::from([("applied", results.to_value())]).to_value())
+}
+
+async fn list(client: &Client, migrations: &[Migration]) -> Result<Value, String> {
+    let mut applied = history(client).await?;
+    let mut results = Vec::new();
+
+    for migration in migrations {
+        let mut value = migration_value(migration.version, &migration.name);
+
+        match applied.remove(&migration.version) {
+            Some((_, applied_at)) => {
+                value.insert("applied", true);
+                value.insert("applied_at", applied_at);
+            }
+            None => {
+                value.insert("applied", false);
+            }
+        }
+
+        results.push(value);
+    }
+
+    // Applied migrations whose files are gone are listed too.
+    for (version, (name, applied_at)) in applied {
+        let mut value = migration_value(version, &name);
+        value.insert("applied", true);
+        value.insert("applied_at", applied_at);
+        value.insert("missing", true);
+        results.push(value);
+    }
+
+    Ok(HashMap::from([("migrations", results.to_value())]).to_value())
+}
+
+async fn rollback(
+    client: &mut Client,
+    migrations: &[Migration],
+    steps: usize,
+) -> Result<Value, String> {
+    let applied = history(client).await?;
+    let mut results = Vec::new();
+
+    for (version, (name, _)) in applied.iter().rev().take(steps) {
+        let down = migrations
+            .iter()
+            .find(|migration| migration.version == *version)
+            .and_then(|migration| migration.down.as_ref())
+            .ok_or_else(|| format!("Migration {}_{} has no down file", version, name))?;
+
+        let sql = read(down)?;
+        let failed =
+            |e: tokio_postgres::Error| format!("Rollback of {}_{} failed: {}", version, name, e);
+
+        let tx = client.transaction().await.map_err(failed)?;
+        tx.batch_execute(&sql).await.map_err(failed)?;
+        tx.execute(
+            &format!("DELETE FROM {} WHERE version = $1", HISTORY_TABLE),
+            &[version],
+        )
+        .await
+        .map_err(failed)?;
+        tx.commit().await.map_err(failed)?;
+
+        info!("Rolled back migration {}_{}", version, name);
+        results.push(migration_value(*version, name));
+    }
+
+    Ok(HashMap::from([("rolled_back", results.to_value())]).to_value())
+}
+
+/// Runs a migrations action on the files in `dir` while holding the advisory lock.
+pub async fn run(
+    client: &mut Client,
+    dir: &str,
+    action: &MigrationAction,
+) -> Result<Value, String> {
+    let migrations = load(dir)?;
+
+    client
+        .execute("SELECT pg_advisory_lock($1)", &[&LOCK_KEY])
+        .await
+        .map_err(|e| format!("Failed to acquire migrations lock: {}", e))?;
+
+    let result = match client
+        .batch_execute(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                version BIGINT PRIMARY KEY,
+                name TEXT NOT NULL,
+                applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+            )",
+            HISTORY_TABLE
+        ))
+        .await
+    {
+        Ok(()) => match action {
+            MigrationAction::Up => up(client, &migrations).await,
+            MigrationAction::List => list(client, &migrations).await,
+            MigrationAction::Rollback(steps) => rollback(client, &migrations, *steps).await,
+        },
+        Err(e) => Err(format!("Failed to create {}: {}", HISTORY_TABLE, e)),
+    };
+
+    if let Err(e) = client
+        .execute("SELECT pg_advisory_unlock($1)", &[&LOCK_KEY])
+        .await
+    {
+        warn!("Failed to release migrations lock: {}", e);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+    use deadpool_postgres::Pool;
+
+    /// A migrations directory with the given files, unique to the test.
+    fn migrations_dir(test: &str, files: &[(&str, &str)]) -> String {
+        let dir =
+            std::env::temp_dir().join(format!("phlow-migrations-{}-{}", test, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for (name, sql) in files {
+            std::fs::write(dir.join(name), sql).unwrap();
+        }
+
+        dir.display().to_string()
+    }
+
+    /// A client whose history table is a temporary one. It shadows the real
+    /// table for the session, so the tests neither see nor change its rows.
+    async fn client(pool: &Pool) -> Client {
+        let client = pool.get().await.unwrap();
+        client
+            .batch_execute(&format!(
+                "CREATE TEMP TABLE {} (
+                    version BIGINT PRIMARY KEY,
+                    name TEXT NOT NULL,
+                    applied_at TIMESTAMPTZ NOT NULL DEFAULT now()
+                )",
+                HISTORY_TABLE
+            ))
+            .await
+            .unwrap();
+        client
+    }
+
+    fn versions(result: &Value, key: &str) -> Vec<u64> {
+        match result.get(key) {
+            Some(Value::Array(items)) => items
+                .into_iter()
+                .filter_map(|item| item.get("version").and_then(Value::to_u64))
+                .collect(),
+            _ => panic!("No {} in {:?}", key, result),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_up_is_idempotent() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = client(&pool).await;
+        let dir = migrations_dir(
+            "up",
+            &[
+                ("0001_create.sql", "CREATE TEMP TABLE mig_up (id int)"),
+                ("0002_seed.sql", "INSERT INTO mig_up VALUES (1)"),
+            ],
+        );
+
+        let first = run(&mut client, &dir, &MigrationAction::Up).await.unwrap();
+        let second = run(&mut client, &dir, &MigrationAction::Up).await.unwrap();
+        let rows: i64 = client
+            .query_one("SELECT count(*) FROM mig_up", &[])
+            .await
+            .unwrap()
+            .get(0);
+
+        assert_eq!(versions(&first, "applied"), vec![1, 2]);
+        assert!(versions(&second, "applied").is_empty());
+        assert_eq!(rows, 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_run_list() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = client(&pool).await;
+        let dir = migrations_dir(
+            "list",
+            &[
+                ("0001_create.sql", "CREATE TEMP TABLE mig_list (id int)"),
+                ("0002_seed.sql", "INSERT INTO mig_list VALUES (1)"),
+            ],
+        );
+
+        run(&mut client, &dir, &MigrationAction::Up).await.unwrap();
+
+        // One applied file is gone and a new one is pending.
+        std::fs::remove_file(Path::new(&dir).join("0002_seed.sql")).unwrap();
+        std::fs::write(
+            Path::new(&dir).join("0003_more.sql"),
+            "INSERT INTO mig_list VALUES (2)",
+        )
+        .unwrap();
+
+        let result = run(&mut client, &dir, &MigrationAction::List)
+            .await
+            .unwrap();
+        let migrations = result.get("migrations").unwrap();
+        let migration = |index: usize| migrations.get(index).cloned().unwrap();
+
+        assert_eq!(versions(&result, "migrations"), vec![1, 3, 2]);
+        assert_eq!(migration(0).get("applied"), Some(&true.to_value()));
+        assert!(migration(0).get("applied_at").is_some());
+        assert_eq!(migration(0).get("missing"), None);
+        assert_eq!(migration(1).get("applied"), Some(&false.to_value()));
+        assert_eq!(migration(1).get("applied_at"), None);
+        assert_eq!(migration(2).get("missing"), Some(&true.to_value()));
+        assert!(migration(2).get("applied_at").is_some());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_run_rollback() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = client(&pool).await;
+        let dir = migrations_dir(
+            "rollback",
+            &[
+                ("0001_create.sql", "CREATE TEMP TABLE mig_rollback (id int)"),
+                (
+                    "0002_add.up.sql",
+                    "ALTER TABLE mig_rollback ADD COLUMN name text",
+                ),
+                (
+                    "0002_add.down.sql",
+                    "ALTER TABLE mig_rollback DROP COLUMN name",
+                ),
+            ],
+        );
+
+        run(&mut client, &dir, &MigrationAction::Up).await.unwrap();
+        let result = run(&mut client, &dir, &MigrationAction::Rollback(1))
+            .await
+            .unwrap();
+
+        let columns: i64 = client
+            .query_one(
+                "SELECT count(*) FROM information_schema.columns
+                 WHERE table_name = 'mig_rollback' AND column_name = 'name'",
+                &[],
+            )
+            .await
+            .unwrap()
+            .get(0);
+        let history: Vec<i64> = client
+            .query(&format!("SELECT version FROM {}", HISTORY_TABLE), &[])
+            .await
+            .unwrap()
+            .iter()
+            .map(|row| row.get(0))
+            .collect();
+
+        assert_eq!(versions(&result, "rolled_back"), vec![2]);
+        assert_eq!(columns, 0);
+        assert_eq!(history, vec![1]);
+
+        // The first migration has no down file.
+        assert!(run(&mut client, &dir, &MigrationAction::Rollback(1))
+            .await
+            .is_err());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_parse_file_name() {
+        assert_eq!(
+            parse_file_name("0001_create_students.sql"),
+            Some((1, "create_students".to_string(), false))
+        );
+        assert_eq!(
+            parse_file_name("20240501120000_add_score.up.sql"),
+            Some((20240501120000, "add_score".to_string(), false))
+        );
+        assert_eq!(
+            parse_file_name("0002_add_score.down.sql"),
+            Some((2, "add_score".to_string(), true))
+        );
+        assert_eq!(parse_file_name("create_students.sql"), None);
+        assert_eq!(parse_file_name("0001_notes.txt"), None);
+    }
+
+    #[test]
+    fn test_resolve_dir() {
+        assert_eq!(
+            resolve_dir("./migrations", Some("examples/api-postgres")),
+            "examples/api-postgres/./migrations"
+        );
+        assert_eq!(
+            resolve_dir("/srv/migrations", Some("examples/api-postgres")),
+            "/srv/migrations"
+        );
+        assert_eq!(resolve_dir("migrations", Some("")), "migrations");
+        assert_eq!(resolve_dir("migrations", None), "migrations");
+    }
+
+    #[test]
+    fn test_migration_action() {
+        let action = |json: &str| MigrationAction::try_from(&Value::json_to_value(json).unwrap());
+
+        assert_eq!(action(r#"{"migrations": "up"}"#), Ok(MigrationAction::Up));
+        assert_eq!(
+            action(r#"{"migrations": "rollback", "steps": 2}"#),
+            Ok(MigrationAction::Rollback(2))
+        );
+        assert!(action(r#"{"migrations": "redo"}"#).is_err());
+    }
+}
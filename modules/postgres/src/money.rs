@@ -0,0 +1,104 @@
+use std::sync::atomic::{AtomicU32, Ordering};
+
+/// MONEY is sent as an integer count of the smallest currency unit, whose
+/// decimal places depend on the server's `lc_monetary` (2 for USD, 0 for JPY).
+/// The scale is read from the server by the health check, 2 until then.
+static SCALE: AtomicU32 = AtomicU32::new(2);
+
+/// Decimal places of MONEY on the server.
+pub const SCALE_QUERY: &str = "SELECT scale(1::money::numeric)";
+
+pub fn set_scale(scale: u32) {
+    SCALE.store(scale, Ordering::Relaxed);
+}
+
+fn scale() -> u32 {
+    SCALE.load(Ordering::Relaxed)
+}
+
+/// The MONEY value as an exact decimal string, like NUMERIC.
+pub fn to_decimal(units: i64) -> String {
+    format_decimal(units, scale())
+}
+
+/// The MONEY value of a decimal string, which cannot have more decimal places
+/// than the server's currency.
+pub fn from_decimal(decimal: &str) -> Result<i64, String> {
+    parse_decimal(decimal, scale())
+}
+
+fn format_decimal(units: i64, scale: u32) -> String {
+    let sign = if units < 0 { "-" } else { "" };
+    let digits = format!(
+        "{:0width$}",
+        units.unsigned_abs(),
+        width = scale as usize + 1
+    );
+
+    match scale {
+        0 => format!("{}{}", sign, digits),
+        scale => {
+            let (integer, fraction) = digits.split_at(digits.len() - scale as usize);
+            format!("{}{}.{}", sign, integer, fraction)
+        }
+    }
+}
+
+fn parse_decimal(decimal: &str, scale: u32) -> Result<i64, String> {
+    let invalid = || format!("{} is not a MONEY amount", decimal);
+    let trimmed = decimal.trim();
+    let (negative, unsigned) = match trimmed.strip_prefix('-') {
+        Some(unsigned) => (true, unsigned),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid());
+    }
+
+    if fraction.len() > scale as usize {
+        return Err(format!(
+            "{} has more than {} decimal places for MONEY",
+            decimal, scale
+        ));
+    }
+
+    let digits = format!("{}{:0<width$}", integer, fraction, width = scale as usize);
+    let units: i64 = match digits.is_empty() {
+        true => 0,
+        false => digits.parse().map_err(|_| invalid())?,
+    };
+
+    Ok(if negative { -units } else { units })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_decimal() {
+        assert_eq!(format_decimal(1234, 2), "12.34");
+        assert_eq!(format_decimal(-5, 2), "-0.05");
+        assert_eq!(format_decimal(1234, 0), "1234");
+        assert_eq!(format_decimal(i64::MIN, 2), "-92233720368547758.08");
+    }
+
+    #[test]
+    fn test_parse_decimal() {
+        assert_eq!(parse_decimal("12.34", 2), Ok(1234));
+        assert_eq!(parse_decimal("-0.5", 2), Ok(-50));
+        assert_eq!(parse_decimal("7", 2), Ok(700));
+        assert_eq!(parse_decimal("1234", 0), Ok(1234));
+        assert!(parse_decimal("12.345", 2).is_err());
+        assert!(parse_decimal("1.5", 0).is_err());
+        assert!(parse_decimal("12,34", 2).is_err());
+        assert!(parse_decimal("-", 2).is_err());
+    }
+}
@@ -0,0 +1,587 @@
+use std::error::Error;
+use std::net::IpAddr;
+
+use crate::money;
+use crate::response::pg_epoch;
+use base64::Engine;
+use bytes::{BufMut, BytesMut};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use phlow_sdk::prelude::*;
+use postgres_protocol::types::{self as protocol, ArrayDimension};
+use tokio_postgres::types::{to_sql_checked, IsNull, Kind, ToSql, Type};
+
+type EncodeError = Box<dyn Error + Sync + Send>;
+
+/// A query parameter. It is encoded for the type the server declares for its
+/// placeholder, so the same value can bind to UUID, JSONB or timestamp columns.
+/// `type_hint` (`{value: ..., type: uuid}`) forces that declaration when the
+/// server cannot infer it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Param {
+    pub value: Value,
+    pub type_hint: Option<Type>,
+}
+
+impl TryFrom<&Value> for Param {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if let Value::Object(object) = value {
+            if let (Some(inner), Some(type_name)) = (object.get("value"), object.get("type")) {
+                let type_name = type_name.to_string();
+                let type_hint = type_from_name(&type_name)
+                    .ok_or_else(|| format!("Unknown parameter type: {}", type_name))?;
+
+                return Ok(Param {
+                    value: inner.clone(),
+                    type_hint: Some(type_hint),
+                });
+            }
+        }
+
+        Ok(Param {
+            value: value.clone(),
+            type_hint: None,
+        })
+    }
+}
+
+/// Postgres type for a hint name, e.g. `uuid`, `timestamptz` or `int4[]`.
+pub fn type_from_name(name: &str) -> Option<Type> {
+    let name = name.trim().to_lowercase();
+
+    if let Some(member) = name.strip_suffix("[]") {
+        let array = match type_from_name(member)? {
+            Type::BOOL => Type::BOOL_ARRAY,
+            Type::INT2 => Type::INT2_ARRAY,
+            Type::INT4 => Type::INT4_ARRAY,
+            Type::INT8 => Type::INT8_ARRAY,
+            Type::FLOAT4 => Type::FLOAT4_ARRAY,
+            Type::FLOAT8 => Type::FLOAT8_ARRAY,
+            Type::NUMERIC => Type::NUMERIC_ARRAY,
+            Type::TEXT => Type::TEXT_ARRAY,
+            Type::VARCHAR => Type::VARCHAR_ARRAY,
+            Type::UUID => Type::UUID_ARRAY,
+            Type::JSON => Type::JSON_ARRAY,
+            Type::JSONB => Type::JSONB_ARRAY,
+            Type::DATE => Type::DATE_ARRAY,
+            Type::TIME => Type::TIME_ARRAY,
+            Type::TIMESTAMP => Type::TIMESTAMP_ARRAY,
+            Type::TIMESTAMPTZ => Type::TIMESTAMPTZ_ARRAY,
+            Type::BYTEA => Type::BYTEA_ARRAY,
+            Type::INET => Type::INET_ARRAY,
+            _ => return None,
+        };
+        return Some(array);
+    }
+
+    let ty = match name.as_str() {
+        "bool" | "boolean" => Type::BOOL,
+        "int2" | "smallint" => Type::INT2,
+        "int" | "int4" | "integer" => Type::INT4,
+        "int8" | "bigint" => Type::INT8,
+        "float4" | "real" => Type::FLOAT4,
+        "float8" | "double" | "double precision" => Type::FLOAT8,
+        "numeric" | "decimal" => Type::NUMERIC,
+        "text" => Type::TEXT,
+        "varchar" => Type::VARCHAR,
+        "uuid" => Type::UUID,
+        "json" => Type::JSON,
+        "jsonb" => Type::JSONB,
+        "date" => Type::DATE,
+        "time" => Type::TIME,
+        "timestamp" => Type::TIMESTAMP,
+        "timestamptz" => Type::TIMESTAMPTZ,
+        "bytea" => Type::BYTEA,
+        "inet" => Type::INET,
+        _ => return None,
+    };
+
+    Some(ty)
+}
+
+/// Rewrites `:name` placeholders to `$n`, returning the query and the names in
+/// placeholder order. Casts (`::type`), string literals, dollar-quoted bodies,
+/// quoted identifiers and comments are left untouched.
+pub fn named_placeholders(query: &str) -> (String, Vec<String>) {
+    let chars: Vec<char> = query.chars().collect();
+    let mut names: Vec<String> = Vec::new();
+    let mut output = String::with_capacity(query.len());
+    let mut i = 0;
+
+    let is_ident_start = |c: char| c.is_ascii_alphabetic() || c == '_';
+    let is_ident = |c: char| c.is_ascii_alphanumeric() || c == '_';
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        let skip_until = |end: &str, start: usize| -> usize {
+            let rest: String = chars[start..].iter().collect();
+            match rest.find(end) {
+                Some(offset) => start + rest[..offset].chars().count() + end.chars().count(),
+                None => chars.len(),
+            }
+        };
+
+        let end = match c {
+            '\'' => skip_until("'", i + 1),
+            '"' => skip_until("\"", i + 1),
+            '-' if chars.get(i + 1) == Some(&'-') => skip_until("\n", i + 2),
+            '/' if chars.get(i + 1) == Some(&'*') => skip_until("*/", i + 2),
+            '$' if i == 0 || !is_ident(chars[i - 1]) => match dollar_tag(&chars[i..]) {
+                Some(tag) => skip_until(&tag, i + tag.chars().count()),
+                None => i + 1,
+            },
+            ':' if chars.get(i + 1) == Some(&':') => i + 2,
+            ':' if chars.get(i + 1).copied().is_some_and(is_ident_start) => {
+                let mut end = i + 1;
+                while end < chars.len() && is_ident(chars[end]) {
+                    end += 1;
+                }
+
+                let name: String = chars[i + 1..end].iter().collect();
+                let position = match names.iter().position(|known| *known == name) {
+                    Some(position) => position,
+                    None => {
+                        names.push(name);
+                        names.len() - 1
+                    }
+                };
+
+                output.push_str(&format!("${}", position + 1));
+                i = end;
+                continue;
+            }
+            _ => i + 1,
+        };
+
+        output.extend(&chars[i..end]);
+        i = end;
+    }
+
+    (output, names)
+}
+
+/// The opening `$tag$` (or `$$`) of a dollar-quoted string at the start of
+/// `chars`. `$1` placeholders are not tags.
+fn dollar_tag(chars: &[char]) -> Option<String> {
+    let tag: String = chars[1..]
+        .iter()
+        .take_while(|c| c.is_ascii_alphanumeric() || **c == '_')
+        .collect();
+
+    if tag.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+
+    match chars.get(tag.chars().count() + 1) {
+        Some('$') => Some(format!("${}$", tag)),
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> String {
+    value.to_string()
+}
+
+fn to_i64(value: &Value) -> Result<i64, EncodeError> {
+    match value {
+        Value::Number(_) => value
+            .to_i64()
+            .or_else(|| {
+                value
+                    .to_f64()
+                    .filter(|f| f.fract() == 0.0)
+                    .map(|f| f as i64)
+            })
+            .ok_or_else(|| format!("{} is not an integer", text(value)).into()),
+        _ => Ok(text(value).trim().parse()?),
+    }
+}
+
+fn to_f64(value: &Value) -> Result<f64, EncodeError> {
+    match value {
+        Value::Number(_) => value
+            .to_f64()
+            .ok_or_else(|| format!("{} is not a number", text(value)).into()),
+        _ => Ok(text(value).trim().parse()?),
+    }
+}
+
+fn to_bool(value: &Value) -> Result<bool, EncodeError> {
+    match value {
+        Value::Boolean(b) => Ok(*b),
+        _ => match text(value).trim().to_lowercase().as_str() {
+            "true" | "t" | "yes" | "on" | "1" => Ok(true),
+            "false" | "f" | "no" | "off" | "0" => Ok(false),
+            other => Err(format!("{} is not a boolean", other).into()),
+        },
+    }
+}
+
+fn parse_timestamp(value: &str) -> Result<NaiveDateTime, EncodeError> {
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(value) {
+        return Ok(datetime.with_timezone(&Utc).naive_utc());
+    }
+
+    for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(datetime);
+        }
+    }
+
+    let date = NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .map_err(|_| format!("{} is not a timestamp", value))?;
+    Ok(date.and_hms_opt(0, 0, 0).expect("valid midnight"))
+}
+
+fn json_text(value: &Value) -> String {
+    match value {
+        // Strings holding a JSON document are sent as that document.
+        Value::String(_) => {
+            let raw = text(value);
+            match Value::json_to_value(&raw) {
+                Ok(Value::Object(_)) | Ok(Value::Array(_)) => raw,
+                _ => value.to_json(JsonMode::Inline),
+            }
+        }
+        value => value.to_json(JsonMode::Inline),
+    }
+}
+
+fn uuid_bytes(value: &str) -> Result<[u8; 16], EncodeError> {
+    let hex: String = value
+        .trim()
+        .trim_start_matches('{')
+        .trim_end_matches('}')
+        .chars()
+        .filter(|c| *c != '-')
+        .collect();
+    let invalid = || format!("{} is not a UUID", value);
+
+    if hex.len() != 32 {
+        return Err(invalid().into());
+    }
+
+    let mut bytes = [0u8; 16];
+    for (i, byte) in bytes.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).map_err(|_| invalid())?;
+    }
+
+    Ok(bytes)
+}
+
+/// Encodes a decimal string in the binary NUMERIC format.
+pub fn encode_numeric(value: &str, out: &mut BytesMut) -> Result<(), EncodeError> {
+    let value = value.trim();
+    let special = match value.to_lowercase().as_str() {
+        "nan" => Some(0xC000),
+        "infinity" | "+infinity" => Some(0xD000),
+        "-infinity" => Some(0xF000),
+        _ => None,
+    };
+
+    if let Some(sign) = special {
+        out.put_i16(0);
+        out.put_i16(0);
+        out.put_u16(sign);
+        out.put_i16(0);
+        return Ok(());
+    }
+
+    let invalid = || format!("{} is not a decimal number", value);
+    let (negative, unsigned) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let (integer, fraction) = unsigned.split_once('.').unwrap_or((unsigned, ""));
+
+    if integer.is_empty() && fraction.is_empty()
+        || !integer
+            .chars()
+            .chain(fraction.chars())
+            .all(|c| c.is_ascii_digit())
+    {
+        return Err(invalid().into());
+    }
+
+    // Pad both parts to whole base-10000 groups around the decimal point.
+    let integer = integer.trim_start_matches('0');
+    let integer_pad = (4 - integer.len() % 4) % 4;
+    let fraction_pad = (4 - fraction.len() % 4) % 4;
+    let padded = format!(
+        "{}{}{}{}",
+        "0".repeat(integer_pad),
+        integer,
+        fraction,
+        "0".repeat(fraction_pad)
+    );
+
+    let mut digits: Vec<i16> = padded
+        .as_bytes()
+        .chunks(4)
+        .map(|chunk| std::str::from_utf8(chunk).unwrap().parse().unwrap())
+        .collect();
+    let mut weight = ((integer_pad + integer.len()) / 4) as i16 - 1;
+
+    while digits.first() == Some(&0) {
+        digits.remove(0);
+        weight -= 1;
+    }
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    if digits.is_empty() {
+        weight = 0;
+    }
+
+    out.put_i16(digits.len() as i16);
+    out.put_i16(weight);
+    out.put_u16(if negative && !digits.is_empty() {
+        0x4000
+    } else {
+        0
+    });
+    out.put_i16(fraction.len() as i16);
+    for digit in digits {
+        out.put_i16(digit);
+    }
+
+    Ok(())
+}
+
+/// Shape of a (possibly nested) array value, as Postgres array dimensions.
+fn array_dimensions(value: &Value) -> Vec<ArrayDimension> {
+    let mut dimensions = Vec::new();
+    let mut current = value;
+
+    while let Value::Array(items) = current {
+        dimensions.push(ArrayDimension {
+            len: items.len() as i32,
+            lower_bound: 1,
+        });
+
+        match items.get(0) {
+            Some(first) => current = first,
+            None => break,
+        }
+    }
+
+    dimensions
+}
+
+fn flatten(value: &Value, depth: usize, elements: &mut Vec<Value>) {
+    match value {
+        Value::Array(items) if depth > 0 => {
+            for item in items.into_iter() {
+                flatten(item, depth - 1, elements);
+            }
+        }
+        value => elements.push(value.clone()),
+    }
+}
+
+fn encode(value: &Value, ty: &Type, out: &mut BytesMut) -> Result<IsNull, EncodeError> {
+    if matches!(value, Value::Null | Value::Undefined) {
+        return Ok(IsNull::Yes);
+    }
+
+    match *ty {
+        Type::BOOL => protocol::bool_to_sql(to_bool(value)?, out),
+        Type::CHAR => protocol::char_to_sql(i8::try_from(to_i64(value)?)?, out),
+        Type::INT2 => protocol::int2_to_sql(i16::try_from(to_i64(value)?)?, out),
+        Type::INT4 => protocol::int4_to_sql(i32::try_from(to_i64(value)?)?, out),
+        Type::INT8 => protocol::int8_to_sql(to_i64(value)?, out),
+        Type::MONEY => protocol::int8_to_sql(money::from_decimal(&text(value))?, out),
+        Type::OID => protocol::oid_to_sql(u32::try_from(to_i64(value)?)?, out),
+        Type::FLOAT4 => protocol::float4_to_sql(to_f64(value)? as f32, out),
+        Type::FLOAT8 => protocol::float8_to_sql(to_f64(value)?, out),
+        Type::NUMERIC => encode_numeric(&text(value), out)?,
+        Type::JSON => out.put_slice(json_text(value).as_bytes()),
+        Type::JSONB => {
+            out.put_u8(1);
+            out.put_slice(json_text(value).as_bytes());
+        }
+        Type::UUID => protocol::uuid_to_sql(uuid_bytes(&text(value))?, out),
+        Type::BYTEA => {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(text(value).trim())
+                .map_err(|e| format!("BYTEA parameters must be base64: {}", e))?;
+            protocol::bytea_to_sql(&bytes, out);
+        }
+        Type::DATE => {
+            let date = match NaiveDate::parse_from_str(text(value).trim(), "%Y-%m-%d") {
+                Ok(date) => date,
+                Err(_) => parse_timestamp(text(value).trim())?.date(),
+            };
+            let days = (date - pg_epoch().date()).num_days();
+            protocol::date_to_sql(i32::try_from(days)?, out);
+        }
+        Type::TIME => {
+            let time = NaiveTime::parse_from_str(text(value).trim(), "%H:%M:%S%.f")
+                .map_err(|_| format!("{} is not a time", text(value)))?;
+            let micros = (time - NaiveTime::MIN)
+                .num_microseconds()
+                .ok_or("Time out of range")?;
+            protocol::time_to_sql(micros, out);
+        }
+        Type::TIMESTAMP | Type::TIMESTAMPTZ => {
+            let timestamp = parse_timestamp(text(value).trim())?;
+            let micros = (timestamp - pg_epoch())
+                .num_microseconds()
+                .ok_or("Timestamp out of range")?;
+            protocol::timestamp_to_sql(micros, out);
+        }
+        Type::INET | Type::CIDR => {
+            let raw = text(value);
+            let (addr, netmask) = match raw.trim().split_once('/') {
+                Some((addr, netmask)) => (addr.parse::<IpAddr>()?, Some(netmask.parse::<u8>()?)),
+                None => (raw.trim().parse::<IpAddr>()?, None),
+            };
+            let netmask = netmask.unwrap_or(if addr.is_ipv4() { 32 } else { 128 });
+            protocol::inet_to_sql(addr, netmask, out);
+        }
+        _ => match ty.kind() {
+            Kind::Array(member) => {
+                if !matches!(value, Value::Array(_)) {
+                    return Err(format!("Expected an array for {}", ty).into());
+                }
+
+                let dimensions = array_dimensions(value);
+                let mut elements = Vec::new();
+                flatten(value, dimensions.len(), &mut elements);
+
+                protocol::array_to_sql(
+                    dimensions,
+                    member.oid(),
+                    elements,
+                    |element, out| match encode(&element, member, out)? {
+                        IsNull::Yes => Ok(postgres_protocol::IsNull::Yes),
+                        IsNull::No => Ok(postgres_protocol::IsNull::No),
+                    },
+                    out,
+                )?;
+            }
+            Kind::Domain(inner) => return encode(value, inner, out),
+            // Text-like types and enums take the value as text.
+            _ => out.put_slice(text(value).as_bytes()),
+        },
+    }
+
+    Ok(IsNull::No)
+}
+
+impl ToSql for Param {
+    fn to_sql(&self, ty: &Type, out: &mut BytesMut) -> Result<IsNull, EncodeError> {
+        encode(&self.value, ty, out)
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+
+    to_sql_checked!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::{decode_numeric, ColumnValue};
+    use tokio_postgres::types::FromSql;
+
+    fn round_trip(value: Value, ty: Type) -> Value {
+        let mut out = BytesMut::new();
+        let param = Param {
+            value,
+            type_hint: None,
+        };
+
+        match param.to_sql(&ty, &mut out).unwrap() {
+            IsNull::Yes => Value::Null,
+            IsNull::No => ColumnValue::from_sql(&ty, &out).unwrap().0,
+        }
+    }
+
+    #[test]
+    fn test_named_placeholders() {
+        let (query, names) = named_placeholders(
+            "SELECT :id::text, ':skip', \"a:b\" -- :comment\nFROM t WHERE a = :name AND b = :id",
+        );
+
+        assert_eq!(
+            query,
+            "SELECT $1::text, ':skip', \"a:b\" -- :comment\nFROM t WHERE a = $2 AND b = $1"
+        );
+        assert_eq!(names, vec!["id".to_string(), "name".to_string()]);
+    }
+
+    #[test]
+    fn test_named_placeholders_dollar_quotes() {
+        let sql = "CREATE FUNCTION f() RETURNS int AS $$ SELECT :inner $$ LANGUAGE sql; \
+                   DO $body$ BEGIN PERFORM ':x', $1; END $body$; \
+                   SELECT a$b, $2, :outer";
+        let (query, names) = named_placeholders(sql);
+
+        assert_eq!(query, sql.replace(":outer", "$1"));
+        assert_eq!(names, vec!["outer".to_string()]);
+
+        let (query, names) = named_placeholders("SELECT $tag$ :open");
+        assert_eq!(query, "SELECT $tag$ :open");
+        assert!(names.is_empty());
+    }
+
+    #[test]
+    fn test_encode_numeric() {
+        for value in [
+            "12345.678",
+            "-0.0012",
+            "20000.00",
+            "0",
+            "1000000000000000000001",
+        ] {
+            let mut out = BytesMut::new();
+            encode_numeric(value, &mut out).unwrap();
+            assert_eq!(decode_numeric(&out).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn test_encode_by_declared_type() {
+        assert_eq!(
+            round_trip(
+                "550e8400-e29b-41d4-a716-446655440000".to_value(),
+                Type::UUID
+            ),
+            "550e8400-e29b-41d4-a716-446655440000".to_value()
+        );
+        assert_eq!(
+            round_trip("2024-03-01T12:30:00-03:00".to_value(), Type::TIMESTAMPTZ),
+            "2024-03-01T15:30:00Z".to_value()
+        );
+        assert_eq!(round_trip("42".to_value(), Type::INT8), 42_i64.to_value());
+        assert_eq!(round_trip(Value::Null, Type::UUID), Value::Null);
+
+        let json = Value::json_to_value(r#"{"tags": ["a"], "n": 1}"#).unwrap();
+        assert_eq!(round_trip(json.clone(), Type::JSONB), json);
+
+        let matrix = Value::json_to_value("[[1, 2], [3, null]]").unwrap();
+        assert_eq!(
+            round_trip(matrix, Type::INT4_ARRAY).to_json(JsonMode::Inline),
+            "[[1,2],[3,null]]"
+        );
+    }
+
+    #[test]
+    fn test_param_type_hint() {
+        let param =
+            Param::try_from(&Value::json_to_value(r#"{"value": "abc", "type": "uuid"}"#).unwrap())
+                .unwrap();
+
+        assert_eq!(param.type_hint, Some(Type::UUID));
+        assert_eq!(param.value, "abc".to_value());
+        assert!(
+            Param::try_from(&Value::json_to_value(r#"{"value": 1, "type": "nope"}"#).unwrap())
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use crate::input::Input;
+use crate::response::{row_to_map, QueryResult};
+use deadpool_postgres::{GenericClient, Object, Transaction};
+use futures::StreamExt;
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::pin;
+use tokio_postgres::types::ToSql;
+use tokio_postgres::{Error, RowStream, SimpleQueryMessage, Statement};
+
+/// `simple_query` is not part of `GenericClient`, but batch mode needs it to
+/// report the affected rows.
+pub trait SimpleQuery {
+    fn simple_query(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<SimpleQueryMessage>, Error>> + Send;
+}
+
+impl SimpleQuery for Object {
+    fn simple_query(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<SimpleQueryMessage>, Error>> + Send {
+        tokio_postgres::Client::simple_query(self, query)
+    }
+}
+
+impl SimpleQuery for Transaction<'_> {
+    fn simple_query(
+        &self,
+        query: &str,
+    ) -> impl Future<Output = Result<Vec<SimpleQueryMessage>, Error>> + Send {
+        tokio_postgres::Transaction::simple_query(self, query)
+    }
+}
+
+pub async fn prepare<C: GenericClient + Sync>(
+    client: &C,
+    query: &str,
+    input: &Input,
+) -> Result<Statement, String> {
+    let prepared = match (input.param_types(), input.cache_query) {
+        (Some(types), true) => client.prepare_typed_cached(query, &types).await,
+        (Some(types), false) => client.prepare_typed(query, &types).await,
+        (None, true) => client.prepare_cached(query).await,
+        (None, false) => client.prepare(query).await,
+    };
+
+    prepared.map_err(|e| format!("Failed to prepare statement: {}", e))
+}
+
+pub fn param_refs(input: &Input) -> Vec<&(dyn ToSql + Sync)> {
+    input
+        .params
+        .iter()
+        .map(|p| p as &(dyn ToSql + Sync))
+        .collect()
+}
+
+/// Runs a query input on a pooled client or inside a transaction.
+pub async fn execute<C>(client: &mut C, input: &Input) -> Result<Value, String>
+where
+    C: GenericClient + SimpleQuery + Sync,
+{
+    if !input.batch {
+        let messages = client
+            .simple_query(&input.query)
+            .await
+            .map_err(|e| format!("Batch execution failed: {}", e))?;
+
+        let rows_affected: u64 = messages
+            .iter()
+            .map(|message| match message {
+                SimpleQueryMessage::CommandComplete(rows) => *rows,
+                _ => 0,
+            })
+            .sum();
+
+        return Ok(HashMap::from([("rows_affected", rows_affected.to_value())]).to_value());
+    }
+
+    if let Some(max_rows) = input.max_rows {
+        return execute_limited(client, input, max_rows).await;
+    }
+
+    let stmt = prepare(client, &input.query, input).await?;
+
+    let stream = client
+        .query_raw(&stmt, param_refs(input))
+        .await
+        .map_err(|e| format!("Query execution failed: {}", e))?;
+
+    collect(stream, None).await
+}
+
+/// Binds the query to a portal and executes it for one row past `max_rows`,
+/// so the server stops sending rows once the limit is reached. Portals only
+/// live inside a transaction, which is a savepoint when already in one.
+async fn execute_limited<C>(client: &mut C, input: &Input, max_rows: usize) -> Result<Value, String>
+where
+    C: GenericClient + Sync,
+{
+    let tx = client
+        .transaction()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let stmt = prepare(&tx, &input.query, input).await?;
+    let portal = tx
+        .bind(&stmt, &param_refs(input))
+        .await
+        .map_err(|e| format!("Query execution failed: {}", e))?;
+
+    let limit = i32::try_from(max_rows.saturating_add(1)).unwrap_or(i32::MAX);
+    let stream = tx
+        .query_portal_raw(&portal, limit)
+        .await
+        .map_err(|e| format!("Query execution failed: {}", e))?;
+
+    let result = collect(stream, Some(max_rows)).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(result)
+}
+
+/// Decodes the rows as they arrive, keeping at most `max_rows` of them.
+async fn collect(stream: RowStream, max_rows: Option<usize>) -> Result<Value, String> {
+    let mut stream = pin!(stream);
+    let mut rows = Vec::new();
+    let mut truncated = false;
+
+    while let Some(row) = stream.next().await {
+        let row = row.map_err(|e| format!("Query execution failed: {}", e))?;
+
+        if max_rows.is_some_and(|max_rows| rows.len() >= max_rows) {
+            truncated = true;
+            break;
+        }
+
+        rows.push(row_to_map(&row));
+    }
+
+    let mut result = QueryResult {
+        count: rows.len(),
+        rows,
+    }
+    .to_value();
+
+    // The server only reports the affected rows once the portal is exhausted.
+    if let Some(rows_affected) = stream.rows_affected().filter(|_| !truncated) {
+        result.insert("rows_affected", rows_affected);
+    }
+
+    if max_rows.is_some() {
+        result.insert("truncated", truncated);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db::{self, input};
+
+    #[tokio::test]
+    async fn test_execute_max_rows() {
+        let Some(pool) = test_db::pool(2) else {
+            return;
+        };
+        let mut client = pool.get().await.unwrap();
+
+        let query = r#"{"query": "SELECT i FROM generate_series(1, 10) AS i", "max_rows": 3}"#;
+        let result = execute(&mut client, &input(query)).await.unwrap();
+
+        assert_eq!(result.get("count").and_then(Value::to_u64), Some(3));
+        assert_eq!(result.get("truncated"), Some(&true.to_value()));
+        assert_eq!(result.get("rows_affected"), None);
+
+        let query = r#"{"query": "SELECT i FROM generate_series(1, 3) AS i", "max_rows": 3}"#;
+        let result = execute(&mut client, &input(query)).await.unwrap();
+
+        assert_eq!(result.get("count").and_then(Value::to_u64), Some(3));
+        assert_eq!(result.get("truncated"), Some(&false.to_value()));
+        assert_eq!(result.get("rows_affected").and_then(Value::to_u64), Some(3));
+
+        // Inside a transaction the portal lives in a savepoint.
+        let mut tx = client.transaction().await.unwrap();
+        let query = r#"{"query": "SELECT i FROM generate_series(1, 10) AS i", "max_rows": 4}"#;
+        let result = execute(&mut tx, &input(query)).await.unwrap();
+        tx.commit().await.unwrap();
+
+        assert_eq!(result.get("count").and_then(Value::to_u64), Some(4));
+        assert_eq!(result.get("truncated"), Some(&true.to_value()));
+    }
+}
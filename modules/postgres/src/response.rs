@@ -1,7 +1,18 @@
 use std::collections::HashMap;
+use std::error::Error;
 
+use crate::money;
+use base64::Engine;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, Utc};
+use fallible_iterator::FallibleIterator;
 use phlow_sdk::prelude::*;
-use tokio_postgres::{types::Type, Row};
+use postgres_protocol::types as protocol;
+use tokio_postgres::{
+    types::{FromSql, Kind, Type},
+    Row,
+};
+
+type DecodeError = Box<dyn Error + Sync + Send>;
 
 #[derive(Debug, Clone, ToValue)]
 pub struct QueryResult {
@@ -11,41 +22,589 @@ pub struct QueryResult {
 
 impl From<Vec<Row>> for QueryResult {
     fn from(rows: Vec<Row>) -> Self {
-        let mut result = QueryResult {
-            rows: Vec::new(),
+        QueryResult {
             count: rows.len(),
+            rows: rows.iter().map(row_to_map).collect(),
+        }
+    }
+}
+
+pub fn row_to_map(row: &Row) -> HashMap<String, Value> {
+    let mut map = HashMap::new();
+
+    for (i, column) in row.columns().iter().enumerate() {
+        let value = match row.try_get::<_, ColumnValue>(i) {
+            Ok(ColumnValue(value)) => value,
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to decode column {} ({}): {}",
+                    column.name(),
+                    column.type_(),
+                    e
+                );
+                Value::Null
+            }
         };
 
-        for row in rows {
-            let mut map = HashMap::new();
-            // ...existing code...
-            for (i, column) in row.columns().iter().enumerate() {
-                let name = column.name();
-
-                let value = match column.type_() {
-                    &Type::INT2 => row.try_get::<_, i16>(i).map_or(Value::Null, Value::from),
-                    &Type::INT4 => row.try_get::<_, i32>(i).map_or(Value::Null, Value::from),
-                    &Type::INT8 => row.try_get::<_, i64>(i).map_or(Value::Null, Value::from),
-                    &Type::FLOAT4 => row.try_get::<_, f32>(i).map_or(Value::Null, Value::from),
-                    &Type::FLOAT8 => row.try_get::<_, f64>(i).map_or(Value::Null, Value::from),
-                    &Type::NUMERIC => row.try_get::<_, f64>(i).map_or(Value::Null, Value::from),
-                    &Type::BOOL => row.try_get::<_, bool>(i).map_or(Value::Null, Value::from),
-                    &Type::DATE => row
-                        .try_get::<_, chrono::NaiveDate>(i)
-                        .map_or(Value::Null, |v| Value::from(v.to_string())),
-                    &Type::TIMESTAMP | &Type::TIMESTAMPTZ => row
-                        .try_get::<_, chrono::NaiveDateTime>(i)
-                        .map_or(Value::Null, |v| Value::from(v.to_string())),
-                    _ => row.try_get::<_, String>(i).map_or(Value::Null, Value::from),
-                };
-
-                map.insert(name.to_string(), value.to_value());
+        map.insert(column.name().to_string(), value);
+    }
+
+    map
+}
+
+/// A column decoded from the binary protocol into a `Value`:
+///
+/// - JSON/JSONB become nested values and arrays become arrays (per dimension).
+/// - NUMERIC and MONEY are exact decimal strings.
+/// - BYTEA is base64.
+/// - Dates, times and timestamps are ISO 8601 / RFC 3339, and INTERVAL is an ISO 8601 duration.
+/// - Enums, domains and text-like types are read through their underlying representation.
+/// - Any other type is its raw binary value in hex, e.g. `\x0a1b`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnValue(pub Value);
+
+impl<'a> FromSql<'a> for ColumnValue {
+    fn from_sql(ty: &Type, raw: &'a [u8]) -> Result<Self, DecodeError> {
+        decode(ty, raw).map(ColumnValue)
+    }
+
+    fn from_sql_null(_: &Type) -> Result<Self, DecodeError> {
+        Ok(ColumnValue(Value::Null))
+    }
+
+    fn accepts(_: &Type) -> bool {
+        true
+    }
+}
+
+fn base64(bytes: &[u8]) -> Value {
+    base64::engine::general_purpose::STANDARD
+        .encode(bytes)
+        .to_value()
+}
+
+/// Raw bytes in the Postgres hex format, e.g. `\x0a1b`.
+fn hex(bytes: &[u8]) -> Value {
+    let digits: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("\\x{}", digits).to_value()
+}
+
+/// Types sent as UTF-8 text in the binary format.
+fn is_text(ty: &Type) -> bool {
+    matches!(
+        *ty,
+        Type::TEXT
+            | Type::VARCHAR
+            | Type::BPCHAR
+            | Type::NAME
+            | Type::XML
+            | Type::UNKNOWN
+            | Type::REFCURSOR
+    ) || ty.name() == "citext"
+}
+
+/// Postgres dates and timestamps count from 2000-01-01.
+pub fn pg_epoch() -> NaiveDateTime {
+    NaiveDate::from_ymd_opt(2000, 1, 1)
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .expect("valid epoch")
+}
+
+fn timestamp(raw: &[u8]) -> Result<Option<NaiveDateTime>, DecodeError> {
+    match protocol::timestamp_from_sql(raw)? {
+        i64::MAX | i64::MIN => Ok(None),
+        micros => Ok(pg_epoch().checked_add_signed(TimeDelta::microseconds(micros))),
+    }
+}
+
+fn infinity(micros_or_days: i64) -> Value {
+    if micros_or_days > 0 {
+        "infinity"
+    } else {
+        "-infinity"
+    }
+    .to_value()
+}
+
+fn decode(ty: &Type, raw: &[u8]) -> Result<Value, DecodeError> {
+    let value = match *ty {
+        Type::BOOL => protocol::bool_from_sql(raw)?.to_value(),
+        Type::CHAR => protocol::char_from_sql(raw)?.to_value(),
+        Type::INT2 => protocol::int2_from_sql(raw)?.to_value(),
+        Type::INT4 => protocol::int4_from_sql(raw)?.to_value(),
+        Type::INT8 => protocol::int8_from_sql(raw)?.to_value(),
+        Type::OID => protocol::oid_from_sql(raw)?.to_value(),
+        Type::FLOAT4 => protocol::float4_from_sql(raw)?.to_value(),
+        Type::FLOAT8 => protocol::float8_from_sql(raw)?.to_value(),
+        Type::NUMERIC => decode_numeric(raw)?.to_value(),
+        Type::MONEY => money::to_decimal(protocol::int8_from_sql(raw)?).to_value(),
+        Type::BYTEA => base64(protocol::bytea_from_sql(raw)),
+        Type::JSON => Value::json_to_value(protocol::text_from_sql(raw)?)
+            .map_err(|e| format!("Invalid JSON: {:?}", e))?,
+        Type::JSONB => match raw.split_first() {
+            Some((1, json)) => Value::json_to_value(std::str::from_utf8(json)?)
+                .map_err(|e| format!("Invalid JSONB: {:?}", e))?,
+            _ => return Err("Unsupported JSONB version".into()),
+        },
+        Type::UUID => decode_uuid(protocol::uuid_from_sql(raw)?).to_value(),
+        Type::DATE => match protocol::date_from_sql(raw)? {
+            days @ (i32::MAX | i32::MIN) => infinity(days as i64),
+            days => pg_epoch()
+                .date()
+                .checked_add_signed(TimeDelta::days(days as i64))
+                .ok_or("Date out of range")?
+                .format("%Y-%m-%d")
+                .to_string()
+                .to_value(),
+        },
+        Type::TIME => decode_time(protocol::time_from_sql(raw)?)?.to_value(),
+        Type::TIMETZ if raw.len() == 12 => {
+            let time = decode_time(protocol::int8_from_sql(&raw[..8])?)?;
+            // Postgres stores the offset in seconds west of UTC.
+            let offset = -protocol::int4_from_sql(&raw[8..])?;
+            format!(
+                "{}{}{:02}:{:02}",
+                time,
+                if offset < 0 { '-' } else { '+' },
+                offset.abs() / 3600,
+                offset.abs() % 3600 / 60
+            )
+            .to_value()
+        }
+        Type::TIMESTAMP => match timestamp(raw)? {
+            Some(timestamp) => timestamp
+                .format("%Y-%m-%dT%H:%M:%S%.f")
+                .to_string()
+                .to_value(),
+            None => infinity(protocol::timestamp_from_sql(raw)?),
+        },
+        Type::TIMESTAMPTZ => match timestamp(raw)? {
+            Some(timestamp) => DateTime::<Utc>::from_naive_utc_and_offset(timestamp, Utc)
+                .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
+                .to_value(),
+            None => infinity(protocol::timestamp_from_sql(raw)?),
+        },
+        Type::INTERVAL if raw.len() == 16 => decode_interval(
+            protocol::int8_from_sql(&raw[..8])?,
+            protocol::int4_from_sql(&raw[8..12])?,
+            protocol::int4_from_sql(&raw[12..])?,
+        )
+        .to_value(),
+        Type::INET | Type::CIDR => {
+            let inet = protocol::inet_from_sql(raw)?;
+            format!("{}/{}", inet.addr(), inet.netmask()).to_value()
+        }
+        Type::MACADDR => protocol::macaddr_from_sql(raw)?
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect::<Vec<_>>()
+            .join(":")
+            .to_value(),
+        _ => match ty.kind() {
+            Kind::Array(member) => decode_array(member, raw)?,
+            Kind::Domain(inner) => decode(inner, raw)?,
+            Kind::Enum(_) => protocol::text_from_sql(raw)?.to_value(),
+            _ if is_text(ty) => protocol::text_from_sql(raw)?.to_value(),
+            // Other types have binary formats of their own, which are not text.
+            _ => hex(raw),
+        },
+    };
+
+    Ok(value)
+}
+
+fn decode_array(member: &Type, raw: &[u8]) -> Result<Value, DecodeError> {
+    let array = protocol::array_from_sql(raw)?;
+    let dimensions: Vec<usize> = array
+        .dimensions()
+        .map(|dimension| Ok(dimension.len.max(0) as usize))
+        .collect()?;
+    let values: Vec<Value> = array
+        .values()
+        .map(|value| match value {
+            Some(raw) => decode(member, raw),
+            None => Ok(Value::Null),
+        })
+        .collect()?;
+
+    Ok(nest(&dimensions, &mut values.into_iter()))
+}
+
+/// Rebuilds the nesting of a multi-dimensional array from its flattened values.
+fn nest(dimensions: &[usize], values: &mut impl Iterator<Item = Value>) -> Value {
+    match dimensions.split_first() {
+        None => Value::from(Vec::<Value>::new()),
+        Some((len, [])) => Value::from(values.take(*len).collect::<Vec<_>>()),
+        Some((len, rest)) => Value::from((0..*len).map(|_| nest(rest, values)).collect::<Vec<_>>()),
+    }
+}
+
+fn decode_uuid(bytes: [u8; 16]) -> String {
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+
+    format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    )
+}
+
+fn decode_time(micros: i64) -> Result<String, DecodeError> {
+    let time = NaiveTime::from_hms_opt(0, 0, 0)
+        .expect("valid midnight")
+        .overflowing_add_signed(TimeDelta::microseconds(micros))
+        .0;
+
+    // 24:00:00 is a valid TIME in Postgres.
+    if micros >= 86_400_000_000 {
+        return Ok("24:00:00".to_string());
+    }
+
+    Ok(time.format("%H:%M:%S%.f").to_string())
+}
+
+/// Formats an INTERVAL as an ISO 8601 duration, e.g. `P1Y2M3DT4H5M6.5S`.
+pub fn decode_interval(micros: i64, days: i32, months: i32) -> String {
+    let mut duration = String::from("P");
+
+    let years = months / 12;
+    let months = months % 12;
+
+    for (amount, unit) in [
+        (years as i64, 'Y'),
+        (months as i64, 'M'),
+        (days as i64, 'D'),
+    ] {
+        if amount != 0 {
+            duration.push_str(&format!("{}{}", amount, unit));
+        }
+    }
+
+    if micros != 0 {
+        duration.push('T');
+
+        let sign = if micros < 0 { "-" } else { "" };
+        let micros = micros.unsigned_abs();
+        let hours = micros / 3_600_000_000;
+        let minutes = micros / 60_000_000 % 60;
+        let seconds = micros / 1_000_000 % 60;
+        let fraction = micros % 1_000_000;
+
+        if hours != 0 {
+            duration.push_str(&format!("{}{}H", sign, hours));
+        }
+        if minutes != 0 {
+            duration.push_str(&format!("{}{}M", sign, minutes));
+        }
+        if seconds != 0 || fraction != 0 {
+            let fraction = if fraction != 0 {
+                format!(".{:06}", fraction)
+                    .trim_end_matches('0')
+                    .to_string()
+            } else {
+                String::new()
+            };
+            duration.push_str(&format!("{}{}{}S", sign, seconds, fraction));
+        }
+    }
+
+    if duration == "P" {
+        duration.push_str("T0S");
+    }
+
+    duration
+}
+
+/// Decodes the binary NUMERIC representation into an exact decimal string.
+pub fn decode_numeric(raw: &[u8]) -> Result<String, DecodeError> {
+    if raw.len() < 8 {
+        return Err("Invalid NUMERIC value".into());
+    }
+
+    let read = |offset: usize| i16::from_be_bytes([raw[offset], raw[offset + 1]]);
+    let ndigits = read(0).max(0) as usize;
+    let weight = read(2) as i32;
+    let sign = read(4) as u16;
+    let scale = read(6).max(0) as usize;
+
+    match sign {
+        0xC000 => return Ok("NaN".to_string()),
+        0xD000 => return Ok("Infinity".to_string()),
+        0xF000 => return Ok("-Infinity".to_string()),
+        _ => {}
+    }
+
+    if raw.len() < 8 + ndigits * 2 {
+        return Err("Invalid NUMERIC value".into());
+    }
+
+    let digits: Vec<i16> = (0..ndigits).map(|i| read(8 + i * 2)).collect();
+    let digit = |index: i32| -> i16 {
+        if index >= 0 && (index as usize) < digits.len() {
+            digits[index as usize]
+        } else {
+            0
+        }
+    };
+
+    // Each digit is a base-10000 group; `weight` is the position of the first one.
+    let mut integer = String::new();
+    for index in 0..=weight {
+        let group = digit(index);
+        if integer.is_empty() {
+            if group != 0 {
+                integer.push_str(&group.to_string());
             }
-            // ...existing code...
-            // ...existing code...
-            result.rows.push(map);
+        } else {
+            integer.push_str(&format!("{:04}", group));
         }
+    }
+    if integer.is_empty() {
+        integer.push('0');
+    }
+
+    let mut fraction = String::new();
+    let mut index = weight + 1;
+    while fraction.len() < scale {
+        fraction.push_str(&format!("{:04}", digit(index)));
+        index += 1;
+    }
+    fraction.truncate(scale);
+
+    let mut number = String::new();
+    if sign == 0x4000 {
+        number.push('-');
+    }
+    number.push_str(&integer);
+    if scale > 0 {
+        number.push('.');
+        number.push_str(&fraction);
+    }
+
+    Ok(number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numeric(weight: i16, sign: u16, scale: i16, digits: &[i16]) -> Vec<u8> {
+        let mut raw = Vec::new();
+        raw.extend((digits.len() as i16).to_be_bytes());
+        raw.extend(weight.to_be_bytes());
+        raw.extend(sign.to_be_bytes());
+        raw.extend(scale.to_be_bytes());
+        for digit in digits {
+            raw.extend(digit.to_be_bytes());
+        }
+        raw
+    }
+
+    fn value(ty: Type, raw: &[u8]) -> Value {
+        ColumnValue::from_sql(&ty, raw).unwrap().0
+    }
+
+    #[test]
+    fn test_decode_numeric() {
+        // 12345.678 = [1, 2345, 6780] with weight 1
+        assert_eq!(
+            decode_numeric(&numeric(1, 0, 3, &[1, 2345, 6780])).unwrap(),
+            "12345.678"
+        );
+        // -0.0012 = [12] with weight -1
+        assert_eq!(
+            decode_numeric(&numeric(-1, 0x4000, 4, &[12])).unwrap(),
+            "-0.0012"
+        );
+        // 1000000000000000000001 keeps every digit
+        assert_eq!(
+            decode_numeric(&numeric(5, 0, 0, &[10, 0, 0, 0, 0, 1])).unwrap(),
+            "1000000000000000000001"
+        );
+        // 20000 = [2] with weight 1, trailing groups omitted
+        assert_eq!(decode_numeric(&numeric(1, 0, 2, &[2])).unwrap(), "20000.00");
+        assert_eq!(decode_numeric(&numeric(0, 0, 0, &[])).unwrap(), "0");
+        assert_eq!(decode_numeric(&numeric(0, 0xC000, 0, &[])).unwrap(), "NaN");
+    }
+
+    #[test]
+    fn test_decode_interval() {
+        assert_eq!(
+            decode_interval(4 * 3_600_000_000 + 5 * 60_000_000 + 6_500_000, 3, 14),
+            "P1Y2M3DT4H5M6.5S"
+        );
+        assert_eq!(decode_interval(0, 0, 0), "PT0S");
+        assert_eq!(decode_interval(-90_000_000, 0, 0), "PT-1M-30S");
+    }
+
+    #[test]
+    fn test_decode_column_types() {
+        let mut jsonb = vec![1];
+        jsonb.extend(br#"{"tags": ["a"], "active": true}"#);
+        let json = value(Type::JSONB, &jsonb);
+        assert_eq!(json.get("active"), Some(&Value::Boolean(true)));
+        assert_eq!(
+            json.get("tags").unwrap().to_json(JsonMode::Inline),
+            r#"["a"]"#
+        );
+
+        assert_eq!(
+            value(
+                Type::UUID,
+                &[
+                    0x55, 0x0e, 0x84, 0x00, 0xe2, 0x9b, 0x41, 0xd4, 0xa7, 0x16, 0x44, 0x66, 0x55,
+                    0x44, 0x00, 0x00
+                ]
+            ),
+            "550e8400-e29b-41d4-a716-446655440000".to_value()
+        );
+        assert_eq!(value(Type::BYTEA, b"phlow"), "cGhsb3c=".to_value());
+
+        // 2024-03-01 12:30:00.5 UTC, in microseconds since 2000-01-01
+        let micros: i64 = 762_611_400_500_000;
+        assert_eq!(
+            value(Type::TIMESTAMPTZ, &micros.to_be_bytes()),
+            "2024-03-01T12:30:00.500Z".to_value()
+        );
+        assert_eq!(
+            value(Type::TIMESTAMP, &micros.to_be_bytes()),
+            "2024-03-01T12:30:00.500".to_value()
+        );
+        assert_eq!(
+            value(Type::DATE, &8_825_i32.to_be_bytes()),
+            "2024-02-29".to_value()
+        );
+        assert_eq!(
+            value(Type::TIMESTAMPTZ, &i64::MAX.to_be_bytes()),
+            "infinity".to_value()
+        );
+    }
+
+    #[test]
+    fn test_decode_arrays() {
+        // int4[][] {{1,2},{3,NULL}}
+        let mut raw = Vec::new();
+        raw.extend(2_i32.to_be_bytes()); // dimensions
+        raw.extend(1_i32.to_be_bytes()); // has nulls
+        raw.extend(Type::INT4.oid().to_be_bytes());
+        for _ in 0..2 {
+            raw.extend(2_i32.to_be_bytes()); // length
+            raw.extend(1_i32.to_be_bytes()); // lower bound
+        }
+        for item in [Some(1_i32), Some(2), Some(3), None] {
+            match item {
+                Some(item) => {
+                    raw.extend(4_i32.to_be_bytes());
+                    raw.extend(item.to_be_bytes());
+                }
+                None => raw.extend((-1_i32).to_be_bytes()),
+            }
+        }
+
+        assert_eq!(
+            value(Type::INT4_ARRAY, &raw).to_json(JsonMode::Inline),
+            "[[1,2],[3,null]]"
+        );
+
+        // empty text[]
+        let mut empty = Vec::new();
+        empty.extend(0_i32.to_be_bytes());
+        empty.extend(0_i32.to_be_bytes());
+        empty.extend(Type::TEXT.oid().to_be_bytes());
+        assert_eq!(
+            value(Type::TEXT_ARRAY, &empty).to_json(JsonMode::Inline),
+            "[]"
+        );
+    }
+
+    #[test]
+    fn test_decode_non_text_types() {
+        // A point (two float8) is not text and is kept as raw hex.
+        let mut point = Vec::new();
+        point.extend(1.5_f64.to_be_bytes());
+        point.extend(2.0_f64.to_be_bytes());
+        assert_eq!(
+            value(Type::POINT, &point),
+            "\\x3ff80000000000004000000000000000".to_value()
+        );
+        assert_eq!(value(Type::VARCHAR, b"phlow"), "phlow".to_value());
+    }
+
+    #[tokio::test]
+    async fn test_decode_against_postgres() {
+        let Some(client) = crate::test_db::connect().await else {
+            return;
+        };
+
+        client
+            .batch_execute(
+                "DROP TYPE IF EXISTS phlow_test_mood; CREATE TYPE phlow_test_mood AS ENUM ('happy')",
+            )
+            .await
+            .unwrap();
+
+        let row = client
+            .query_one(
+                "SELECT
+                    true AS bool,
+                    12::int2 AS int2,
+                    9007199254740993::int8 AS int8,
+                    12345.678::numeric AS numeric,
+                    1.5::float8 AS float8,
+                    12.34::money AS money,
+                    'phlow'::bytea AS bytea,
+                    'text'::varchar AS varchar,
+                    'happy'::phlow_test_mood AS mood,
+                    '{\"a\": [1, 2]}'::jsonb AS jsonb,
+                    '550e8400-e29b-41d4-a716-446655440000'::uuid AS uuid,
+                    '2024-02-29'::date AS date,
+                    '12:30:00.5'::time AS time,
+                    '2024-03-01 12:30:00.5+00'::timestamptz AS timestamptz,
+                    '1 year 2 mons 3 days 04:05:06.5'::interval AS interval,
+                    '192.168.0.1/24'::inet AS inet,
+                    ARRAY[[1, 2], [3, NULL]]::int4[] AS matrix,
+                    'infinity'::timestamp AS infinity,
+                    '(1.5,2)'::point AS point",
+                &[],
+            )
+            .await
+            .unwrap();
+
+        let map = row_to_map(&row);
+        let json = |key: &str| map.get(key).unwrap().to_json(JsonMode::Inline);
+
+        assert_eq!(json("bool"), "true");
+        assert_eq!(json("int2"), "12");
+        assert_eq!(json("int8"), "9007199254740993");
+        assert_eq!(json("numeric"), r#""12345.678""#);
+        assert_eq!(json("float8"), "1.5");
+        assert_eq!(json("money"), r#""12.34""#);
+        assert_eq!(json("bytea"), r#""cGhsb3c=""#);
+        assert_eq!(json("varchar"), r#""text""#);
+        assert_eq!(json("mood"), r#""happy""#);
+        assert_eq!(
+            map.get("jsonb"),
+            Some(&Value::json_to_value(r#"{"a": [1, 2]}"#).unwrap())
+        );
+        assert_eq!(json("uuid"), r#""550e8400-e29b-41d4-a716-446655440000""#);
+        assert_eq!(json("date"), r#""2024-02-29""#);
+        assert_eq!(json("time"), r#""12:30:00.500""#);
+        assert_eq!(json("timestamptz"), r#""2024-03-01T12:30:00.500Z""#);
+        assert_eq!(json("interval"), r#""P1Y2M3DT4H5M6.5S""#);
+        assert_eq!(json("inet"), r#""192.168.0.1/24""#);
+        assert_eq!(json("matrix"), "[[1,2],[3,null]]");
+        assert_eq!(json("infinity"), r#""infinity""#);
+        assert_eq!(
+            map.get("point"),
+            Some(&"\\x3ff80000000000004000000000000000".to_value())
+        );
 
-        result
+        client
+            .batch_execute("DROP TYPE phlow_test_mood")
+            .await
+            .unwrap();
     }
 }
@@ -0,0 +1,94 @@
+use crate::cursor::Cursors;
+use crate::input::Input;
+use crate::response::row_to_map;
+use deadpool_postgres::Pool;
+use phlow_sdk::prelude::*;
+
+/// Page size of the stream when the query sets no `fetch_size`.
+pub const DEFAULT_FETCH_SIZE: usize = 100;
+
+/// Reads the query through a cursor and starts the flow once per row, in
+/// order. The next page is only fetched once every row of the current one
+/// has been handled, so a single page is held in memory at a time.
+pub async fn stream(
+    id: ModuleId,
+    main_sender: MainRuntimeSender,
+    dispatch: Dispatch,
+    pool: &Pool,
+    cursors: &Cursors,
+    input: Input,
+) -> Result<usize, String> {
+    let fetch_size = input.fetch_size.unwrap_or(DEFAULT_FETCH_SIZE);
+    let mut cursor = Some(cursors.declare(pool, &input).await?);
+    let mut handled = 0;
+
+    while let Some(current) = cursor.take() {
+        let (rows, next) = current.fetch(fetch_size).await?;
+
+        for row in rows {
+            let span = tracing::dispatcher::with_default(&dispatch, || {
+                tracing::info_span!(
+                    "postgres_stream_row",
+                    otel.name = "postgres stream row",
+                    otel.kind = "consumer",
+                    db.system = "postgresql",
+                )
+            });
+
+            let response = sender_package!(
+                span,
+                dispatch.clone(),
+                id,
+                main_sender,
+                Some(row_to_map(&row).to_value())
+            );
+
+            let response = response.await.unwrap_or(Value::Null);
+            debug!("Row {} handled: {:?}", handled, response);
+            handled += 1;
+        }
+
+        cursor = next;
+    }
+
+    Ok(handled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+
+    #[tokio::test]
+    async fn test_stream_rows() {
+        let Some(pool) = test_db::pool(2) else {
+            return;
+        };
+        let input = test_db::input(
+            r#"{"query": "SELECT i FROM generate_series(1, 5) AS i", "fetch_size": 2}"#,
+        );
+
+        let (sender, receiver) = channel::unbounded::<Package>();
+        let flow = std::thread::spawn(move || {
+            receiver
+                .iter()
+                .map(|package| {
+                    let row = package.request_data.clone().unwrap();
+                    package.response.unwrap().send(Value::Null).unwrap();
+                    row.get("i").cloned().unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let dispatch = Dispatch::none();
+        let handled = stream(0, sender, dispatch, &pool, &Cursors::new(1), input)
+            .await
+            .unwrap();
+
+        assert_eq!(handled, 5);
+        assert_eq!(
+            flow.join().unwrap(),
+            (1..=5).map(|i| i.to_value()).collect::<Vec<_>>()
+        );
+    }
+}
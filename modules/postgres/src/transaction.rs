@@ -0,0 +1,367 @@
+use crate::input::Input;
+use crate::postgres::PostgresConfig;
+use crate::query;
+use deadpool_postgres::Client;
+use phlow_sdk::prelude::*;
+use std::collections::HashMap;
+use tokio_postgres::IsolationLevel;
+
+pub struct TransactionQuery {
+    pub input: Input,
+    /// When set, a failing query rolls back to this savepoint and the
+    /// transaction continues instead of being rolled back entirely.
+    pub savepoint: Option<String>,
+}
+
+/// Ordered list of queries run on one connection and committed together.
+pub struct TransactionInput {
+    pub isolation_level: Option<IsolationLevel>,
+    pub read_only: bool,
+    pub deferrable: bool,
+    pub queries: Vec<TransactionQuery>,
+}
+
+fn isolation_level(value: &str) -> Result<IsolationLevel, String> {
+    match value.to_lowercase().replace([' ', '-'], "_").as_str() {
+        "read_uncommitted" => Ok(IsolationLevel::ReadUncommitted),
+        "read_committed" => Ok(IsolationLevel::ReadCommitted),
+        "repeatable_read" => Ok(IsolationLevel::RepeatableRead),
+        "serializable" => Ok(IsolationLevel::Serializable),
+        other => Err(format!(
+            "Invalid isolation level: {}. Use read_uncommitted, read_committed, repeatable_read or serializable.",
+            other
+        )),
+    }
+}
+
+impl TryFrom<(&Value, &PostgresConfig)> for TransactionInput {
+    type Error = String;
+
+    /// Accepts either a list of queries or `{ queries, isolation_level, read_only, deferrable }`.
+    fn try_from((value, config): (&Value, &PostgresConfig)) -> Result<Self, Self::Error> {
+        let (options, queries) = match value {
+            Value::Array(_) => (None, value),
+            Value::Object(_) => (
+                Some(value),
+                value
+                    .get("queries")
+                    .ok_or_else(|| "Transaction has no queries".to_string())?,
+            ),
+            _ => return Err("Transaction must be a list of queries or an object".to_string()),
+        };
+
+        let flag = |key: &str| {
+            options
+                .and_then(|options| options.get(key))
+                .and_then(Value::as_bool)
+                .copied()
+                .unwrap_or(false)
+        };
+
+        let isolation_level = options
+            .and_then(|options| options.get("isolation_level"))
+            .map(|level| isolation_level(&level.to_string()))
+            .transpose()?;
+
+        let queries = match queries {
+            Value::Array(queries) => queries
+                .into_iter()
+                .enumerate()
+                .map(|(index, query)| {
+                    let savepoint = match query.get("savepoint") {
+                        Some(Value::Boolean(true)) => Some(format!("phlow_savepoint_{}", index)),
+                        Some(Value::String(name)) if is_savepoint_name(name.as_str()) => {
+                            Some(name.as_str().to_string())
+                        }
+                        Some(Value::String(name)) => {
+                            return Err(format!(
+                                "Query {}: invalid savepoint name {}",
+                                index,
+                                name.as_str()
+                            ))
+                        }
+                        _ => None,
+                    };
+                    let input = Input::try_from((Some(query.clone()), config))
+                        .map_err(|e| format!("Query {}: {}", index, e))?;
+
+                    Ok(TransactionQuery { input, savepoint })
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+            _ => return Err("Transaction queries must be a list".to_string()),
+        };
+
+        Ok(TransactionInput {
+            isolation_level,
+            read_only: flag("read_only"),
+            deferrable: flag("deferrable"),
+            queries,
+        })
+    }
+}
+
+/// Runs the queries in order and commits. A failing query without a savepoint
+/// rolls the whole transaction back and fails the step.
+pub async fn run(client: &mut Client, transaction: TransactionInput) -> Result<Value, String> {
+    let mut builder = client
+        .build_transaction()
+        .read_only(transaction.read_only)
+        .deferrable(transaction.deferrable);
+
+    if let Some(level) = transaction.isolation_level {
+        builder = builder.isolation_level(level);
+    }
+
+    let mut tx = builder
+        .start()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let mut results = Vec::new();
+
+    for (index, query) in transaction.queries.iter().enumerate() {
+        let result = match &query.savepoint {
+            Some(name) => {
+                let mut savepoint = tx
+                    .savepoint(name.as_str())
+                    .await
+                    .map_err(|e| format!("Failed to create savepoint {}: {}", name, e))?;
+
+                match query::execute(&mut savepoint, &query.input).await {
+                    Ok(result) => {
+                        savepoint
+                            .commit()
+                            .await
+                            .map_err(|e| format!("Failed to release savepoint {}: {}", name, e))?;
+                        result
+                    }
+                    Err(e) => {
+                        savepoint.rollback().await.map_err(|e| {
+                            format!("Failed to roll back to savepoint {}: {}", name, e)
+                        })?;
+
+                        HashMap::from([
+                            ("error", e.to_value()),
+                            ("rolled_back_to", name.to_value()),
+                        ])
+                        .to_value()
+                    }
+                }
+            }
+            None => match query::execute(&mut tx, &query.input).await {
+                Ok(result) => result,
+                Err(e) => {
+                    if let Err(rollback) = tx.rollback().await {
+                        tracing::error!("Failed to roll back transaction: {}", rollback);
+                    }
+
+                    return Err(format!(
+                        "Transaction rolled back, query {} failed: {}",
+                        index, e
+                    ));
+                }
+            },
+        };
+
+        results.push(result);
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(HashMap::from([
+        ("committed", true.to_value()),
+        ("results", results.to_value()),
+    ])
+    .to_value())
+}
+
+/// Savepoint names are formatted into the SQL as they are, so only plain
+/// identifiers (`^[A-Za-z_][A-Za-z0-9_]{0,62}$`) are accepted.
+fn is_savepoint_name(name: &str) -> bool {
+    let mut chars = name.chars();
+
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && name.len() <= 63
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_db;
+
+    #[test]
+    fn test_transaction_input() {
+        let config = test_db::postgres_config();
+        let value = Value::json_to_value(
+            r#"{
+                "isolation_level": "repeatable read",
+                "read_only": true,
+                "queries": [
+                    {"query": "UPDATE accounts SET balance = balance - :amount WHERE id = :id", "params": {"amount": 10, "id": 1}},
+                    {"query": "INSERT INTO audit (message) VALUES ($1)", "params": ["debit"], "savepoint": true}
+                ]
+            }"#,
+        )
+        .unwrap();
+
+        let transaction = TransactionInput::try_from((&value, &config)).unwrap();
+
+        assert!(matches!(
+            transaction.isolation_level,
+            Some(IsolationLevel::RepeatableRead)
+        ));
+        assert!(transaction.read_only);
+        assert_eq!(transaction.queries.len(), 2);
+        assert_eq!(transaction.queries[0].savepoint, None);
+        assert_eq!(
+            transaction.queries[1].savepoint.as_deref(),
+            Some("phlow_savepoint_1")
+        );
+
+        let invalid =
+            Value::json_to_value(r#"{"isolation_level": "dirty", "queries": []}"#).unwrap();
+        assert!(TransactionInput::try_from((&invalid, &config)).is_err());
+    }
+
+    #[test]
+    fn test_savepoint_names() {
+        let config = test_db::postgres_config();
+        let transaction = |savepoint: &str| {
+            let value = Value::json_to_value(&format!(
+                r#"{{"queries": [{{"query": "SELECT 1", "savepoint": "{}"}}]}}"#,
+                savepoint
+            ))
+            .unwrap();
+            TransactionInput::try_from((&value, &config))
+        };
+
+        assert_eq!(
+            transaction("before_audit").unwrap().queries[0]
+                .savepoint
+                .as_deref(),
+            Some("before_audit")
+        );
+        assert!(transaction("x; DROP TABLE accounts").is_err());
+        assert!(transaction("1st").is_err());
+        assert!(transaction(&"a".repeat(64)).is_err());
+    }
+
+    async fn run_json(client: &mut Client, json: &str) -> Result<Value, String> {
+        let value = Value::json_to_value(json).unwrap();
+        let transaction = TransactionInput::try_from((&value, &test_db::postgres_config()))?;
+        run(client, transaction).await
+    }
+
+    async fn count(client: &Client, table: &str) -> i64 {
+        client
+            .query_one(&format!("SELECT count(*) FROM {}", table), &[])
+            .await
+            .unwrap()
+            .get(0)
+    }
+
+    #[tokio::test]
+    async fn test_run_rolls_back() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = pool.get().await.unwrap();
+        client
+            .batch_execute("CREATE TEMP TABLE tx_rollback (id int PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        let error = run_json(
+            &mut client,
+            r#"[
+                {"query": "INSERT INTO tx_rollback VALUES (1)"},
+                {"query": "INSERT INTO tx_rollback VALUES (2)"},
+                {"query": "INSERT INTO tx_rollback VALUES (1)"}
+            ]"#,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(
+            error.starts_with("Transaction rolled back, query 2"),
+            "{}",
+            error
+        );
+        assert_eq!(count(&client, "tx_rollback").await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_run_savepoint() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = pool.get().await.unwrap();
+        client
+            .batch_execute("CREATE TEMP TABLE tx_savepoint (id int PRIMARY KEY)")
+            .await
+            .unwrap();
+
+        let result = run_json(
+            &mut client,
+            r#"[
+                {"query": "INSERT INTO tx_savepoint VALUES (1)"},
+                {"query": "INSERT INTO tx_savepoint VALUES (1)", "savepoint": "before_duplicate"},
+                {"query": "INSERT INTO tx_savepoint VALUES (2)"}
+            ]"#,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.get("committed"), Some(&true.to_value()));
+        assert_eq!(
+            result
+                .get("results")
+                .and_then(|results| results.get(1))
+                .and_then(|result| result.get("rolled_back_to")),
+            Some(&"before_duplicate".to_value())
+        );
+        assert_eq!(count(&client, "tx_savepoint").await, 2);
+    }
+
+    #[tokio::test]
+    async fn test_run_options() {
+        let Some(pool) = test_db::pool(1) else {
+            return;
+        };
+        let mut client = pool.get().await.unwrap();
+
+        let result = run_json(
+            &mut client,
+            r#"{
+                "isolation_level": "serializable",
+                "read_only": true,
+                "queries": [{"query": "SELECT current_setting('transaction_isolation') AS isolation, current_setting('transaction_read_only') AS read_only"}]
+            }"#,
+        )
+        .await
+        .unwrap();
+        let row = result
+            .get("results")
+            .and_then(|results| results.get(0))
+            .and_then(|result| result.get("rows"))
+            .and_then(|rows| rows.get(0))
+            .cloned()
+            .unwrap();
+
+        assert_eq!(row.get("isolation"), Some(&"serializable".to_value()));
+        assert_eq!(row.get("read_only"), Some(&"on".to_value()));
+
+        let error = run_json(
+            &mut client,
+            r#"{"read_only": true, "queries": [{"query": "CREATE TEMP TABLE tx_read_only (id int)"}]}"#,
+        )
+        .await
+        .unwrap_err();
+        assert!(error.contains("read-only"), "{}", error);
+    }
+}
@@ -0,0 +1,55 @@
+mod scheduler;
+mod setup;
+use phlow_sdk::prelude::*;
+use scheduler::MainSetup;
+use setup::Config;
+
+create_main!(start_server(setup));
+
+pub async fn start_server(
+    setup: ModuleSetup,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    if !setup.is_main() {
+        debug!("This module is not the main module, exiting");
+        match setup.setup_sender.send(None) {
+            Ok(_) => {}
+            Err(e) => {
+                return Err(format!("{:?}", e).into());
+            }
+        };
+        return Ok(());
+    }
+
+    // An invalid schedule fails the setup instead of leaving the application
+    // running with nothing to start its flow.
+    let config = Config::try_from(&setup.with).map_err(|e| format!("{:?}", e))?;
+
+    // The schedule only starts flows, it has nothing to offer as a step.
+    match setup.setup_sender.send(None) {
+        Ok(_) => {}
+        Err(e) => {
+            return Err(format!("{:?}", e).into());
+        }
+    };
+
+    let main_sender = match setup.main_sender.clone() {
+        Some(sender) => sender,
+        None => {
+            return Err("Main sender is None".into());
+        }
+    };
+
+    info!("Main module started");
+
+    scheduler::start(
+        MainSetup {
+            id: setup.id,
+            main_sender,
+            dispatch: setup.dispatch.clone(),
+        },
+        config,
+    )
+    .await;
+
+    Ok(())
+}
@@ -0,0 +1,284 @@
+use crate::setup::{Config, Overlap};
+use chrono::{DateTime, Utc};
+use phlow_sdk::prelude::*;
+use phlow_sdk::tokio::sync::mpsc;
+use rand::Rng;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// What the scheduler needs from the module setup to start the flow.
+#[derive(Clone)]
+pub struct MainSetup {
+    pub id: ModuleId,
+    pub main_sender: MainRuntimeSender,
+    pub dispatch: Dispatch,
+}
+
+#[derive(Debug, Clone)]
+struct Tick {
+    number: u64,
+    scheduled_at: DateTime<Utc>,
+    /// Whether the tick was missed while the application was down.
+    missed: bool,
+}
+
+/// Runs the flow for each tick, following the overlap policy.
+struct Runner {
+    main: MainSetup,
+    config: Arc<Config>,
+    running: Arc<AtomicUsize>,
+    queue: Option<mpsc::UnboundedSender<Tick>>,
+}
+
+impl Runner {
+    fn new(main: MainSetup, config: Arc<Config>) -> Self {
+        let queue = match config.overlap {
+            Overlap::Queue => {
+                let (tx, mut rx) = mpsc::unbounded_channel::<Tick>();
+                let main = main.clone();
+                let config = config.clone();
+
+                tokio::spawn(async move {
+                    while let Some(tick) = rx.recv().await {
+                        run(&main, &config, tick).await;
+                    }
+                });
+
+                Some(tx)
+            }
+            _ => None,
+        };
+
+        Self {
+            main,
+            config,
+            running: Arc::new(AtomicUsize::new(0)),
+            queue,
+        }
+    }
+
+    /// Runs a tick to completion, whatever the overlap policy.
+    async fn run(&self, tick: Tick) {
+        run(&self.main, &self.config, tick).await;
+    }
+
+    fn submit(&self, tick: Tick) {
+        if let Some(queue) = &self.queue {
+            if queue.send(tick).is_err() {
+                error!("Schedule queue is closed");
+            }
+            return;
+        }
+
+        if self.config.overlap == Overlap::Skip && self.running.load(Ordering::SeqCst) > 0 {
+            warn!(
+                "Skipping tick {} scheduled at {}, the previous one is still running",
+                tick.number, tick.scheduled_at
+            );
+            return;
+        }
+
+        let main = self.main.clone();
+        let config = self.config.clone();
+        let running = self.running.clone();
+
+        running.fetch_add(1, Ordering::SeqCst);
+        tokio::spawn(async move {
+            run(&main, &config, tick).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
+
+fn local(config: &Config, time: DateTime<Utc>) -> String {
+    config.timezone.format(time)
+}
+
+async fn run(main: &MainSetup, config: &Config, tick: Tick) {
+    let MainSetup {
+        id,
+        main_sender: sender,
+        dispatch,
+    } = main.clone();
+
+    let data: HashMap<String, Value> = HashMap::from([
+        (
+            "scheduled_at".to_string(),
+            local(config, tick.scheduled_at).to_value(),
+        ),
+        ("fired_at".to_string(), local(config, Utc::now()).to_value()),
+        ("timezone".to_string(), config.timezone_name.to_value()),
+        ("tick".to_string(), tick.number.to_value()),
+        ("missed".to_string(), tick.missed.to_value()),
+    ]);
+
+    let span = tracing::dispatcher::with_default(&dispatch, || {
+        tracing::info_span!(
+            "schedule_tick",
+            otel.name = "schedule tick",
+            otel.kind = "internal",
+            schedule.tick = tick.number,
+            schedule.scheduled_at = tick.scheduled_at.to_rfc3339().as_str(),
+            schedule.missed = tick.missed,
+        )
+    });
+
+    debug!(
+        "Running tick {} scheduled at {}",
+        tick.number, tick.scheduled_at
+    );
+
+    let result = sender_package!(span, dispatch, id, sender, Some(data.to_value()))
+        .await
+        .ok();
+
+    debug!("Tick {} result: {:?}", tick.number, result);
+}
+
+/// Reads the time of the last tick from the state file.
+fn last_tick(config: &Config) -> Option<DateTime<Utc>> {
+    let content = std::fs::read_to_string(&config.state_file).ok()?;
+    let state = Value::json_to_value(&content).ok()?;
+    let last_tick = state.get("last_tick")?.to_string();
+
+    DateTime::parse_from_rfc3339(&last_tick)
+        .ok()
+        .map(|time| time.with_timezone(&Utc))
+}
+
+fn save_last_tick(config: &Config, time: DateTime<Utc>) {
+    let state: HashMap<String, Value> =
+        HashMap::from([("last_tick".to_string(), time.to_rfc3339().to_value())]);
+
+    if let Some(parent) = config.state_file.parent() {
+        let _ = std::fs::create_dir_all(parent);
+    }
+
+    if let Err(e) = std::fs::write(
+        &config.state_file,
+        state.to_value().to_json(JsonMode::Inline),
+    ) {
+        warn!(
+            "Failed to write schedule state to {}: {}",
+            config.state_file.display(),
+            e
+        );
+    }
+}
+
+/// The ticks between the last recorded one and now, keeping the most recent
+/// `catch_up_limit` of them.
+fn missed_ticks(config: &Config, now: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let mut missed = Vec::new();
+    let mut last = match last_tick(config) {
+        Some(last) => last,
+        None => return missed,
+    };
+
+    while let Some(next) = config.trigger.next_after(last, &config.timezone) {
+        if next > now {
+            break;
+        }
+
+        missed.push(next);
+        if missed.len() > config.catch_up_limit {
+            missed.remove(0);
+        }
+        last = next;
+    }
+
+    missed
+}
+
+fn jitter(config: &Config) -> std::time::Duration {
+    match config.jitter {
+        Some(jitter) if !jitter.is_zero() => {
+            let millis = rand::thread_rng().gen_range(0..=jitter.as_millis() as u64);
+            std::time::Duration::from_millis(millis)
+        }
+        _ => std::time::Duration::ZERO,
+    }
+}
+
+pub async fn start(main: MainSetup, config: Config) {
+    let config = Arc::new(config);
+    let runner = Runner::new(main, config.clone());
+    let mut number = 0;
+
+    let now = Utc::now();
+
+    if config.catch_up {
+        let missed = missed_ticks(&config, now);
+
+        if !missed.is_empty() {
+            info!("Running {} missed ticks", missed.len());
+        }
+
+        // Missed ticks run one after another, so the overlap policy cannot
+        // skip them, and each is recorded once it has run.
+        for scheduled_at in missed {
+            number += 1;
+            runner
+                .run(Tick {
+                    number,
+                    scheduled_at,
+                    missed: true,
+                })
+                .await;
+            save_last_tick(&config, scheduled_at);
+        }
+    }
+
+    let mut next = config.trigger.next_after(now, &config.timezone);
+
+    while let Some(scheduled_at) = next {
+        let wait = (scheduled_at - Utc::now()).to_std().unwrap_or_default() + jitter(&config);
+        tokio::time::sleep(wait).await;
+
+        number += 1;
+        runner.submit(Tick {
+            number,
+            scheduled_at,
+            missed: false,
+        });
+
+        if config.catch_up {
+            save_last_tick(&config, scheduled_at);
+        }
+
+        // Ticks that passed while this one was waiting are not run.
+        next = config
+            .trigger
+            .next_after(scheduled_at.max(Utc::now()), &config.timezone);
+    }
+
+    warn!("The schedule has no further ticks");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_missed_ticks() {
+        let dir = std::env::temp_dir().join(format!("phlow-schedule-{}", std::process::id()));
+        let value = Value::json_to_value(&format!(
+            r#"{{"cron": "0 * * * *", "catch_up": true, "catch_up_limit": 2, "state_file": "{}"}}"#,
+            dir.join("state.json").display()
+        ))
+        .unwrap();
+        let config = Config::try_from(&value).unwrap();
+        let time = |time: &str| DateTime::parse_from_rfc3339(time).unwrap().to_utc();
+
+        assert!(missed_ticks(&config, time("2025-01-01T05:30:00Z")).is_empty());
+
+        save_last_tick(&config, time("2025-01-01T01:00:00Z"));
+        assert_eq!(
+            missed_ticks(&config, time("2025-01-01T05:30:00Z")),
+            vec![time("2025-01-01T04:00:00Z"), time("2025-01-01T05:00:00Z")]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
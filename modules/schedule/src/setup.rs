@@ -0,0 +1,319 @@
+use chrono::{DateTime, FixedOffset, Local, SecondsFormat, TimeZone, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use phlow_sdk::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_STATE_FILE: &str = ".phlow/schedule.json";
+const DEFAULT_CATCH_UP_LIMIT: usize = 10;
+
+#[derive(Debug, Clone)]
+pub enum Trigger {
+    Cron(Box<Cron>),
+    Interval(Duration),
+}
+
+impl Trigger {
+    /// The first tick after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>, timezone: &Timezone) -> Option<DateTime<Utc>> {
+        match self {
+            Trigger::Cron(cron) => match timezone {
+                Timezone::Fixed(offset) => next_cron(cron, after, offset),
+                Timezone::Zone(zone) => next_cron(cron, after, zone),
+                Timezone::Local => next_cron(cron, after, &Local),
+            },
+            Trigger::Interval(interval) => {
+                Some(after + chrono::Duration::from_std(*interval).ok()?)
+            }
+        }
+    }
+}
+
+/// Cron expressions are matched against the local time of the zone, so that
+/// ticks follow its daylight saving changes.
+fn next_cron<Z: TimeZone>(cron: &Cron, after: DateTime<Utc>, zone: &Z) -> Option<DateTime<Utc>> {
+    cron.find_next_occurrence(&after.with_timezone(zone), false)
+        .ok()
+        .map(|next| next.with_timezone(&Utc))
+}
+
+/// The timezone schedules are evaluated in. Named zones come from the tz
+/// database compiled into `chrono-tz`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Timezone {
+    Fixed(FixedOffset),
+    Zone(Tz),
+    /// The zone of the system, from `/etc/localtime`.
+    Local,
+}
+
+impl Timezone {
+    /// `time` in this timezone, as RFC 3339.
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            Timezone::Fixed(offset) => time
+                .with_timezone(offset)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            Timezone::Zone(zone) => time
+                .with_timezone(zone)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+            Timezone::Local => time
+                .with_timezone(&Local)
+                .to_rfc3339_opts(SecondsFormat::Millis, true),
+        }
+    }
+}
+
+impl TryFrom<&str> for Timezone {
+    type Error = String;
+
+    fn try_from(name: &str) -> Result<Self, Self::Error> {
+        if name.eq_ignore_ascii_case("utc") || name == "Z" {
+            return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+        }
+
+        if name.starts_with('+') || name.starts_with('-') {
+            return name
+                .parse::<FixedOffset>()
+                .map(Timezone::Fixed)
+                .map_err(|_| format!("Invalid timezone offset: {}", name));
+        }
+
+        if name.eq_ignore_ascii_case("local") {
+            return match std::env::var("TZ") {
+                Ok(tz) if !tz.is_empty() => Timezone::try_from(tz.trim_start_matches(':')),
+                _ => Ok(Timezone::Local),
+            };
+        }
+
+        name.parse::<Tz>()
+            .map(Timezone::Zone)
+            .map_err(|_| format!("Unknown timezone: {}", name))
+    }
+}
+
+/// What to do with a tick while the flow of an earlier one is still running.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Overlap {
+    /// Drop the tick.
+    Skip,
+    /// Run it once the earlier flows finish.
+    Queue,
+    /// Run it right away, alongside the earlier flows.
+    Allow,
+}
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub trigger: Trigger,
+    pub timezone: Timezone,
+    pub timezone_name: String,
+    pub overlap: Overlap,
+    /// Upper bound of the random delay added to each tick.
+    pub jitter: Option<Duration>,
+    /// Run the ticks missed while the application was down, on startup.
+    pub catch_up: bool,
+    pub catch_up_limit: usize,
+    /// Where the time of the last tick is kept, for `catch_up`.
+    pub state_file: PathBuf,
+}
+
+/// `500ms`, `30s`, `5m`, `1h`, `1d`, or a number of seconds.
+pub fn parse_duration(value: &Value) -> Result<Duration, String> {
+    if let Some(seconds) = value.to_u64() {
+        return Ok(Duration::from_secs(seconds));
+    }
+
+    let value = value.to_string();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(split);
+    let amount: u64 = amount
+        .parse()
+        .map_err(|_| format!("Invalid duration: {}", value))?;
+
+    let millis = match unit.trim() {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60 * 1000,
+        "h" => 60 * 60 * 1000,
+        "d" => 24 * 60 * 60 * 1000,
+        _ => return Err(format!("Invalid duration: {}", value)),
+    };
+
+    amount
+        .checked_mul(millis)
+        .map(Duration::from_millis)
+        .ok_or_else(|| format!("Duration out of range: {}", value))
+}
+
+impl TryFrom<&Value> for Config {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let trigger = match (value.get("cron"), value.get("interval")) {
+            (Some(cron), None) => Trigger::Cron(Box::new(
+                Cron::new(&cron.to_string())
+                    .with_seconds_optional()
+                    .parse()
+                    .map_err(|e| format!("Invalid cron expression {}: {}", cron, e))?,
+            )),
+            (None, Some(interval)) => {
+                let interval = parse_duration(interval)?;
+                if interval.is_zero() {
+                    return Err("interval must be greater than zero".to_string());
+                }
+                Trigger::Interval(interval)
+            }
+            (Some(_), Some(_)) => return Err("Use either cron or interval, not both".to_string()),
+            (None, None) => return Err("cron or interval is required".to_string()),
+        };
+
+        let timezone_name = value
+            .get("timezone")
+            .map(|v| v.to_string())
+            .unwrap_or("UTC".to_string());
+        let timezone = Timezone::try_from(timezone_name.as_str())?;
+
+        let overlap = match value.get("overlap").map(|v| v.to_string()).as_deref() {
+            None | Some("skip") => Overlap::Skip,
+            Some("queue") => Overlap::Queue,
+            Some("allow") => Overlap::Allow,
+            Some(overlap) => {
+                return Err(format!(
+                    "Invalid overlap: {}. Use 'skip', 'queue' or 'allow'.",
+                    overlap
+                ))
+            }
+        };
+
+        let jitter = value.get("jitter").map(parse_duration).transpose()?;
+
+        let catch_up = *value
+            .get("catch_up")
+            .map(|v| v.as_bool().unwrap_or(&false))
+            .unwrap_or(&false);
+
+        let catch_up_limit = value
+            .get("catch_up_limit")
+            .and_then(|v| v.to_u64())
+            .map(|limit| limit as usize)
+            .unwrap_or(DEFAULT_CATCH_UP_LIMIT);
+
+        let state_file = value
+            .get("state_file")
+            .map(|v| v.to_string())
+            .unwrap_or(DEFAULT_STATE_FILE.to_string());
+
+        Ok(Self {
+            trigger,
+            timezone,
+            timezone_name,
+            overlap,
+            jitter,
+            catch_up,
+            catch_up_limit,
+            state_file: PathBuf::from(state_file),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_config() {
+        let value = Value::json_to_value(
+            r#"{"interval": "90s", "overlap": "queue", "jitter": "500ms", "timezone": "-03:00"}"#,
+        )
+        .unwrap();
+        let config = Config::try_from(&value).unwrap();
+
+        assert!(matches!(config.trigger, Trigger::Interval(d) if d == Duration::from_secs(90)));
+        assert_eq!(config.overlap, Overlap::Queue);
+        assert_eq!(config.jitter, Some(Duration::from_millis(500)));
+        assert_eq!(
+            config.timezone,
+            Timezone::Fixed(FixedOffset::west_opt(3 * 3600).unwrap())
+        );
+        assert!(!config.catch_up);
+
+        let value = Value::json_to_value(r#"{"cron": "*/5 * * * *", "interval": 10}"#).unwrap();
+        assert!(Config::try_from(&value).is_err());
+
+        let value = Value::json_to_value(r#"{"interval": "10 minutes"}"#).unwrap();
+        assert!(Config::try_from(&value).is_err());
+
+        let value = Value::json_to_value(r#"{"interval": "18446744073709551615d"}"#).unwrap();
+        assert!(Config::try_from(&value).is_err());
+    }
+
+    fn time(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().to_utc()
+    }
+
+    fn next(config: &str, after: &str) -> Option<DateTime<Utc>> {
+        let config = Config::try_from(&Value::json_to_value(config).unwrap()).unwrap();
+        config.trigger.next_after(time(after), &config.timezone)
+    }
+
+    #[test]
+    fn test_cron_next_after() {
+        assert_eq!(
+            next(r#"{"cron": "*/15 * * * *"}"#, "2025-01-01T10:07:00Z"),
+            Some(time("2025-01-01T10:15:00Z"))
+        );
+        assert_eq!(
+            next(r#"{"cron": "30 0 9 * * mon-fri"}"#, "2025-01-03T09:00:30Z"),
+            Some(time("2025-01-06T09:00:30Z"))
+        );
+        assert_eq!(
+            next(
+                r#"{"cron": "@monthly", "timezone": "-03:00"}"#,
+                "2025-01-15T00:00:00Z"
+            ),
+            Some(time("2025-02-01T03:00:00Z"))
+        );
+
+        let value = Value::json_to_value(r#"{"cron": "61 * * * *"}"#).unwrap();
+        assert!(Config::try_from(&value).is_err());
+    }
+
+    #[test]
+    fn test_cron_named_timezone() {
+        // New York moves from EST (-05:00) to EDT (-04:00) on 2025-03-09.
+        let config = r#"{"cron": "0 9 * * *", "timezone": "America/New_York"}"#;
+
+        assert_eq!(
+            next(config, "2025-03-08T15:00:00Z"),
+            Some(time("2025-03-09T13:00:00Z"))
+        );
+        assert_eq!(
+            next(config, "2025-03-07T15:00:00Z"),
+            Some(time("2025-03-08T14:00:00Z"))
+        );
+
+        let value =
+            Value::json_to_value(r#"{"cron": "0 9 * * *", "timezone": "Mars/Olympus"}"#).unwrap();
+        assert!(Config::try_from(&value).is_err());
+    }
+
+    #[test]
+    fn test_timezone_format() {
+        let zone = Timezone::try_from("America/Sao_Paulo").unwrap();
+
+        assert_eq!(
+            zone.format(time("2025-01-01T12:00:00Z")),
+            "2025-01-01T09:00:00.000-03:00"
+        );
+        assert_eq!(
+            Timezone::try_from("UTC")
+                .unwrap()
+                .format(time("2025-01-01T12:00:00Z")),
+            "2025-01-01T12:00:00.000Z"
+        );
+    }
+}
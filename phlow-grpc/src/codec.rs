@@ -0,0 +1,188 @@
+use phlow_sdk::prelude::*;
+use prost_reflect::prost::bytes::Buf;
+use prost_reflect::prost::Message;
+use prost_reflect::{
+    DeserializeOptions, DynamicMessage, MessageDescriptor, ReflectMessage, SerializeOptions,
+};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::Status;
+
+/// Encodes and decodes messages known only from their descriptor.
+#[derive(Clone)]
+pub struct DynamicCodec {
+    decode: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    /// A codec decoding messages of type `decode`.
+    pub fn new(decode: MessageDescriptor) -> Self {
+        Self { decode }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        DynamicDecoder(self.decode.clone())
+    }
+}
+
+pub struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        item.encode(dst)
+            .map_err(|e| Status::internal(format!("Failed to encode message: {}", e)))
+    }
+}
+
+pub struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        decode(self.0.clone(), src).map(Some)
+    }
+}
+
+/// Bytes that do not decode as the expected message were sent wrong, so they
+/// fail with INVALID_ARGUMENT rather than as an internal error.
+fn decode(desc: MessageDescriptor, buf: impl Buf) -> Result<DynamicMessage, Status> {
+    DynamicMessage::decode(desc, buf)
+        .map_err(|e| Status::invalid_argument(format!("Failed to decode message: {}", e)))
+}
+
+/// The message in the proto3 JSON mapping, keeping the field names of the
+/// proto and every field, even when it has its default value.
+pub fn to_value(message: &DynamicMessage) -> Value {
+    let options = SerializeOptions::new()
+        .stringify_64_bit_integers(false)
+        .use_proto_field_name(true)
+        .skip_default_fields(false);
+
+    let json = message
+        .serialize_with_options(serde_json::value::Serializer, &options)
+        .map_err(|e| e.to_string())
+        .and_then(|json| Value::json_to_value(&json.to_string()).map_err(|e| format!("{:?}", e)));
+
+    match json {
+        Ok(value) => value,
+        Err(e) => {
+            error!(
+                "Failed to convert {}: {}",
+                message.descriptor().full_name(),
+                e
+            );
+            Value::Null
+        }
+    }
+}
+
+/// Builds a message of type `desc` from a value in the proto3 JSON mapping.
+/// Null is the empty message, and unknown fields are ignored.
+pub fn from_value(desc: MessageDescriptor, value: &Value) -> Result<DynamicMessage, String> {
+    if value.is_null() {
+        return Ok(DynamicMessage::new(desc));
+    }
+
+    let json = value.to_json(JsonMode::Inline);
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let options = DeserializeOptions::new().deny_unknown_fields(false);
+
+    let message =
+        DynamicMessage::deserialize_with_options(desc.clone(), &mut deserializer, &options)
+            .and_then(|message| deserializer.end().map(|_| message))
+            .map_err(|e| format!("Invalid {}: {}", desc.full_name(), e))?;
+
+    Ok(message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use prost_reflect::prost_types::{
+        field_descriptor_proto::Type, DescriptorProto, FieldDescriptorProto, FileDescriptorProto,
+        FileDescriptorSet,
+    };
+    use prost_reflect::DescriptorPool;
+
+    fn user() -> MessageDescriptor {
+        let field = |name: &str, number: i32, kind: Type| FieldDescriptorProto {
+            name: Some(name.to_string()),
+            number: Some(number),
+            r#type: Some(kind as i32),
+            json_name: None,
+            ..Default::default()
+        };
+
+        let pool = DescriptorPool::from_file_descriptor_set(FileDescriptorSet {
+            file: vec![FileDescriptorProto {
+                name: Some("user.proto".to_string()),
+                package: Some("test".to_string()),
+                syntax: Some("proto3".to_string()),
+                message_type: vec![DescriptorProto {
+                    name: Some("User".to_string()),
+                    field: vec![
+                        field("user_id", 1, Type::Int64),
+                        field("name", 2, Type::String),
+                    ],
+                    ..Default::default()
+                }],
+                ..Default::default()
+            }],
+        })
+        .unwrap();
+
+        pool.get_message_by_name("test.User").unwrap()
+    }
+
+    #[test]
+    fn test_value_round_trip() {
+        let value =
+            Value::json_to_value(r#"{"user_id": 42, "name": "Ada", "extra": true}"#).unwrap();
+        let message = from_value(user(), &value).unwrap();
+
+        assert_eq!(
+            to_value(&message),
+            Value::json_to_value(r#"{"user_id": 42, "name": "Ada"}"#).unwrap()
+        );
+        assert_eq!(
+            to_value(&from_value(user(), &Value::Null).unwrap()),
+            Value::json_to_value(r#"{"user_id": 0, "name": ""}"#).unwrap()
+        );
+        assert!(from_value(
+            user(),
+            &Value::json_to_value(r#"{"user_id": "x"}"#).unwrap()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_decode_invalid_message() {
+        let message =
+            from_value(user(), &Value::json_to_value(r#"{"user_id": 42}"#).unwrap()).unwrap();
+        let bytes = message.encode_to_vec();
+
+        assert_eq!(
+            to_value(&decode(user(), bytes.as_slice()).unwrap()),
+            to_value(&message)
+        );
+
+        // A length-delimited field cut short.
+        let status = decode(user(), [0x12, 0x05, b'A'].as_slice()).unwrap_err();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+    }
+}
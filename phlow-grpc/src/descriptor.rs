@@ -0,0 +1,100 @@
+use prost_reflect::DescriptorPool;
+use protobuf::descriptor::FileDescriptorSet;
+use protobuf::Message;
+use std::path::Path;
+
+/// Parses `.proto` files into an encoded `FileDescriptorSet`. Each file's
+/// directory is an include path, so sibling imports resolve without setup.
+pub fn compile(protos: &[String], includes: &[String]) -> Result<Vec<u8>, String> {
+    let mut parser = protobuf_parse::Parser::new();
+    parser.pure().includes(includes).inputs(protos);
+
+    for proto in protos {
+        if let Some(parent) = Path::new(proto).parent() {
+            parser.include(match parent.as_os_str().is_empty() {
+                true => Path::new("."),
+                false => parent,
+            });
+        }
+    }
+
+    // The imported files are kept too, the pool needs them to resolve types.
+    let mut set = FileDescriptorSet::new();
+    set.file = parser
+        .parse_and_typecheck()
+        .map_err(|e| format!("Failed to parse protos: {:#}", e))?
+        .file_descriptors;
+
+    set.write_to_bytes()
+        .map_err(|e| format!("Failed to encode descriptors: {}", e))
+}
+
+/// Loads a compiled descriptor set and the `.proto` files into one pool.
+pub fn load(
+    descriptor_set: Option<&str>,
+    protos: &[String],
+    includes: &[String],
+) -> Result<DescriptorPool, String> {
+    let mut pool = DescriptorPool::new();
+
+    if let Some(path) = descriptor_set {
+        let bytes = std::fs::read(path)
+            .map_err(|e| format!("Failed to read descriptor set {}: {}", path, e))?;
+        pool.decode_file_descriptor_set(bytes.as_slice())
+            .map_err(|e| format!("Invalid descriptor set {}: {}", path, e))?;
+    }
+
+    if !protos.is_empty() {
+        let bytes = compile(protos, includes)?;
+        pool.decode_file_descriptor_set(bytes.as_slice())
+            .map_err(|e| format!("Invalid protos: {}", e))?;
+    }
+
+    Ok(pool)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compile_with_imports() {
+        let dir = std::env::temp_dir().join(format!("phlow-grpc-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("common.proto"),
+            "syntax = \"proto3\";\npackage common;\nmessage Empty {}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("users.proto"),
+            r#"syntax = "proto3";
+package users;
+import "common.proto";
+import "google/protobuf/timestamp.proto";
+service Users {
+  rpc Get (GetRequest) returns (User);
+  rpc List (common.Empty) returns (stream User);
+}
+message GetRequest { int64 id = 1; }
+message User { int64 id = 1; string name = 2; google.protobuf.Timestamp created_at = 3; }
+"#,
+        )
+        .unwrap();
+
+        let protos = vec![dir.join("users.proto").display().to_string()];
+        let pool = load(None, &protos, &[]).unwrap();
+        let service = pool.get_service_by_name("users.Users").unwrap();
+        let methods: Vec<_> = service
+            .methods()
+            .map(|method| (method.name().to_string(), method.is_server_streaming()))
+            .collect();
+
+        assert_eq!(
+            methods,
+            vec![("Get".to_string(), false), ("List".to_string(), true)]
+        );
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,78 @@
+pub mod codec;
+pub mod descriptor;
+pub mod server;
+#[cfg(feature = "testing")]
+pub mod testing;
+use phlow_sdk::prelude::*;
+use tonic::Code;
+
+/// Status codes by their position, as named in the gRPC spec.
+pub const CODES: [&str; 17] = [
+    "OK",
+    "CANCELLED",
+    "UNKNOWN",
+    "INVALID_ARGUMENT",
+    "DEADLINE_EXCEEDED",
+    "NOT_FOUND",
+    "ALREADY_EXISTS",
+    "PERMISSION_DENIED",
+    "RESOURCE_EXHAUSTED",
+    "FAILED_PRECONDITION",
+    "ABORTED",
+    "OUT_OF_RANGE",
+    "UNIMPLEMENTED",
+    "INTERNAL",
+    "UNAVAILABLE",
+    "DATA_LOSS",
+    "UNAUTHENTICATED",
+];
+
+/// The spec name of a status code, such as `NOT_FOUND`.
+pub fn code_name(code: Code) -> &'static str {
+    CODES[code as usize]
+}
+
+/// The status code named `name`, in any case. Unknown names are `UNKNOWN`.
+pub fn code_from_name(name: &str) -> Code {
+    let name = name.to_uppercase();
+
+    CODES
+        .iter()
+        .position(|code| *code == name)
+        .map(|code| Code::from_i32(code as i32))
+        .unwrap_or(Code::Unknown)
+}
+
+/// A list of strings, or a single one.
+pub fn strings(value: &Value, key: &str) -> Vec<String> {
+    match value.get(key) {
+        Some(Value::Array(items)) => items.into_iter().map(|item| item.to_string()).collect(),
+        Some(Value::Null) | None => Vec::new(),
+        Some(item) => vec![item.to_string()],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes() {
+        assert_eq!(code_name(Code::NotFound), "NOT_FOUND");
+        assert_eq!(code_name(Code::Unauthenticated), "UNAUTHENTICATED");
+        assert_eq!(code_from_name("not_found"), Code::NotFound);
+        assert_eq!(code_from_name("NOPE"), Code::Unknown);
+    }
+
+    #[test]
+    fn test_strings() {
+        let value = Value::json_to_value(r#"{"one": "a.proto", "many": ["a", "b"]}"#).unwrap();
+
+        assert_eq!(strings(&value, "one"), vec!["a.proto".to_string()]);
+        assert_eq!(
+            strings(&value, "many"),
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert!(strings(&value, "none").is_empty());
+    }
+}
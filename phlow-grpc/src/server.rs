@@ -0,0 +1,34 @@
+use hyper::server::conn::http2;
+use hyper::service::service_fn;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use phlow_sdk::prelude::*;
+use phlow_sdk::tokio::net::TcpListener;
+use std::convert::Infallible;
+use std::future::Future;
+use tonic::body::Body;
+
+/// A gRPC call as received by the server.
+pub type HttpRequest = hyper::Request<hyper::body::Incoming>;
+
+/// Serves gRPC over HTTP/2 on `listener`, answering every call with `handle`.
+/// Only returns when accepting a connection fails.
+pub async fn serve<F, Fut>(listener: TcpListener, handle: F) -> std::io::Result<()>
+where
+    F: Fn(HttpRequest) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<hyper::Response<Body>, Infallible>> + Send + 'static,
+{
+    loop {
+        let (tcp, _) = listener.accept().await?;
+        let io = TokioIo::new(tcp);
+        let handle = handle.clone();
+
+        tokio::task::spawn(async move {
+            if let Err(e) = http2::Builder::new(TokioExecutor::new())
+                .serve_connection(io, service_fn(handle))
+                .await
+            {
+                debug!("Error serving connection: {}", e);
+            }
+        });
+    }
+}
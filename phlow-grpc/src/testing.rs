@@ -0,0 +1,54 @@
+//! Fixtures for the tests of the gRPC modules: a `users.Users` service and a
+//! server on an ephemeral local port.
+use crate::descriptor;
+use crate::server::{serve, HttpRequest};
+use phlow_sdk::prelude::*;
+use phlow_sdk::tokio::net::TcpListener;
+use prost_reflect::DescriptorPool;
+use std::convert::Infallible;
+use std::future::Future;
+use std::sync::OnceLock;
+use tonic::body::Body;
+
+pub const USERS_PROTO: &str = r#"syntax = "proto3";
+package users;
+service Users {
+  rpc Get (GetRequest) returns (User);
+  rpc List (ListRequest) returns (stream User);
+}
+message GetRequest { int64 id = 1; }
+message ListRequest { int64 count = 1; }
+message User { int64 id = 1; string name = 2; }
+"#;
+
+/// The descriptors of [`USERS_PROTO`], compiled once per test binary.
+pub fn users() -> DescriptorPool {
+    static POOL: OnceLock<DescriptorPool> = OnceLock::new();
+
+    POOL.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("phlow-grpc-users-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("users.proto"), USERS_PROTO).unwrap();
+
+        let protos = vec![dir.join("users.proto").display().to_string()];
+        let pool = descriptor::load(None, &protos, &[]).unwrap();
+
+        let _ = std::fs::remove_dir_all(dir);
+        pool
+    })
+    .clone()
+}
+
+/// Serves `handle` on an ephemeral local port and returns its url.
+pub async fn spawn<F, Fut>(handle: F) -> String
+where
+    F: Fn(HttpRequest) -> Fut + Clone + Send + 'static,
+    Fut: Future<Output = Result<hyper::Response<Body>, Infallible>> + Send + 'static,
+{
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(serve(listener, handle));
+
+    format!("http://{}", addr)
+}
@@ -12,11 +12,37 @@ pub struct MainArgs {
     pub ext: ModuleExtension,
 }
 
+#[derive(Debug)]
+pub enum MigrateAction {
+    Up,
+    List,
+    Rollback { steps: u64 },
+}
+
+#[derive(Debug)]
+pub enum SubCommand {
+    OpenApi {
+        main: MainArgs,
+        output: Option<String>,
+    },
+    Migrate {
+        main: MainArgs,
+        action: MigrateAction,
+        module: Option<String>,
+    },
+    Run {
+        main: MainArgs,
+        input: Option<String>,
+        jsonl: bool,
+    },
+}
+
 #[derive(Debug)]
 pub struct Cli {
     pub main: Option<MainArgs>,
     pub only_download_modules: bool,
     pub publish_path: Option<String>,
+    pub command: Option<SubCommand>,
 }
 
 impl Cli {
@@ -51,19 +77,116 @@ impl Cli {
                     .long("publish")
                     .help("Publish module on phlow.dev"),
             )
+            .subcommand(
+                Command::new("openapi")
+                    .about("Print the OpenAPI document of the main module routes")
+                    .arg(
+                        Arg::new("main_path")
+                            .help("Main path/file to load")
+                            .required(false)
+                            .index(1),
+                    )
+                    .arg(
+                        Arg::new("output")
+                            .long("output")
+                            .short('o')
+                            .help("Write the document to this file instead of stdout"),
+                    ),
+            )
+            .subcommand(
+                migrate_args(
+                    Command::new("migrate")
+                        .about("Run, list or roll back the migrations of the modules in the main file")
+                        .subcommand(
+                            migrate_args(Command::new("up").about("Apply the pending migrations (the default)")),
+                        )
+                        .subcommand(migrate_args(
+                            Command::new("list").about("List the migrations and whether they were applied"),
+                        ))
+                        .subcommand(
+                            migrate_args(Command::new("rollback").about("Roll back the latest migrations")).arg(
+                                Arg::new("steps")
+                                    .long("steps")
+                                    .short('s')
+                                    .help("Number of migrations to roll back")
+                                    .value_parser(clap::value_parser!(u64))
+                                    .default_value("1"),
+                            ),
+                        ),
+                ),
+            )
+            .subcommand(
+                Command::new("run")
+                    .about("Run the flow once with an input and print the result, without a main module")
+                    .arg(
+                        Arg::new("main_path")
+                            .help("Main path/file to load")
+                            .required(false)
+                            .index(1),
+                    )
+                    .arg(
+                        Arg::new("input")
+                            .long("input")
+                            .help("JSON file used as main, or - to read it from stdin (the default)"),
+                    )
+                    .arg(
+                        Arg::new("jsonl")
+                            .long("jsonl")
+                            .help("Read one JSON input per line and run the flow for each of them")
+                            .action(clap::ArgAction::SetTrue),
+                    ),
+            )
             .get_matches();
 
-        let main = match matches.get_one::<String>("main_path") {
-            Some(file) => {
-                let (path, ext) = get_main_file(file)?;
-                Some(MainArgs { path, ext })
+        let command = match matches.subcommand() {
+            Some(("openapi", sub_matches)) => {
+                let main = resolve_main(sub_matches.get_one::<String>("main_path"))?
+                    .ok_or_else(|| Error::ModuleNotFound("main".to_string()))?;
+                let output = sub_matches
+                    .get_one::<String>("output")
+                    .map(|s| s.to_string());
+
+                Some(SubCommand::OpenApi { main, output })
             }
-            None => match find_default_file("") {
-                Some((path, ext)) => Some(MainArgs { path, ext }),
-                None => None,
-            },
+            Some(("migrate", sub_matches)) => {
+                let (action, sub_matches) = match sub_matches.subcommand() {
+                    Some(("list", list_matches)) => (MigrateAction::List, list_matches),
+                    Some(("rollback", rollback_matches)) => (
+                        MigrateAction::Rollback {
+                            steps: *rollback_matches.get_one::<u64>("steps").unwrap_or(&1),
+                        },
+                        rollback_matches,
+                    ),
+                    Some((_, up_matches)) => (MigrateAction::Up, up_matches),
+                    None => (MigrateAction::Up, sub_matches),
+                };
+                let main = resolve_main(sub_matches.get_one::<String>("main_path"))?
+                    .ok_or_else(|| Error::ModuleNotFound("main".to_string()))?;
+                let module = sub_matches
+                    .get_one::<String>("module")
+                    .map(|s| s.to_string());
+
+                Some(SubCommand::Migrate {
+                    main,
+                    action,
+                    module,
+                })
+            }
+            Some(("run", sub_matches)) => {
+                let main = resolve_main(sub_matches.get_one::<String>("main_path"))?
+                    .ok_or_else(|| Error::ModuleNotFound("main".to_string()))?;
+                let input = sub_matches
+                    .get_one::<String>("input")
+                    .map(|s| s.to_string());
+                let jsonl = sub_matches.get_flag("jsonl");
+
+                Some(SubCommand::Run { main, input, jsonl })
+            }
+            _ => None,
         };
 
+        let main = resolve_main(matches.get_one::<String>("main_path"))?;
+
         let install = *matches.get_one::<bool>("install").unwrap_or(&false);
 
         let publish_path = matches.get_one::<String>("publish").map(|s| s.to_string());
@@ -72,10 +195,39 @@ impl Cli {
             main,
             only_download_modules: install,
             publish_path,
+            command,
         })
     }
 }
 
+/// The arguments shared by `migrate` and each of its actions, so the action
+/// can be left out: `phlow migrate <main_path>` applies the pending migrations.
+fn migrate_args(command: Command) -> Command {
+    command
+        .arg(
+            Arg::new("main_path")
+                .help("Main path/file to load")
+                .required(false)
+                .index(1),
+        )
+        .arg(
+            Arg::new("module")
+                .long("module")
+                .short('m')
+                .help("Only migrate the module with this name"),
+        )
+}
+
+fn resolve_main(main_path: Option<&String>) -> Result<Option<MainArgs>, Error> {
+    match main_path {
+        Some(file) => {
+            let (path, ext) = get_main_file(file)?;
+            Ok(Some(MainArgs { path, ext }))
+        }
+        None => Ok(find_default_file("").map(|(path, ext)| MainArgs { path, ext })),
+    }
+}
+
 #[derive(Debug)]
 pub enum ModuleExtension {
     Json,
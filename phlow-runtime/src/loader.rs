@@ -184,6 +184,9 @@ impl Loader {
         let license = value.get("license").map(|v| v.to_string());
         let repository = value.get("repository").map(|v| v.to_string());
         let homepage = value.get("homepage").map(|v| v.to_string());
+        let base_path = Path::new(main_path)
+            .parent()
+            .map(|parent| parent.to_string_lossy().to_string());
 
         let app_data = ApplicationData {
             name,
@@ -194,6 +197,7 @@ impl Loader {
             license,
             repository,
             homepage,
+            base_path,
         };
 
         Ok(Self {
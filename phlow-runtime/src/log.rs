@@ -16,3 +16,14 @@ pub fn init_tracing() {
         .with(fmt::layer().with_filter(LevelFilter::from_level(get_log_level())))
         .init()
 }
+
+/// Logs to stderr, leaving stdout to the output of the command.
+pub fn init_tracing_stderr() {
+    Registry::default()
+        .with(
+            fmt::layer()
+                .with_writer(std::io::stderr)
+                .with_filter(LevelFilter::from_level(get_log_level())),
+        )
+        .init()
+}
@@ -2,13 +2,15 @@ mod cli;
 mod loader;
 mod log;
 mod memory;
+mod migrate;
 mod publish;
+mod run;
 mod runtime;
 mod settings;
 mod yaml;
-use cli::Cli;
+use cli::{Cli, SubCommand};
 use loader::Loader;
-use log::init_tracing;
+use log::{init_tracing, init_tracing_stderr};
 use phlow_sdk::otel::init_tracing_subscriber;
 use phlow_sdk::prelude::*;
 use phlow_sdk::tracing::error;
@@ -21,6 +23,98 @@ async fn main() {
     let settings = Settings::load();
     let cli = Cli::load().expect("Error loading CLI");
 
+    if let Some(SubCommand::OpenApi { main, output }) = &cli.command {
+        let loader = match Loader::load(&main.path, &main.ext) {
+            Ok(main) => main,
+            Err(err) => {
+                eprintln!("Runtime Error Main File: {:?}", err);
+                std::process::exit(1);
+            }
+        };
+
+        let routes = usize::try_from(loader.main)
+            .ok()
+            .and_then(|index| loader.modules.get(index))
+            .and_then(|module| module.with.get("routes").cloned())
+            .unwrap_or(Value::Null);
+
+        let document = phlow_sdk::openapi::build_document(
+            &loader.app_data,
+            &routes,
+            &phlow_sdk::openapi::load_module_specs(std::path::Path::new(
+                phlow_sdk::openapi::MODULES_DIR,
+            )),
+        )
+        .to_json(JsonMode::Indented);
+
+        match output {
+            Some(output) => {
+                if let Err(err) = std::fs::write(output, document) {
+                    eprintln!("Error writing OpenAPI document: {:?}", err);
+                    std::process::exit(1);
+                }
+            }
+            None => println!("{}", document),
+        }
+
+        return;
+    }
+
+    if let Some(SubCommand::Migrate {
+        main,
+        action,
+        module,
+    }) = cli.command
+    {
+        init_tracing_stderr();
+
+        let loader = match Loader::load(&main.path, &main.ext) {
+            Ok(main) => main,
+            Err(err) => {
+                eprintln!("Runtime Error Main File: {:?}", err);
+                std::process::exit(1);
+            }
+        };
+
+        loader
+            .download(&settings.default_package_repository_url)
+            .await
+            .expect("Error downloading modules");
+
+        if let Err(err) = migrate::run(loader, action, module).await {
+            eprintln!("Migration Error: {}", err);
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
+    if let Some(SubCommand::Run { main, input, jsonl }) = cli.command {
+        init_tracing_stderr();
+
+        let loader = match Loader::load(&main.path, &main.ext) {
+            Ok(main) => main,
+            Err(err) => {
+                eprintln!("Runtime Error Main File: {:?}", err);
+                std::process::exit(1);
+            }
+        };
+
+        loader
+            .download(&settings.default_package_repository_url)
+            .await
+            .expect("Error downloading modules");
+
+        // Modules may keep threads running, so exit instead of returning.
+        match run::run(loader, input, jsonl).await {
+            Ok(()) => std::process::exit(0),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+    }
+
     if let Some(publish_path) = cli.publish_path {
         init_tracing();
 
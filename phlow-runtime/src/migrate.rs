@@ -0,0 +1,107 @@
+use crate::cli::MigrateAction;
+use crate::loader::Loader;
+use phlow_sdk::context::Context;
+use phlow_sdk::otel::TracePropagation;
+use phlow_sdk::prelude::*;
+use phlow_sdk::tracing::dispatcher;
+use std::collections::HashMap;
+use tokio::sync::oneshot;
+
+impl MigrateAction {
+    fn to_input(&self) -> Value {
+        let (action, steps) = match self {
+            MigrateAction::Up => ("up", None),
+            MigrateAction::List => ("list", None),
+            MigrateAction::Rollback { steps } => ("rollback", Some(*steps)),
+        };
+
+        let mut input = HashMap::from([("migrations", action.to_value())]);
+
+        if let Some(steps) = steps {
+            input.insert("steps", steps.to_value());
+        }
+
+        input.to_value()
+    }
+}
+
+/// Loads only the modules that declare `with.migrations`, without the main
+/// module, and asks each one to run the migrations action.
+pub async fn run(
+    loader: Loader,
+    action: MigrateAction,
+    module: Option<String>,
+) -> Result<(), String> {
+    let dispatch = dispatcher::get_default(|dispatch| dispatch.clone());
+    let health = Health::default();
+    let context = Context::new().add_module_input(action.to_input());
+
+    let targets: Vec<_> = loader
+        .modules
+        .into_iter()
+        .enumerate()
+        .filter(|(_, target)| target.with.is_object() && target.with.get("migrations").is_some())
+        .filter(|(_, target)| module.as_ref().is_none_or(|name| &target.name == name))
+        .collect();
+
+    if targets.is_empty() {
+        return Err(match module {
+            Some(name) => format!("Module {} has no migrations", name),
+            None => "No module has migrations".to_string(),
+        });
+    }
+
+    for (id, target) in targets {
+        let (setup_sender, setup_receive) =
+            oneshot::channel::<Option<channel::Sender<ModulePackage>>>();
+
+        // The command runs the action itself, so the module must not migrate on setup.
+        let mut with = target.with.clone();
+        with.insert("auto_migrate", false);
+
+        let setup = ModuleSetup {
+            id,
+            setup_sender,
+            main_sender: None,
+            with,
+            dispatch: dispatch.clone(),
+            app_data: loader.app_data.clone(),
+            health: health.module(&target.name),
+            trace_propagation: TracePropagation::default(),
+        };
+
+        let module_target = target.module.clone();
+
+        std::thread::spawn(move || {
+            if let Err(err) = Loader::load_module(setup, &module_target) {
+                error!("Runtime Error Load Module: {:?}", err)
+            }
+        });
+
+        let sender = match setup_receive.await {
+            Ok(Some(sender)) => sender,
+            _ => return Err(format!("Module {} failed to start", target.name)),
+        };
+
+        let mut modules = Modules::default();
+        modules.register(&target.name, sender);
+
+        let response = modules
+            .execute(&target.name, &context)
+            .await
+            .map_err(|e| format!("{:?}", e))?;
+
+        if let Some(error) = response.error {
+            return Err(format!("{}: {}", target.name, error));
+        }
+
+        println!(
+            "{}",
+            HashMap::from([(target.name, response.data)])
+                .to_value()
+                .to_json(JsonMode::Indented)
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,95 @@
+use crate::loader::Loader;
+use crate::runtime::Runtime;
+use phlow_engine::{Context, Phlow};
+use phlow_sdk::prelude::*;
+use phlow_sdk::tracing::dispatcher;
+use std::io::{BufRead, IsTerminal, Read, Write};
+use std::sync::Arc;
+
+/// Where the input of `phlow run` comes from: a file, or stdin when the path
+/// is missing or `-`.
+fn reader(input: Option<&str>) -> Result<Box<dyn BufRead>, String> {
+    match input {
+        None | Some("-") => Ok(Box::new(std::io::stdin().lock())),
+        Some(path) => std::fs::File::open(path)
+            .map(|file| Box::new(std::io::BufReader::new(file)) as Box<dyn BufRead>)
+            .map_err(|e| format!("Error opening input {}: {}", path, e)),
+    }
+}
+
+fn parse(input: &str) -> Result<Value, String> {
+    match input.trim() {
+        "" => Ok(Value::Null),
+        input => Value::json_to_value(input).map_err(|e| format!("Invalid input: {:?}", e)),
+    }
+}
+
+async fn execute(flow: &Phlow, input: Value) -> Result<Value, String> {
+    let mut context = Context::from_main(input);
+
+    flow.execute(&mut context)
+        .await
+        .map(|result| result.unwrap_or(Value::Null))
+        .map_err(|e| format!("Runtime Error Execute Steps: {:?}", e))
+}
+
+/// Runs the flow without a main module: once with the whole input, or once
+/// per line with `jsonl`, printing each result to stdout. Without an input
+/// file and with nothing piped in, main is null.
+pub async fn run(loader: Loader, input: Option<String>, jsonl: bool) -> Result<(), String> {
+    let dispatch = dispatcher::get_default(|dispatch| dispatch.clone());
+    let steps = loader.get_steps();
+    let modules = Runtime::load_modules(loader, dispatch, &Health::default(), None).await?;
+    let flow = Phlow::try_from_value(&steps, Some(Arc::new(modules)))
+        .map_err(|e| format!("Runtime Error To Value: {:?}", e))?;
+
+    let interactive = input.is_none() && std::io::stdin().is_terminal();
+    let mut reader = match interactive {
+        true => Box::new(std::io::empty()),
+        false => reader(input.as_deref())?,
+    };
+
+    if !jsonl {
+        let mut content = String::new();
+        reader
+            .read_to_string(&mut content)
+            .map_err(|e| format!("Error reading input: {}", e))?;
+
+        let result = execute(&flow, parse(&content)?).await?;
+        println!("{}", result.to_json(JsonMode::Indented));
+
+        return Ok(());
+    }
+
+    let mut failed = 0;
+    let mut stdout = std::io::stdout();
+
+    for (number, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| format!("Error reading input: {}", e))?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = match parse(&line) {
+            Ok(input) => execute(&flow, input).await,
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(result) => {
+                let _ = writeln!(stdout, "{}", result.to_json(JsonMode::Inline));
+                let _ = stdout.flush();
+            }
+            Err(e) => {
+                eprintln!("Line {}: {}", number + 1, e);
+                failed += 1;
+            }
+        }
+    }
+
+    match failed {
+        0 => Ok(()),
+        failed => Err(format!("{} lines failed", failed)),
+    }
+}
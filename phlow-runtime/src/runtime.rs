@@ -4,6 +4,7 @@ use crate::settings::Settings;
 use crossbeam::channel;
 use futures::future::join_all;
 use phlow_engine::{Context, Phlow};
+use phlow_sdk::otel::TracePropagation;
 use phlow_sdk::prelude::*;
 use phlow_sdk::tracing::{debug, dispatcher, error, info};
 use std::{sync::Arc, thread};
@@ -12,28 +13,28 @@ use tokio::sync::oneshot;
 pub struct Runtime {}
 
 impl Runtime {
-    pub async fn run(loader: Loader, dispatch: Dispatch, settings: Settings) {
-        let steps: Value = loader.get_steps();
+    /// Loads the modules of the main file and waits for each one to register.
+    /// Only the main module, if any, gets `main_sender`.
+    pub async fn load_modules(
+        loader: Loader,
+        dispatch: Dispatch,
+        health: &Health,
+        main_sender: Option<channel::Sender<Package>>,
+    ) -> Result<Modules, String> {
         let mut modules = Modules::default();
 
-        // -------------------------
-        // Create the channels
-        // -------------------------
-        let (tx_main_package, rx_main_package) = channel::unbounded::<Package>();
-
-        // -------------------------
-        // Load the modules
-        // -------------------------
         for (id, module) in loader.modules.into_iter().enumerate() {
             let (setup_sender, setup_receive) =
                 oneshot::channel::<Option<channel::Sender<ModulePackage>>>();
 
             let main_sender = if loader.main == id as i32 {
-                Some(tx_main_package.clone())
+                main_sender.clone()
             } else {
                 None
             };
 
+            let module_health = health.module(&module.name);
+
             let setup = ModuleSetup {
                 id,
                 setup_sender,
@@ -41,6 +42,8 @@ impl Runtime {
                 with: module.with.clone(),
                 dispatch: dispatch.clone(),
                 app_data: loader.app_data.clone(),
+                health: module_health.clone(),
+                trace_propagation: TracePropagation::default(),
             };
 
             let module_target = module.module.clone();
@@ -56,7 +59,14 @@ impl Runtime {
                 module.module, module.name
             );
 
-            match setup_receive.await {
+            let setup_result = setup_receive.await;
+
+            // Modules that report their own health keep it, the others are up once loaded.
+            if module_health.status() == HealthStatus::Starting {
+                module_health.up();
+            }
+
+            match setup_result {
                 Ok(Some(sender)) => {
                     debug!("Module {} registered", module.name);
                     modules.register(&module.name, sender);
@@ -65,18 +75,39 @@ impl Runtime {
                     debug!("Module {} did not register", module.name);
                 }
                 Err(err) => {
-                    error!("Runtime Error Setup Receive: {:?}", err);
-                    return;
+                    return Err(format!("Runtime Error Setup Receive: {:?}", err));
                 }
             }
         }
 
+        Ok(modules)
+    }
+
+    pub async fn run(loader: Loader, dispatch: Dispatch, settings: Settings) {
+        let steps: Value = loader.get_steps();
+        let health = Health::default();
+
         if loader.main == -1 {
             error!("Runtime Error Main Module: No main module found");
             return;
         }
 
-        drop(tx_main_package);
+        // -------------------------
+        // Create the channels
+        // -------------------------
+        let (tx_main_package, rx_main_package) = channel::unbounded::<Package>();
+
+        // -------------------------
+        // Load the modules
+        // -------------------------
+        let modules =
+            match Self::load_modules(loader, dispatch, &health, Some(tx_main_package)).await {
+                Ok(modules) => modules,
+                Err(err) => {
+                    error!("{}", err);
+                    return;
+                }
+            };
 
         #[cfg(target_os = "linux")]
         if settings.garbage_collection {
@@ -103,6 +134,8 @@ impl Runtime {
 
         let mut handles = Vec::new();
 
+        health.set_ready(true);
+
         info!("Phlow!");
 
         for _i in 0..settings.package_consumer_count {
@@ -0,0 +1,211 @@
+use crate::metrics::PrometheusMetrics;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::{Debug, Formatter},
+    future::Future,
+    sync::{Arc, OnceLock, RwLock},
+    time::Duration,
+};
+use valu3::prelude::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthStatus {
+    Starting,
+    Up,
+    Down(String),
+}
+
+impl HealthStatus {
+    pub fn is_up(&self) -> bool {
+        matches!(self, HealthStatus::Up)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            HealthStatus::Starting => "starting",
+            HealthStatus::Up => "up",
+            HealthStatus::Down(_) => "down",
+        }
+    }
+}
+
+impl ToValueBehavior for HealthStatus {
+    fn to_value(&self) -> Value {
+        let mut status = HashMap::from([("status", self.name().to_value())]);
+
+        if let HealthStatus::Down(reason) = self {
+            status.insert("reason", reason.to_value());
+        }
+
+        status.to_value()
+    }
+}
+
+#[derive(Default)]
+struct HealthState {
+    ready: bool,
+    modules: BTreeMap<String, HealthStatus>,
+}
+
+/// Health of the application, shared by the runtime and every module.
+/// The runtime marks it ready once all modules are loaded and the flow is built.
+#[derive(Clone, Default)]
+pub struct Health {
+    state: Arc<RwLock<HealthState>>,
+    metrics: Arc<OnceLock<Option<PrometheusMetrics>>>,
+}
+
+impl Debug for Health {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Health({})", self.report().to_json(JsonMode::Inline))
+    }
+}
+
+impl Health {
+    /// Registers a module as `starting` and returns the handle it reports through.
+    pub fn module(&self, name: &str) -> ModuleHealth {
+        self.set(name, HealthStatus::Starting);
+
+        ModuleHealth {
+            name: name.to_string(),
+            health: self.clone(),
+        }
+    }
+
+    /// Prometheus registry shared by every module, created on first use.
+    /// Modules register their instruments here and http_server exposes them.
+    pub fn metrics(&self) -> Option<PrometheusMetrics> {
+        self.metrics
+            .get_or_init(|| match PrometheusMetrics::new() {
+                Ok(metrics) => Some(metrics),
+                Err(e) => {
+                    tracing::error!("Failed to create Prometheus exporter: {:?}", e);
+                    None
+                }
+            })
+            .clone()
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        if let Ok(mut state) = self.state.write() {
+            state.ready = ready;
+        }
+    }
+
+    fn set(&self, name: &str, status: HealthStatus) {
+        if let Ok(mut state) = self.state.write() {
+            state.modules.insert(name.to_string(), status);
+        }
+    }
+
+    pub fn status(&self, name: &str) -> Option<HealthStatus> {
+        self.state
+            .read()
+            .ok()
+            .and_then(|state| state.modules.get(name).cloned())
+    }
+
+    pub fn modules(&self) -> BTreeMap<String, HealthStatus> {
+        self.state
+            .read()
+            .map(|state| state.modules.clone())
+            .unwrap_or_default()
+    }
+
+    /// Ready when the runtime has started and every module is up.
+    pub fn is_ready(&self) -> bool {
+        match self.state.read() {
+            Ok(state) => state.ready && state.modules.values().all(HealthStatus::is_up),
+            Err(_) => false,
+        }
+    }
+
+    pub fn report(&self) -> Value {
+        let modules: HashMap<String, Value> = self
+            .modules()
+            .into_iter()
+            .map(|(name, status)| (name, status.to_value()))
+            .collect();
+        let status = if self.is_ready() { "up" } else { "down" };
+
+        HashMap::from([
+            ("status", status.to_value()),
+            ("modules", modules.to_value()),
+        ])
+        .to_value()
+    }
+}
+
+/// Health-check hook given to each module through `ModuleSetup`.
+#[derive(Clone, Debug)]
+pub struct ModuleHealth {
+    name: String,
+    health: Health,
+}
+
+impl ModuleHealth {
+    pub fn up(&self) {
+        self.health.set(&self.name, HealthStatus::Up);
+    }
+
+    pub fn down(&self, reason: impl Into<String>) {
+        self.health
+            .set(&self.name, HealthStatus::Down(reason.into()));
+    }
+
+    pub fn status(&self) -> HealthStatus {
+        self.health
+            .status(&self.name)
+            .unwrap_or(HealthStatus::Starting)
+    }
+
+    /// Application-wide health, for modules that expose it (e.g. http_server).
+    pub fn registry(&self) -> Health {
+        self.health.clone()
+    }
+
+    /// Runs `check` on the module's runtime every `interval` and reports its result.
+    pub fn check_every<F, Fut>(&self, interval: Duration, check: F)
+    where
+        F: Fn() -> Fut + Send + 'static,
+        Fut: Future<Output = HealthStatus> + Send,
+    {
+        let health = self.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let status = check().await;
+                health.health.set(&health.name, status);
+                tokio::time::sleep(interval).await;
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_health_readiness() {
+        let health = Health::default();
+        let postgres = health.module("postgres");
+        let http = health.module("http_server");
+
+        assert!(!health.is_ready());
+
+        postgres.up();
+        http.up();
+        assert!(!health.is_ready());
+
+        health.set_ready(true);
+        assert!(health.is_ready());
+
+        postgres.down("connection refused");
+        assert!(!health.is_ready());
+        assert_eq!(
+            health.status("postgres"),
+            Some(HealthStatus::Down("connection refused".to_string()))
+        );
+    }
+}
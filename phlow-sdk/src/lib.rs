@@ -1,8 +1,11 @@
 pub mod context;
 pub mod count;
 pub mod ext;
+pub mod health;
 pub mod id;
 pub mod macros;
+pub mod metrics;
+pub mod openapi;
 pub mod otel;
 pub mod prelude;
 pub mod structs;
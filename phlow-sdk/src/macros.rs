@@ -85,6 +85,11 @@ macro_rules! create_step {
     ($handler:ident(setup)) => {
         #[no_mangle]
         pub extern "C" fn plugin(setup: $crate::structs::ModuleSetup) {
+            // Step modules report to the runtime's subscriber, so their spans join the flow's trace.
+            if $crate::tracing::dispatcher::set_global_default(setup.dispatch.clone()).is_ok() {
+                $crate::tracing_core::callsite::rebuild_interest_cache();
+            }
+
             if let Ok(rt) = $crate::tokio::runtime::Runtime::new() {
                 if let Err(e) = rt.block_on($handler(setup)) {
                     $crate::tracing::error!("Error in plugin: {:?}", e);
@@ -99,6 +104,11 @@ macro_rules! create_step {
     ($handler:ident(rx)) => {
         #[no_mangle]
         pub extern "C" fn plugin(setup: $crate::structs::ModuleSetup) {
+            // Step modules report to the runtime's subscriber, so their spans join the flow's trace.
+            if $crate::tracing::dispatcher::set_global_default(setup.dispatch.clone()).is_ok() {
+                $crate::tracing_core::callsite::rebuild_interest_cache();
+            }
+
             if let Ok(rt) = $crate::tokio::runtime::Runtime::new() {
                 let rx = module_channel!(setup);
 
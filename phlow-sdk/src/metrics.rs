@@ -0,0 +1,63 @@
+use opentelemetry::metrics::{Meter, MeterProvider};
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use prometheus::{Encoder, Registry, TextEncoder};
+
+/// Meter provider backed by a Prometheus registry, for modules that expose
+/// a `/metrics` endpoint.
+#[derive(Clone)]
+pub struct PrometheusMetrics {
+    registry: Registry,
+    provider: SdkMeterProvider,
+}
+
+impl std::fmt::Debug for PrometheusMetrics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PrometheusMetrics").finish()
+    }
+}
+
+impl PrometheusMetrics {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
+        let registry = Registry::new();
+        let exporter = opentelemetry_prometheus::exporter()
+            .with_registry(registry.clone())
+            .build()?;
+        let provider = SdkMeterProvider::builder().with_reader(exporter).build();
+
+        Ok(Self { registry, provider })
+    }
+
+    pub fn meter(&self, name: &'static str) -> Meter {
+        self.provider.meter(name)
+    }
+
+    /// Current metrics in the Prometheus text exposition format.
+    pub fn encode(&self) -> String {
+        let mut buffer = Vec::new();
+
+        if let Err(err) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::debug!("Error encoding metrics: {:?}", err);
+        }
+
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use opentelemetry::KeyValue;
+
+    #[test]
+    fn test_encode_metrics() {
+        let metrics = PrometheusMetrics::new().unwrap();
+        let counter = metrics.meter("phlow").u64_counter("requests").build();
+
+        counter.add(2, &[KeyValue::new("route", "/students")]);
+
+        let text = metrics.encode();
+
+        assert!(text.contains("requests_total"));
+        assert!(text.contains("route=\"/students\""));
+    }
+}
@@ -0,0 +1,489 @@
+use crate::structs::ApplicationData;
+use std::collections::BTreeMap;
+use std::path::Path;
+use valu3::prelude::*;
+
+pub const MODULES_DIR: &str = "phlow_modules";
+
+/// The operations of an OpenAPI path item.
+const METHODS: [&str; 8] = [
+    "get", "put", "post", "delete", "options", "head", "patch", "trace",
+];
+
+/// Module metadata (`phlow.yaml`) found in the modules directory, keyed by module name.
+pub fn load_module_specs(modules_dir: &Path) -> BTreeMap<String, Value> {
+    let mut specs = BTreeMap::new();
+
+    let entries = match std::fs::read_dir(modules_dir) {
+        Ok(entries) => entries,
+        Err(_) => return specs,
+    };
+
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        if !dir.is_dir() {
+            continue;
+        }
+
+        let spec = ["phlow.yaml", "phlow.yml", "phlow.json"]
+            .iter()
+            .map(|file| dir.join(file))
+            .find(|path| path.exists())
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .and_then(|content| serde_yaml::from_str::<Value>(&content).ok());
+
+        if let Some(spec) = spec {
+            if !spec.is_object() {
+                continue;
+            }
+
+            let name = match spec.get("name") {
+                Some(name) => name.to_string(),
+                None => entry.file_name().to_string_lossy().to_string(),
+            };
+
+            specs.insert(name, spec);
+        }
+    }
+
+    specs
+}
+
+/// Converts a phlow property map (`name: { type, description, required, ... }`),
+/// as used by `with`/`input`/`output` in `phlow.yaml`, into a JSON Schema object.
+pub fn properties_to_schema(properties: &Value) -> Value {
+    let mut schema_properties = BTreeMap::new();
+    let mut required = Vec::new();
+
+    let mut add = |name: String, property: &Value| {
+        if let Some(Value::Boolean(true)) = property.get("required") {
+            required.push(name.to_value());
+        }
+        schema_properties.insert(name, property_to_schema(property));
+    };
+
+    match properties {
+        Value::Object(object) => {
+            for (key, property) in object.iter() {
+                if property.is_object() {
+                    add(key.to_string(), property);
+                }
+            }
+        }
+        Value::Array(array) => {
+            for property in array.into_iter() {
+                if property.is_object() {
+                    if let Some(name) = property.get("name") {
+                        add(name.to_string(), property);
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+
+    let mut schema = BTreeMap::from([
+        ("type".to_string(), "object".to_value()),
+        ("properties".to_string(), schema_properties.to_value()),
+    ]);
+
+    if !required.is_empty() {
+        schema.insert("required".to_string(), required.to_value());
+    }
+
+    schema.to_value()
+}
+
+fn property_to_schema(property: &Value) -> Value {
+    let mut schema = BTreeMap::new();
+
+    match property.get("type").map(|t| t.to_string()).as_deref() {
+        Some("any") | None => {}
+        Some(kind) => {
+            schema.insert("type".to_string(), kind.to_value());
+        }
+    }
+
+    for key in ["description", "default", "enum", "format"] {
+        if let Some(value) = property.get(key) {
+            schema.insert(key.to_string(), value.clone());
+        }
+    }
+
+    if let Some(properties) = property.get("properties") {
+        if let Value::Object(nested) = properties_to_schema(properties) {
+            for (key, value) in nested.iter() {
+                schema.insert(key.to_string(), value.clone());
+            }
+        }
+    }
+
+    schema.to_value()
+}
+
+/// Turns `/users/{id}` style templates into the list of path parameter names.
+pub fn path_parameters(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter_map(|segment| {
+            segment
+                .strip_prefix('{')
+                .and_then(|s| s.strip_suffix('}'))
+                .map(|s| s.to_string())
+        })
+        .collect()
+}
+
+fn schema_content(schema: &Value) -> Value {
+    BTreeMap::from([(
+        "application/json",
+        BTreeMap::from([("schema", schema.clone())]).to_value(),
+    )])
+    .to_value()
+}
+
+/// Whether a body schema accepts `null`, which is what a missing body is
+/// validated as, so that the body is optional.
+fn accepts_null(schema: &Value) -> bool {
+    if !schema.is_object() || matches!(schema.get("nullable"), Some(Value::Boolean(true))) {
+        return true;
+    }
+
+    let branches = |key: &str| match schema.get(key) {
+        Some(Value::Array(branches)) => Some(branches.into_iter().cloned().collect::<Vec<_>>()),
+        _ => None,
+    };
+
+    let accepts = match schema.get("type") {
+        Some(Value::Array(types)) => types.into_iter().any(|kind| kind.to_string() == "null"),
+        Some(kind) => kind.to_string() == "null",
+        None => true,
+    };
+
+    accepts
+        && branches("enum").is_none_or(|values| values.contains(&Value::Null))
+        && schema.get("const").is_none_or(|value| value.is_null())
+        && branches("allOf").is_none_or(|all| all.iter().all(accepts_null))
+        && branches("anyOf").is_none_or(|any| any.iter().any(accepts_null))
+        && branches("oneOf").is_none_or(|one| one.iter().any(accepts_null))
+}
+
+fn route_operation(route: &Value, path: &str) -> Value {
+    let mut operation = BTreeMap::new();
+
+    for (key, target) in [
+        ("summary", "summary"),
+        ("description", "description"),
+        ("tags", "tags"),
+        ("operation_id", "operationId"),
+    ] {
+        if let Some(value) = route.get(key) {
+            operation.insert(target.to_string(), value.clone());
+        }
+    }
+
+    let mut parameters: Vec<Value> = path_parameters(path)
+        .into_iter()
+        .map(|name| {
+            BTreeMap::from([
+                ("name", name.to_value()),
+                ("in", "path".to_value()),
+                ("required", true.to_value()),
+                ("schema", BTreeMap::from([("type", "string")]).to_value()),
+            ])
+            .to_value()
+        })
+        .collect();
+
+    if let Some(Value::Object(query)) = route.get("query") {
+        for (name, property) in query.iter() {
+            let required = matches!(property.get("required"), Some(Value::Boolean(true)));
+            let mut parameter = BTreeMap::from([
+                ("name", name.to_string().to_value()),
+                ("in", "query".to_value()),
+                ("required", required.to_value()),
+                ("schema", property_to_schema(property)),
+            ]);
+
+            if let Some(description) = property.get("description") {
+                parameter.insert("description", description.clone());
+            }
+
+            parameters.push(parameter.to_value());
+        }
+    }
+
+    if !parameters.is_empty() {
+        operation.insert("parameters".to_string(), parameters.to_value());
+    }
+
+    if let Some(schema) = route.get("schema") {
+        operation.insert(
+            "requestBody".to_string(),
+            BTreeMap::from([
+                ("required", (!accepts_null(schema)).to_value()),
+                ("content", schema_content(schema)),
+            ])
+            .to_value(),
+        );
+    }
+
+    let mut responses = BTreeMap::new();
+
+    if let Some(Value::Object(declared)) = route.get("responses") {
+        for (status, response) in declared.iter() {
+            let mut entry = BTreeMap::new();
+            let description = response
+                .get("description")
+                .map(|d| d.to_string())
+                .unwrap_or_else(|| format!("Response {}", status));
+
+            entry.insert("description", description.to_value());
+
+            if let Some(schema) = response.get("schema") {
+                entry.insert("content", schema_content(schema));
+            }
+
+            responses.insert(status.to_string(), entry.to_value());
+        }
+    }
+
+    if responses.is_empty() {
+        responses.insert(
+            "default".to_string(),
+            BTreeMap::from([("description", "Flow response")]).to_value(),
+        );
+    }
+
+    operation.insert("responses".to_string(), responses.to_value());
+
+    operation.to_value()
+}
+
+/// Builds an OpenAPI 3 document from the declared routes of the main module and the
+/// `input` schemas of the available modules (exposed under `components.schemas`).
+pub fn build_document(
+    app_data: &ApplicationData,
+    routes: &Value,
+    modules: &BTreeMap<String, Value>,
+) -> Value {
+    let mut info = BTreeMap::from([
+        (
+            "title",
+            app_data
+                .name
+                .clone()
+                .unwrap_or_else(|| "phlow".to_string())
+                .to_value(),
+        ),
+        (
+            "version",
+            app_data
+                .version
+                .clone()
+                .unwrap_or_else(|| "0.0.0".to_string())
+                .to_value(),
+        ),
+    ]);
+
+    if let Some(description) = &app_data.description {
+        info.insert("description", description.to_value());
+    }
+
+    if let Some(license) = &app_data.license {
+        info.insert(
+            "license",
+            BTreeMap::from([("name", license.to_string())]).to_value(),
+        );
+    }
+
+    let mut paths: BTreeMap<String, BTreeMap<String, Value>> = BTreeMap::new();
+
+    if let Value::Array(routes) = routes {
+        for route in routes.into_iter() {
+            if !route.is_object() {
+                continue;
+            }
+
+            let path = match route.get("path") {
+                Some(path) => path.to_string(),
+                None => continue,
+            };
+
+            let operation = route_operation(route, &path);
+            let operations = paths.entry(path.clone()).or_default();
+
+            // A route without a method is served for every method, and the
+            // last route declared for a path wins, as in the router.
+            match route.get("method") {
+                Some(method) => {
+                    operations.insert(method.to_string().to_lowercase(), operation);
+                }
+                None => {
+                    for method in METHODS {
+                        let mut operation = operation.clone();
+
+                        if let Some(id) = operation.get("operationId").map(Value::to_string) {
+                            operation.insert("operationId", format!("{}_{}", id, method));
+                        }
+
+                        operations.insert(method.to_string(), operation);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut schemas = BTreeMap::new();
+
+    for (name, spec) in modules.iter() {
+        if let Some(input) = spec.get("input").or_else(|| spec.get("inputs")) {
+            schemas.insert(format!("{}.input", name), properties_to_schema(input));
+        }
+    }
+
+    BTreeMap::from([
+        ("openapi", "3.0.3".to_value()),
+        ("info", info.to_value()),
+        ("paths", paths.to_value()),
+        (
+            "components",
+            BTreeMap::from([("schemas", schemas.to_value())]).to_value(),
+        ),
+    ])
+    .to_value()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn app_data() -> ApplicationData {
+        ApplicationData {
+            name: Some("students".to_string()),
+            version: Some("1.0.0".to_string()),
+            environment: None,
+            description: None,
+            author: None,
+            license: None,
+            repository: None,
+            homepage: None,
+            base_path: None,
+        }
+    }
+
+    #[test]
+    fn test_properties_to_schema() {
+        let properties = Value::json_to_value(
+            r#"{"query": {"type": "string", "required": true}, "params": {"type": "array"}, "value": {"type": "any"}}"#,
+        )
+        .unwrap();
+
+        let schema = properties_to_schema(&properties);
+
+        assert_eq!(schema.get("type"), Some(&"object".to_value()));
+        assert_eq!(schema.get("required"), Some(&vec!["query"].to_value()));
+        assert_eq!(
+            schema
+                .get("properties")
+                .unwrap()
+                .get("params")
+                .unwrap()
+                .get("type"),
+            Some(&"array".to_value())
+        );
+        assert_eq!(
+            schema
+                .get("properties")
+                .unwrap()
+                .get("value")
+                .unwrap()
+                .get("type"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_build_document() {
+        let routes = Value::json_to_value(
+            r#"[
+                {"path": "/students/{id}", "method": "GET", "summary": "Get student",
+                 "responses": {"200": {"description": "Student", "schema": {"type": "object"}}}},
+                {"path": "/students", "method": "POST", "schema": {"type": "object"}}
+            ]"#,
+        )
+        .unwrap();
+        let modules = BTreeMap::from([(
+            "postgres".to_string(),
+            Value::json_to_value(r#"{"inputs": {"query": {"type": "string"}}}"#).unwrap(),
+        )]);
+
+        let document = build_document(&app_data(), &routes, &modules);
+        let paths = document.get("paths").unwrap();
+        let get = paths.get("/students/{id}").unwrap().get("get").unwrap();
+        let post = paths.get("/students").unwrap().get("post").unwrap();
+
+        assert_eq!(document.get("openapi"), Some(&"3.0.3".to_value()));
+        assert_eq!(get.get("summary"), Some(&"Get student".to_value()));
+        assert_eq!(
+            get.get("parameters").unwrap().get(0).unwrap().get("name"),
+            Some(&"id".to_value())
+        );
+        assert!(post.get("requestBody").is_some());
+        assert!(post.get("responses").unwrap().get("default").is_some());
+        assert!(document
+            .get("components")
+            .unwrap()
+            .get("schemas")
+            .unwrap()
+            .get("postgres.input")
+            .is_some());
+    }
+
+    #[test]
+    fn test_build_document_any_method() {
+        let routes = Value::json_to_value(
+            r#"[
+                {"path": "/hooks", "operation_id": "hook", "schema": {"type": ["object", "null"]}},
+                {"path": "/hooks", "method": "POST", "schema": {"type": "object"}}
+            ]"#,
+        )
+        .unwrap();
+
+        let document = build_document(&app_data(), &routes, &BTreeMap::new());
+        let hooks = document.get("paths").unwrap().get("/hooks").unwrap();
+        let required = |method: &str| {
+            hooks
+                .get(method)
+                .unwrap()
+                .get("requestBody")
+                .unwrap()
+                .get("required")
+                .cloned()
+        };
+
+        for method in METHODS {
+            assert!(hooks.get(method).is_some(), "{}", method);
+        }
+        assert_eq!(
+            hooks.get("put").unwrap().get("operationId"),
+            Some(&"hook_put".to_value())
+        );
+        assert_eq!(required("put"), Some(false.to_value()));
+        assert_eq!(required("post"), Some(true.to_value()));
+    }
+
+    #[test]
+    fn test_accepts_null() {
+        let accepts = |json: &str| accepts_null(&Value::json_to_value(json).unwrap());
+
+        assert!(accepts(r#"{}"#));
+        assert!(accepts(r#"{"type": "null"}"#));
+        assert!(accepts(r#"{"type": "object", "nullable": true}"#));
+        assert!(accepts(
+            r#"{"anyOf": [{"type": "object"}, {"type": "null"}]}"#
+        ));
+        assert!(!accepts(r#"{"type": "object"}"#));
+        assert!(!accepts(r#"{"enum": ["a", "b"]}"#));
+        assert!(!accepts(r#"{"allOf": [{}, {"type": "string"}]}"#));
+    }
+}
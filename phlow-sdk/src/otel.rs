@@ -1,13 +1,15 @@
-use std::env;
+use std::{collections::HashMap, env};
 
 use opentelemetry::{
     global::{self, BoxedTracer},
+    propagation::TextMapPropagator,
     trace::TracerProvider,
     KeyValue,
 };
 use opentelemetry_otlp::ExporterBuildError;
 use opentelemetry_sdk::{
     metrics::{MeterProviderBuilder, PeriodicReader, SdkMeterProvider},
+    propagation::TraceContextPropagator,
     trace::{RandomIdGenerator, Sampler, SdkTracerProvider},
     Resource,
 };
@@ -20,6 +22,7 @@ use tracing::{dispatcher, Dispatch};
 use tracing_core::LevelFilter;
 use tracing_opentelemetry::MetricsLayer;
 use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
 use tracing_subscriber::fmt;
 use tracing_subscriber::prelude::*;
 use tracing_subscriber::Registry;
@@ -129,6 +132,47 @@ fn init_tracer_provider(resource: Resource) -> Result<SdkTracerProvider, Exporte
         .build())
 }
 
+/// W3C trace context (`traceparent`/`tracestate`) of the span, to be sent as
+/// headers on outgoing requests. Empty when OpenTelemetry is not active.
+pub fn inject_trace_context(span: &tracing::Span) -> HashMap<String, String> {
+    let mut headers = HashMap::new();
+
+    TraceContextPropagator::new().inject_context(&span.context(), &mut headers);
+    headers.retain(|_, value| !value.is_empty());
+
+    headers
+}
+
+/// Sets the remote parent of the span from incoming W3C trace context headers.
+/// Header names must be lowercase.
+pub fn extract_trace_context(span: &tracing::Span, headers: &HashMap<String, String>) {
+    if !headers.contains_key("traceparent") {
+        return;
+    }
+
+    let context = TraceContextPropagator::new().extract(headers);
+    span.set_parent(context);
+}
+
+/// Trace context propagation as compiled into the runtime. Modules are loaded as
+/// separate libraries with their own copy of `tracing-opentelemetry`, which cannot
+/// read the OpenTelemetry data of spans recorded by the runtime's subscriber, so
+/// they propagate through these functions instead.
+#[derive(Debug, Clone, Copy)]
+pub struct TracePropagation {
+    pub inject: fn(&tracing::Span) -> HashMap<String, String>,
+    pub extract: fn(&tracing::Span, &HashMap<String, String>),
+}
+
+impl Default for TracePropagation {
+    fn default() -> Self {
+        Self {
+            inject: inject_trace_context,
+            extract: extract_trace_context,
+        }
+    }
+}
+
 pub fn get_log_level() -> Level {
     *PHLOW_LOG
 }
@@ -218,3 +262,31 @@ impl Drop for OtelGuard {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_context_round_trip() {
+        let provider = SdkTracerProvider::builder().build();
+        let subscriber = Registry::default().with(OpenTelemetryLayer::new(provider.tracer("test")));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let incoming = HashMap::from([(
+                "traceparent".to_string(),
+                "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01".to_string(),
+            )]);
+
+            let server = tracing::info_span!("server");
+            extract_trace_context(&server, &incoming);
+
+            let client = tracing::info_span!(parent: &server, "client");
+            let outgoing = inject_trace_context(&client);
+            let traceparent = outgoing.get("traceparent").unwrap();
+
+            assert!(traceparent.starts_with("00-4bf92f3577b34da6a3ce929d0e0e4736-"));
+            assert!(!traceparent.contains("00f067aa0ba902b7"));
+        });
+    }
+}
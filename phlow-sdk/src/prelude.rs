@@ -1,3 +1,4 @@
+pub use crate::health::{Health, HealthStatus, ModuleHealth};
 pub use crate::structs::*;
 pub use crate::timer::Timer;
 pub use crate::{
@@ -1,4 +1,6 @@
 pub mod modules;
+use crate::health::ModuleHealth;
+use crate::otel::TracePropagation;
 use crate::sender_safe;
 use crossbeam::channel::{self, Receiver};
 pub use modules::*;
@@ -22,6 +24,9 @@ pub struct ApplicationData {
     pub license: Option<String>,
     pub repository: Option<String>,
     pub homepage: Option<String>,
+    /// Directory of the main file, which relative paths in `with` are
+    /// resolved against.
+    pub base_path: Option<String>,
 }
 
 #[derive(Debug)]
@@ -32,6 +37,8 @@ pub struct ModuleSetup {
     pub with: Value,
     pub dispatch: tracing::Dispatch,
     pub app_data: ApplicationData,
+    pub health: ModuleHealth,
+    pub trace_propagation: TracePropagation,
 }
 
 impl ModuleSetup {
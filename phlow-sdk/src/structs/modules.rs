@@ -46,6 +46,9 @@ impl ModuleResponse {
 pub struct ModulePackage {
     pub context: Context,
     pub sender: oneshot::Sender<ModuleResponse>,
+    /// Span of the step that called the module, to parent module spans.
+    pub span: Option<tracing::Span>,
+    pub dispatch: Option<tracing::Dispatch>,
 }
 
 impl ModulePackage {
@@ -80,6 +83,10 @@ impl Modules {
             let package = ModulePackage {
                 context: context.clone(),
                 sender: package_sender,
+                span: Some(tracing::Span::current()),
+                dispatch: Some(tracing::dispatcher::get_default(|dispatch| {
+                    dispatch.clone()
+                })),
             };
 
             let _ = module_sender.send(package);